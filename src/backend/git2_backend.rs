@@ -0,0 +1,84 @@
+use super::GitBackend;
+use crate::{
+    CommitOptions, add_files_to_git_repo_index, commit_index_to_git_repo, config_git_repo_user,
+    lookup_entry_from_git_repo_commit_tree_by_path, open_or_init_git_repo,
+    read_git_repo_blob_content, reset_git_repo_head, switch_git_repo_branch,
+    upsert_branch_to_git_repo, upsert_tag_to_git_repo,
+};
+
+// 基于 libgit2 的后端，直接复用 crate 根模块中的函数
+pub struct Git2Backend {
+    repo: git2::Repository,
+}
+
+impl Git2Backend {
+    pub fn repository(&self) -> &git2::Repository {
+        &self.repo
+    }
+}
+
+impl GitBackend for Git2Backend {
+    type ObjectId = git2::Oid;
+
+    fn open_or_init(dir: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Git2Backend {
+            repo: open_or_init_git_repo(dir)?,
+        })
+    }
+
+    fn config_user(&mut self, name: &str, email: &str) -> Result<(), Box<dyn std::error::Error>> {
+        config_git_repo_user(&mut self.repo, name, email)?;
+        Ok(())
+    }
+
+    fn add(&mut self, file_relative_paths: &[&str]) -> Result<(), Box<dyn std::error::Error>> {
+        add_files_to_git_repo_index(&mut self.repo, file_relative_paths.to_vec())?;
+        Ok(())
+    }
+
+    fn commit(&mut self, message: &str) -> Result<git2::Oid, Box<dyn std::error::Error>> {
+        let index = self.repo.index()?;
+        commit_index_to_git_repo(&mut self.repo, index, message, &CommitOptions::default())
+    }
+
+    fn branch(
+        &mut self,
+        branch_name: &str,
+        target: Option<git2::Oid>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        upsert_branch_to_git_repo(&mut self.repo, branch_name, target)?;
+        Ok(())
+    }
+
+    fn tag(
+        &mut self,
+        tag_name: &str,
+        message: &str,
+        target: Option<git2::Oid>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        upsert_tag_to_git_repo(&mut self.repo, tag_name, message, target)?;
+        Ok(())
+    }
+
+    fn checkout(&mut self, branch_name: &str) -> Result<(), Box<dyn std::error::Error>> {
+        switch_git_repo_branch(&mut self.repo, branch_name, true)?;
+        Ok(())
+    }
+
+    fn reset_hard(&mut self, target: git2::Oid) -> Result<(), Box<dyn std::error::Error>> {
+        reset_git_repo_head(&mut self.repo, target)
+    }
+
+    fn lookup_path(
+        &self,
+        commit: Option<git2::Oid>,
+        path: &str,
+    ) -> Result<Option<git2::Oid>, Box<dyn std::error::Error>> {
+        let entry = lookup_entry_from_git_repo_commit_tree_by_path(&self.repo, commit, path)?;
+        Ok(entry.map(|entry| entry.oid))
+    }
+
+    fn read_blob(&self, blob: git2::Oid) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        read_git_repo_blob_content(&self.repo, blob)
+    }
+}
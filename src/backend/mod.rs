@@ -0,0 +1,103 @@
+// Git 操作的后端抽象：上层只依赖 GitBackend，底层可以是 libgit2（git2）或其他实现
+// 目前只有 git2 实现；gitoxide 后端预留了 gix feature，实现时在本目录下添加 gix_backend.rs，
+// 为 gix feature 加上 gix 可选依赖，并把下面的 compile_error 换成对应的 mod / pub use
+mod git2_backend;
+
+pub use git2_backend::Git2Backend;
+
+#[cfg(feature = "gix")]
+compile_error!("gix 后端尚未实现，暂时不能启用 gix feature");
+
+pub trait GitBackend: Sized {
+    // 后端使用的对象 id 类型
+    type ObjectId: Copy + Eq + std::fmt::Debug + std::fmt::Display;
+
+    // git init / 打开已存在的仓库
+    fn open_or_init(dir: &str) -> Result<Self, Box<dyn std::error::Error>>;
+
+    // git config user.name / user.email
+    fn config_user(&mut self, name: &str, email: &str) -> Result<(), Box<dyn std::error::Error>>;
+
+    // git add，磁盘上不存在的文件被跳过
+    fn add(&mut self, file_relative_paths: &[&str]) -> Result<(), Box<dyn std::error::Error>>;
+
+    // git commit，index 与 HEAD 没有差异时返回 HEAD
+    fn commit(&mut self, message: &str) -> Result<Self::ObjectId, Box<dyn std::error::Error>>;
+
+    // git branch -f，target 为 None 时使用 HEAD
+    fn branch(
+        &mut self,
+        branch_name: &str,
+        target: Option<Self::ObjectId>,
+    ) -> Result<(), Box<dyn std::error::Error>>;
+
+    // git tag -f -a，target 为 None 时使用 HEAD
+    fn tag(
+        &mut self,
+        tag_name: &str,
+        message: &str,
+        target: Option<Self::ObjectId>,
+    ) -> Result<(), Box<dyn std::error::Error>>;
+
+    // git checkout <branch>，同时更新工作目录
+    fn checkout(&mut self, branch_name: &str) -> Result<(), Box<dyn std::error::Error>>;
+
+    // git reset --hard <commit>
+    fn reset_hard(&mut self, target: Self::ObjectId) -> Result<(), Box<dyn std::error::Error>>;
+
+    // 在 commit（None 时为 HEAD）的 tree 中查找路径，返回对应对象的 id
+    fn lookup_path(
+        &self,
+        commit: Option<Self::ObjectId>,
+        path: &str,
+    ) -> Result<Option<Self::ObjectId>, Box<dyn std::error::Error>>;
+
+    // 读取 blob 的原始内容
+    fn read_blob(&self, blob: Self::ObjectId) -> Result<Vec<u8>, Box<dyn std::error::Error>>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::clean_test_dir;
+    use std::fs;
+
+    // 与具体后端无关的操作流程，每个后端都应得到相同的结果
+    fn exercise_backend<B: GitBackend>(dir: &std::path::Path) {
+        let mut backend = B::open_or_init(dir.to_str().unwrap()).unwrap();
+        backend.config_user("TestUser", "test@example.com").unwrap();
+
+        fs::write(dir.join("a.txt"), "v1").unwrap();
+        backend.add(&["a.txt"]).unwrap();
+        let first = backend.commit("first").unwrap();
+        backend.tag("v1", "release v1", None).unwrap();
+        backend.branch("release", Some(first)).unwrap();
+
+        fs::write(dir.join("a.txt"), "v2").unwrap();
+        fs::write(dir.join("b.txt"), "b").unwrap();
+        backend.add(&["a.txt", "b.txt"]).unwrap();
+        let second = backend.commit("second").unwrap();
+        assert_ne!(first, second);
+
+        let blob = backend.lookup_path(None, "a.txt").unwrap().unwrap();
+        assert_eq!(backend.read_blob(blob).unwrap(), b"v2");
+        let blob = backend.lookup_path(Some(first), "a.txt").unwrap().unwrap();
+        assert_eq!(backend.read_blob(blob).unwrap(), b"v1");
+        assert!(backend.lookup_path(Some(first), "b.txt").unwrap().is_none());
+
+        backend.checkout("release").unwrap();
+        assert_eq!(fs::read_to_string(dir.join("a.txt")).unwrap(), "v1");
+        assert!(!dir.join("b.txt").exists());
+
+        backend.checkout("main").unwrap();
+        backend.reset_hard(first).unwrap();
+        assert_eq!(fs::read_to_string(dir.join("a.txt")).unwrap(), "v1");
+    }
+
+    #[test]
+    fn test_git2_backend() {
+        let dir = clean_test_dir("git2_backend");
+        exercise_backend::<Git2Backend>(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -2,7 +2,8 @@ use crate::{
     add_files_to_git_repo_index, commit_index_to_git_repo, config_git_repo_user,
     lookup_entry_from_git_repo_commit_tree_by_path, read_git_repo_blob_content,
     upsert_tag_to_git_repo, upsert_branch_to_git_repo, switch_git_repo_branch, open_or_init_git_repo,
-    reset_git_repo_head, clean_git_repo_index, traverse_git_repo_commit_tree_recorder, restore_git_repo_head_to_workdir
+    clean_git_repo_index, traverse_git_repo_commit_tree_recorder, restore_git_repo_head_to_workdir,
+    reset_git_repo_head_with_mode, ResetMode, CommitOptions, commit_tree_snapshot,
 };
 use std::fs;
 use std::path::Path;
@@ -27,15 +28,42 @@ fn generate_random_file_content() -> String {
 
 // 创建测试文件
 fn create_test_file(
-    repo_dir: &str,
-    filename: &str,
+    repo_dir: impl AsRef<Path>,
+    filename: impl AsRef<Path>,
     content: &str,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let file_path = Path::new(repo_dir).join(filename);
+    let file_path = repo_dir.as_ref().join(filename);
     fs::write(file_path, content)?;
     Ok(())
 }
 
+// 删除测试仓库目录：先释放仓库句柄，删除失败时短暂等待后重试
+// （Windows 上仓库文件句柄未释放时 remove_dir_all 会偶发失败）
+fn remove_repo_dir(
+    repo: Option<git2::Repository>,
+    path: impl AsRef<Path>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    drop(repo);
+
+    let path = path.as_ref();
+    let max_attempts = 5;
+    let mut attempt = 1;
+    loop {
+        if !path.exists() {
+            return Ok(());
+        }
+        match fs::remove_dir_all(path) {
+            Ok(()) => return Ok(()),
+            Err(e) if attempt < max_attempts => {
+                eprintln!("删除目录 {:?} 失败，将重试: {}", path, e);
+                std::thread::sleep(Duration::from_millis(50 * attempt));
+                attempt += 1;
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 #[allow(dead_code)]
 pub struct BenchmarkResult {
@@ -97,32 +125,61 @@ impl BenchmarkResult {
     }
 
     pub fn print_summary(&self) {
-        println!("\n=== 性能测试结果 ===");
-        println!("总运行次数: {}", self.total_runs);
-        println!("成功次数: {}", self.successful_runs);
-        println!("失败次数: {}", self.failed_runs);
-        println!(
-            "平均耗时: {:.2}ms",
-            self.avg_duration.as_secs_f64() * 1000.0
-        );
-        println!(
-            "PCT50 耗时: {:.2}ms",
-            self.pct50_duration.as_secs_f64() * 1000.0
-        );
-        println!(
-            "PCT90 耗时: {:.2}ms",
-            self.pct90_duration.as_secs_f64() * 1000.0
-        );
-        println!(
-            "PCT95 耗时: {:.2}ms",
-            self.pct95_duration.as_secs_f64() * 1000.0
-        );
+        self.print_summary_with_unit(DurationUnit::auto(self.avg_duration));
+    }
+
+    pub fn print_summary_with_unit(&self, unit: DurationUnit) {
+        print!("{}", self.format_summary(unit));
+    }
+
+    pub fn format_summary(&self, unit: DurationUnit) -> String {
+        use std::fmt::Write;
+
+        let mut summary = String::new();
+        writeln!(summary, "\n=== 性能测试结果 ===").unwrap();
+        writeln!(summary, "总运行次数: {}", self.total_runs).unwrap();
+        writeln!(summary, "成功次数: {}", self.successful_runs).unwrap();
+        writeln!(summary, "失败次数: {}", self.failed_runs).unwrap();
+        writeln!(summary, "平均耗时: {}", unit.format(self.avg_duration)).unwrap();
+        writeln!(summary, "PCT50 耗时: {}", unit.format(self.pct50_duration)).unwrap();
+        writeln!(summary, "PCT90 耗时: {}", unit.format(self.pct90_duration)).unwrap();
+        writeln!(summary, "PCT95 耗时: {}", unit.format(self.pct95_duration)).unwrap();
 
         if !self.durations.is_empty() {
             let min_duration = self.durations.first().unwrap();
             let max_duration = self.durations.last().unwrap();
-            println!("最小耗时: {:.2}ms", min_duration.as_secs_f64() * 1000.0);
-            println!("最大耗时: {:.2}ms", max_duration.as_secs_f64() * 1000.0);
+            writeln!(summary, "最小耗时: {}", unit.format(*min_duration)).unwrap();
+            writeln!(summary, "最大耗时: {}", unit.format(*max_duration)).unwrap();
+        }
+        summary
+    }
+}
+
+// 耗时的展示单位，亚微秒级的操作用毫秒展示会变成 0.00ms
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DurationUnit {
+    Ns,
+    Us,
+    Ms,
+}
+
+impl DurationUnit {
+    // 根据耗时的量级自动选择单位
+    pub fn auto(duration: Duration) -> Self {
+        if duration < Duration::from_micros(1) {
+            DurationUnit::Ns
+        } else if duration < Duration::from_millis(1) {
+            DurationUnit::Us
+        } else {
+            DurationUnit::Ms
+        }
+    }
+
+    pub fn format(&self, duration: Duration) -> String {
+        match self {
+            DurationUnit::Ns => format!("{}ns", duration.as_nanos()),
+            DurationUnit::Us => format!("{:.2}us", duration.as_secs_f64() * 1_000_000.0),
+            DurationUnit::Ms => format!("{:.2}ms", duration.as_secs_f64() * 1000.0),
         }
     }
 }
@@ -141,9 +198,7 @@ fn benchmark_open_or_init_git_repo_new_scenario(iterations: usize) -> BenchmarkR
         let test_dir = format!("{}_{}_{}", base_dir, i, std::process::id());
 
         // 确保目录不存在（新建场景）
-        if Path::new(&test_dir).exists() {
-            let _ = std::fs::remove_dir_all(&test_dir);
-        }
+        let _ = remove_repo_dir(None, &test_dir);
 
         // 开始计时
         let start = Instant::now();
@@ -164,9 +219,7 @@ fn benchmark_open_or_init_git_repo_new_scenario(iterations: usize) -> BenchmarkR
         }
 
         // 清理测试目录
-        if Path::new(&test_dir).exists() {
-            let _ = std::fs::remove_dir_all(&test_dir);
-        }
+        let _ = remove_repo_dir(None, &test_dir);
     }
 
     BenchmarkResult::new(durations)
@@ -184,9 +237,7 @@ fn benchmark_lookup_and_read_git_repo_blob() -> BenchmarkResult {
         let test_dir = format!("{}_{}_{}", base_dir, i, std::process::id());
 
         // 确保目录不存在
-        if Path::new(&test_dir).exists() {
-            let _ = std::fs::remove_dir_all(&test_dir);
-        }
+        let _ = remove_repo_dir(None, &test_dir);
 
         // 创建新的 Git 仓库
         let mut repo = match open_or_init_git_repo(&test_dir) {
@@ -200,7 +251,7 @@ fn benchmark_lookup_and_read_git_repo_blob() -> BenchmarkResult {
         // 配置用户信息
         if let Err(e) = config_git_repo_user(&mut repo, "Test User", "test@example.com") {
             eprintln!("第 {} 次测试配置用户失败: {}", i + 1, e);
-            let _ = std::fs::remove_dir_all(&test_dir);
+            let _ = remove_repo_dir(Some(repo), &test_dir);
             continue;
         }
 
@@ -210,26 +261,31 @@ fn benchmark_lookup_and_read_git_repo_blob() -> BenchmarkResult {
             Ok(files) => files,
             Err(e) => {
                 eprintln!("第 {} 次测试创建嵌套文件失败: {}", i + 1, e);
-                let _ = std::fs::remove_dir_all(&test_dir);
+                let _ = remove_repo_dir(Some(repo), &test_dir);
                 continue;
             }
         };
 
         let nested_file_refs: Vec<&str> = nested_files.iter().map(|s| s.as_str()).collect();
         let index1 = match add_files_to_git_repo_index(&mut repo, nested_file_refs) {
-            Ok(index) => index,
+            Ok((index, _)) => index,
             Err(e) => {
                 eprintln!("第 {} 次测试添加嵌套文件到 index 失败: {}", i + 1, e);
-                let _ = std::fs::remove_dir_all(&test_dir);
+                let _ = remove_repo_dir(Some(repo), &test_dir);
                 continue;
             }
         };
 
-        let commit_oid = match commit_index_to_git_repo(&mut repo, index1, "Add nested files") {
+        let commit_oid = match commit_index_to_git_repo(
+            &mut repo,
+            index1,
+            "Add nested files",
+            &CommitOptions::default(),
+        ) {
             Ok(commit_id) => commit_id,
             Err(e) => {
                 eprintln!("第 {} 次测试提交嵌套文件失败: {}", i + 1, e);
-                let _ = std::fs::remove_dir_all(&test_dir);
+                let _ = remove_repo_dir(Some(repo), &test_dir);
                 continue;
             }
         };
@@ -245,7 +301,7 @@ fn benchmark_lookup_and_read_git_repo_blob() -> BenchmarkResult {
             Ok(entry) => entry,
             Err(e) => {
                 eprintln!("第 {} 次测试查找文件 entry 失败: {}", i + 1, e);
-                let _ = std::fs::remove_dir_all(&test_dir);
+                let _ = remove_repo_dir(Some(repo), &test_dir);
                 continue;
             }
         };
@@ -254,7 +310,7 @@ fn benchmark_lookup_and_read_git_repo_blob() -> BenchmarkResult {
             Some(entry) => entry,
             None => {
                 eprintln!("第 {} 次测试未找到文件 entry: {}", i + 1, deepest_file_path);
-                let _ = std::fs::remove_dir_all(&test_dir);
+                let _ = remove_repo_dir(Some(repo), &test_dir);
                 continue;
             }
         };
@@ -275,9 +331,7 @@ fn benchmark_lookup_and_read_git_repo_blob() -> BenchmarkResult {
         }
 
         // 清理测试目录
-        if Path::new(&test_dir).exists() {
-            let _ = std::fs::remove_dir_all(&test_dir);
-        }
+        let _ = remove_repo_dir(Some(repo), &test_dir);
     }
 
     BenchmarkResult::new(durations)
@@ -294,9 +348,7 @@ fn benchmark_open_or_init_git_repo_existing_scenario(iterations: usize) -> Bench
     let test_dir = format!("bench_existing_repo_{}", std::process::id());
 
     // 预先创建一个 Git 仓库
-    if Path::new(&test_dir).exists() {
-        let _ = std::fs::remove_dir_all(&test_dir);
-    }
+    let _ = remove_repo_dir(None, &test_dir);
 
     // 创建测试仓库
     match open_or_init_git_repo(&test_dir) {
@@ -328,9 +380,7 @@ fn benchmark_open_or_init_git_repo_existing_scenario(iterations: usize) -> Bench
     }
 
     // 清理测试目录
-    if Path::new(&test_dir).exists() {
-        let _ = std::fs::remove_dir_all(&test_dir);
-    }
+    let _ = remove_repo_dir(None, &test_dir);
 
     BenchmarkResult::new(durations)
 }
@@ -346,9 +396,7 @@ fn benchmark_config_git_repo_user(iterations: usize) -> BenchmarkResult {
     let test_dir = format!("bench_config_repo_{}", std::process::id());
 
     // 预先创建一个 Git 仓库
-    if Path::new(&test_dir).exists() {
-        let _ = std::fs::remove_dir_all(&test_dir);
-    }
+    let _ = remove_repo_dir(None, &test_dir);
 
     let mut repo = match open_or_init_git_repo(&test_dir) {
         Ok(repo) => {
@@ -385,9 +433,7 @@ fn benchmark_config_git_repo_user(iterations: usize) -> BenchmarkResult {
     }
 
     // 清理测试目录
-    if Path::new(&test_dir).exists() {
-        let _ = std::fs::remove_dir_all(&test_dir);
-    }
+    let _ = remove_repo_dir(Some(repo), &test_dir);
 
     BenchmarkResult::new(durations)
 }
@@ -407,9 +453,7 @@ fn benchmark_add_single_file_empty_repo(iterations: usize) -> BenchmarkResult {
         let test_dir = format!("{}_{}_{}", base_dir, i, std::process::id());
 
         // 确保目录不存在
-        if Path::new(&test_dir).exists() {
-            let _ = std::fs::remove_dir_all(&test_dir);
-        }
+        let _ = remove_repo_dir(None, &test_dir);
 
         // 创建新的 Git 仓库
         let mut repo = match open_or_init_git_repo(&test_dir) {
@@ -423,7 +467,7 @@ fn benchmark_add_single_file_empty_repo(iterations: usize) -> BenchmarkResult {
         // 配置用户信息
         if let Err(e) = config_git_repo_user(&mut repo, "Test User", "test@example.com") {
             eprintln!("第 {} 次测试配置用户失败: {}", i + 1, e);
-            let _ = std::fs::remove_dir_all(&test_dir);
+            let _ = remove_repo_dir(Some(repo), &test_dir);
             continue;
         }
 
@@ -431,7 +475,7 @@ fn benchmark_add_single_file_empty_repo(iterations: usize) -> BenchmarkResult {
         let content = generate_random_file_content();
         if let Err(e) = create_test_file(&test_dir, "test_file.txt", &content) {
             eprintln!("第 {} 次测试创建文件失败: {}", i + 1, e);
-            let _ = std::fs::remove_dir_all(&test_dir);
+            let _ = remove_repo_dir(Some(repo), &test_dir);
             continue;
         }
 
@@ -454,9 +498,7 @@ fn benchmark_add_single_file_empty_repo(iterations: usize) -> BenchmarkResult {
         }
 
         // 清理测试目录
-        if Path::new(&test_dir).exists() {
-            let _ = std::fs::remove_dir_all(&test_dir);
-        }
+        let _ = remove_repo_dir(Some(repo), &test_dir);
     }
 
     BenchmarkResult::new(durations)
@@ -477,9 +519,7 @@ fn benchmark_add_single_file_existing_repo(iterations: usize) -> BenchmarkResult
         let test_dir = format!("{}_{}_{}", base_dir, i, std::process::id());
 
         // 确保目录不存在
-        if Path::new(&test_dir).exists() {
-            let _ = std::fs::remove_dir_all(&test_dir);
-        }
+        let _ = remove_repo_dir(None, &test_dir);
 
         // 创建新的 Git 仓库
         let mut repo = match open_or_init_git_repo(&test_dir) {
@@ -493,7 +533,7 @@ fn benchmark_add_single_file_existing_repo(iterations: usize) -> BenchmarkResult
         // 配置用户信息
         if let Err(e) = config_git_repo_user(&mut repo, "Test User", "test@example.com") {
             eprintln!("第 {} 次测试配置用户失败: {}", i + 1, e);
-            let _ = std::fs::remove_dir_all(&test_dir);
+            let _ = remove_repo_dir(Some(repo), &test_dir);
             continue;
         }
 
@@ -510,26 +550,30 @@ fn benchmark_add_single_file_existing_repo(iterations: usize) -> BenchmarkResult
         }
 
         if initial_files.len() != 10 {
-            let _ = std::fs::remove_dir_all(&test_dir);
+            let _ = remove_repo_dir(Some(repo), &test_dir);
             continue;
         }
 
         // 添加初始文件到索引
         let initial_file_refs: Vec<&str> = initial_files.iter().map(|s| s.as_str()).collect();
         let index = match add_files_to_git_repo_index(&mut repo, initial_file_refs) {
-            Ok(index) => index,
+            Ok((index, _)) => index,
             Err(e) => {
                 eprintln!("第 {} 次测试添加初始文件到索引失败: {}", i + 1, e);
-                let _ = std::fs::remove_dir_all(&test_dir);
+                let _ = remove_repo_dir(Some(repo), &test_dir);
                 continue;
             }
         };
 
         // 提交初始文件
-        if let Err(e) = commit_index_to_git_repo(&mut repo, index, "Initial commit with 10 files")
-        {
+        if let Err(e) = commit_index_to_git_repo(
+            &mut repo,
+            index,
+            "Initial commit with 10 files",
+            &CommitOptions::default(),
+        ) {
             eprintln!("第 {} 次测试提交初始文件失败: {}", i + 1, e);
-            let _ = std::fs::remove_dir_all(&test_dir);
+            let _ = remove_repo_dir(Some(repo), &test_dir);
             continue;
         }
 
@@ -537,7 +581,7 @@ fn benchmark_add_single_file_existing_repo(iterations: usize) -> BenchmarkResult
         let content = generate_random_file_content();
         if let Err(e) = create_test_file(&test_dir, "new_file.txt", &content) {
             eprintln!("第 {} 次测试创建新文件失败: {}", i + 1, e);
-            let _ = std::fs::remove_dir_all(&test_dir);
+            let _ = remove_repo_dir(Some(repo), &test_dir);
             continue;
         }
 
@@ -560,9 +604,7 @@ fn benchmark_add_single_file_existing_repo(iterations: usize) -> BenchmarkResult
         }
 
         // 清理测试目录
-        if Path::new(&test_dir).exists() {
-            let _ = std::fs::remove_dir_all(&test_dir);
-        }
+        let _ = remove_repo_dir(Some(repo), &test_dir);
     }
 
     BenchmarkResult::new(durations)
@@ -583,9 +625,7 @@ fn benchmark_modify_single_file_existing_repo(iterations: usize) -> BenchmarkRes
         let test_dir = format!("{}_{}_{}", base_dir, i, std::process::id());
 
         // 确保目录不存在
-        if Path::new(&test_dir).exists() {
-            let _ = std::fs::remove_dir_all(&test_dir);
-        }
+        let _ = remove_repo_dir(None, &test_dir);
 
         // 创建新的 Git 仓库
         let mut repo = match open_or_init_git_repo(&test_dir) {
@@ -599,7 +639,7 @@ fn benchmark_modify_single_file_existing_repo(iterations: usize) -> BenchmarkRes
         // 配置用户信息
         if let Err(e) = config_git_repo_user(&mut repo, "Test User", "test@example.com") {
             eprintln!("第 {} 次测试配置用户失败: {}", i + 1, e);
-            let _ = std::fs::remove_dir_all(&test_dir);
+            let _ = remove_repo_dir(Some(repo), &test_dir);
             continue;
         }
 
@@ -616,26 +656,30 @@ fn benchmark_modify_single_file_existing_repo(iterations: usize) -> BenchmarkRes
         }
 
         if initial_files.len() != 10 {
-            let _ = std::fs::remove_dir_all(&test_dir);
+            let _ = remove_repo_dir(Some(repo), &test_dir);
             continue;
         }
 
         // 添加初始文件到索引
         let initial_file_refs: Vec<&str> = initial_files.iter().map(|s| s.as_str()).collect();
         let index = match add_files_to_git_repo_index(&mut repo, initial_file_refs) {
-            Ok(index) => index,
+            Ok((index, _)) => index,
             Err(e) => {
                 eprintln!("第 {} 次测试添加初始文件到索引失败: {}", i + 1, e);
-                let _ = std::fs::remove_dir_all(&test_dir);
+                let _ = remove_repo_dir(Some(repo), &test_dir);
                 continue;
             }
         };
 
         // 提交初始文件
-        if let Err(e) = commit_index_to_git_repo(&mut repo, index, "Initial commit with 10 files")
-        {
+        if let Err(e) = commit_index_to_git_repo(
+            &mut repo,
+            index,
+            "Initial commit with 10 files",
+            &CommitOptions::default(),
+        ) {
             eprintln!("第 {} 次测试提交初始文件失败: {}", i + 1, e);
-            let _ = std::fs::remove_dir_all(&test_dir);
+            let _ = remove_repo_dir(Some(repo), &test_dir);
             continue;
         }
 
@@ -643,7 +687,7 @@ fn benchmark_modify_single_file_existing_repo(iterations: usize) -> BenchmarkRes
         let modified_content = generate_random_file_content();
         if let Err(e) = create_test_file(&test_dir, "initial_file_0.txt", &modified_content) {
             eprintln!("第 {} 次测试修改文件失败: {}", i + 1, e);
-            let _ = std::fs::remove_dir_all(&test_dir);
+            let _ = remove_repo_dir(Some(repo), &test_dir);
             continue;
         }
 
@@ -666,9 +710,7 @@ fn benchmark_modify_single_file_existing_repo(iterations: usize) -> BenchmarkRes
         }
 
         // 清理测试目录
-        if Path::new(&test_dir).exists() {
-            let _ = std::fs::remove_dir_all(&test_dir);
-        }
+        let _ = remove_repo_dir(Some(repo), &test_dir);
     }
 
     BenchmarkResult::new(durations)
@@ -689,9 +731,7 @@ fn benchmark_commit_single_file_empty_repo(iterations: usize) -> BenchmarkResult
         let test_dir = format!("{}_{}_{}", base_dir, i, std::process::id());
 
         // 确保目录不存在
-        if Path::new(&test_dir).exists() {
-            let _ = std::fs::remove_dir_all(&test_dir);
-        }
+        let _ = remove_repo_dir(None, &test_dir);
 
         // 创建新的 Git 仓库
         let mut repo = match open_or_init_git_repo(&test_dir) {
@@ -705,7 +745,7 @@ fn benchmark_commit_single_file_empty_repo(iterations: usize) -> BenchmarkResult
         // 配置用户信息
         if let Err(e) = config_git_repo_user(&mut repo, "Test User", "test@example.com") {
             eprintln!("第 {} 次测试配置用户失败: {}", i + 1, e);
-            let _ = std::fs::remove_dir_all(&test_dir);
+            let _ = remove_repo_dir(Some(repo), &test_dir);
             continue;
         }
 
@@ -713,15 +753,15 @@ fn benchmark_commit_single_file_empty_repo(iterations: usize) -> BenchmarkResult
         let content = generate_random_file_content();
         if let Err(e) = create_test_file(&test_dir, "test_file.txt", &content) {
             eprintln!("第 {} 次测试创建文件失败: {}", i + 1, e);
-            let _ = std::fs::remove_dir_all(&test_dir);
+            let _ = remove_repo_dir(Some(repo), &test_dir);
             continue;
         }
 
         let index = match add_files_to_git_repo_index(&mut repo, vec!["test_file.txt"]) {
-            Ok(index) => index,
+            Ok((index, _)) => index,
             Err(e) => {
                 eprintln!("第 {} 次测试添加文件到索引失败: {}", i + 1, e);
-                let _ = std::fs::remove_dir_all(&test_dir);
+                let _ = remove_repo_dir(Some(repo), &test_dir);
                 continue;
             }
         };
@@ -730,7 +770,12 @@ fn benchmark_commit_single_file_empty_repo(iterations: usize) -> BenchmarkResult
         let start = Instant::now();
 
         // 执行被测试的函数（提交索引）
-        match commit_index_to_git_repo(&mut repo, index, "Add single file to empty repo") {
+        match commit_index_to_git_repo(
+            &mut repo,
+            index,
+            "Add single file to empty repo",
+            &CommitOptions::default(),
+        ) {
             Ok(_) => {
                 let duration = start.elapsed();
                 durations.push(duration);
@@ -745,9 +790,7 @@ fn benchmark_commit_single_file_empty_repo(iterations: usize) -> BenchmarkResult
         }
 
         // 清理测试目录
-        if Path::new(&test_dir).exists() {
-            let _ = std::fs::remove_dir_all(&test_dir);
-        }
+        let _ = remove_repo_dir(Some(repo), &test_dir);
     }
 
     BenchmarkResult::new(durations)
@@ -768,9 +811,7 @@ fn benchmark_commit_new_file_existing_repo(iterations: usize) -> BenchmarkResult
         let test_dir = format!("{}_{}_{}", base_dir, i, std::process::id());
 
         // 确保目录不存在
-        if Path::new(&test_dir).exists() {
-            let _ = std::fs::remove_dir_all(&test_dir);
-        }
+        let _ = remove_repo_dir(None, &test_dir);
 
         // 创建新的 Git 仓库
         let mut repo = match open_or_init_git_repo(&test_dir) {
@@ -784,7 +825,7 @@ fn benchmark_commit_new_file_existing_repo(iterations: usize) -> BenchmarkResult
         // 配置用户信息
         if let Err(e) = config_git_repo_user(&mut repo, "Test User", "test@example.com") {
             eprintln!("第 {} 次测试配置用户失败: {}", i + 1, e);
-            let _ = std::fs::remove_dir_all(&test_dir);
+            let _ = remove_repo_dir(Some(repo), &test_dir);
             continue;
         }
 
@@ -801,26 +842,29 @@ fn benchmark_commit_new_file_existing_repo(iterations: usize) -> BenchmarkResult
         }
 
         if initial_files.len() != 10 {
-            let _ = std::fs::remove_dir_all(&test_dir);
+            let _ = remove_repo_dir(Some(repo), &test_dir);
             continue;
         }
 
         // 添加初始文件到索引并提交
         let initial_file_refs: Vec<&str> = initial_files.iter().map(|s| s.as_str()).collect();
         let initial_index = match add_files_to_git_repo_index(&mut repo, initial_file_refs) {
-            Ok(index) => index,
+            Ok((index, _)) => index,
             Err(e) => {
                 eprintln!("第 {} 次测试添加初始文件到索引失败: {}", i + 1, e);
-                let _ = std::fs::remove_dir_all(&test_dir);
+                let _ = remove_repo_dir(Some(repo), &test_dir);
                 continue;
             }
         };
 
-        if let Err(e) =
-            commit_index_to_git_repo(&mut repo, initial_index, "Initial commit with 10 files")
-        {
+        if let Err(e) = commit_index_to_git_repo(
+            &mut repo,
+            initial_index,
+            "Initial commit with 10 files",
+            &CommitOptions::default(),
+        ) {
             eprintln!("第 {} 次测试提交初始文件失败: {}", i + 1, e);
-            let _ = std::fs::remove_dir_all(&test_dir);
+            let _ = remove_repo_dir(Some(repo), &test_dir);
             continue;
         }
 
@@ -828,15 +872,15 @@ fn benchmark_commit_new_file_existing_repo(iterations: usize) -> BenchmarkResult
         let content = generate_random_file_content();
         if let Err(e) = create_test_file(&test_dir, "new_file.txt", &content) {
             eprintln!("第 {} 次测试创建新文件失败: {}", i + 1, e);
-            let _ = std::fs::remove_dir_all(&test_dir);
+            let _ = remove_repo_dir(Some(repo), &test_dir);
             continue;
         }
 
         let index = match add_files_to_git_repo_index(&mut repo, vec!["new_file.txt"]) {
-            Ok(index) => index,
+            Ok((index, _)) => index,
             Err(e) => {
                 eprintln!("第 {} 次测试添加新文件到索引失败: {}", i + 1, e);
-                let _ = std::fs::remove_dir_all(&test_dir);
+                let _ = remove_repo_dir(Some(repo), &test_dir);
                 continue;
             }
         };
@@ -845,7 +889,12 @@ fn benchmark_commit_new_file_existing_repo(iterations: usize) -> BenchmarkResult
         let start = Instant::now();
 
         // 执行被测试的函数（提交新文件）
-        match commit_index_to_git_repo(&mut repo, index, "Add new file to existing repo") {
+        match commit_index_to_git_repo(
+            &mut repo,
+            index,
+            "Add new file to existing repo",
+            &CommitOptions::default(),
+        ) {
             Ok(_) => {
                 let duration = start.elapsed();
                 durations.push(duration);
@@ -860,9 +909,7 @@ fn benchmark_commit_new_file_existing_repo(iterations: usize) -> BenchmarkResult
         }
 
         // 清理测试目录
-        if Path::new(&test_dir).exists() {
-            let _ = std::fs::remove_dir_all(&test_dir);
-        }
+        let _ = remove_repo_dir(Some(repo), &test_dir);
     }
 
     BenchmarkResult::new(durations)
@@ -883,9 +930,7 @@ fn benchmark_commit_modified_file_existing_repo(iterations: usize) -> BenchmarkR
         let test_dir = format!("{}_{}_{}", base_dir, i, std::process::id());
 
         // 确保目录不存在
-        if Path::new(&test_dir).exists() {
-            let _ = std::fs::remove_dir_all(&test_dir);
-        }
+        let _ = remove_repo_dir(None, &test_dir);
 
         // 创建新的 Git 仓库
         let mut repo = match open_or_init_git_repo(&test_dir) {
@@ -899,7 +944,7 @@ fn benchmark_commit_modified_file_existing_repo(iterations: usize) -> BenchmarkR
         // 配置用户信息
         if let Err(e) = config_git_repo_user(&mut repo, "Test User", "test@example.com") {
             eprintln!("第 {} 次测试配置用户失败: {}", i + 1, e);
-            let _ = std::fs::remove_dir_all(&test_dir);
+            let _ = remove_repo_dir(Some(repo), &test_dir);
             continue;
         }
 
@@ -916,26 +961,29 @@ fn benchmark_commit_modified_file_existing_repo(iterations: usize) -> BenchmarkR
         }
 
         if initial_files.len() != 10 {
-            let _ = std::fs::remove_dir_all(&test_dir);
+            let _ = remove_repo_dir(Some(repo), &test_dir);
             continue;
         }
 
         // 添加初始文件到索引并提交
         let initial_file_refs: Vec<&str> = initial_files.iter().map(|s| s.as_str()).collect();
         let initial_index = match add_files_to_git_repo_index(&mut repo, initial_file_refs) {
-            Ok(index) => index,
+            Ok((index, _)) => index,
             Err(e) => {
                 eprintln!("第 {} 次测试添加初始文件到索引失败: {}", i + 1, e);
-                let _ = std::fs::remove_dir_all(&test_dir);
+                let _ = remove_repo_dir(Some(repo), &test_dir);
                 continue;
             }
         };
 
-        if let Err(e) =
-            commit_index_to_git_repo(&mut repo, initial_index, "Initial commit with 10 files")
-        {
+        if let Err(e) = commit_index_to_git_repo(
+            &mut repo,
+            initial_index,
+            "Initial commit with 10 files",
+            &CommitOptions::default(),
+        ) {
             eprintln!("第 {} 次测试提交初始文件失败: {}", i + 1, e);
-            let _ = std::fs::remove_dir_all(&test_dir);
+            let _ = remove_repo_dir(Some(repo), &test_dir);
             continue;
         }
 
@@ -943,15 +991,15 @@ fn benchmark_commit_modified_file_existing_repo(iterations: usize) -> BenchmarkR
         let modified_content = generate_random_file_content();
         if let Err(e) = create_test_file(&test_dir, "initial_file_0.txt", &modified_content) {
             eprintln!("第 {} 次测试修改文件失败: {}", i + 1, e);
-            let _ = std::fs::remove_dir_all(&test_dir);
+            let _ = remove_repo_dir(Some(repo), &test_dir);
             continue;
         }
 
         let index = match add_files_to_git_repo_index(&mut repo, vec!["initial_file_0.txt"]) {
-            Ok(index) => index,
+            Ok((index, _)) => index,
             Err(e) => {
                 eprintln!("第 {} 次测试添加修改文件到索引失败: {}", i + 1, e);
-                let _ = std::fs::remove_dir_all(&test_dir);
+                let _ = remove_repo_dir(Some(repo), &test_dir);
                 continue;
             }
         };
@@ -960,7 +1008,12 @@ fn benchmark_commit_modified_file_existing_repo(iterations: usize) -> BenchmarkR
         let start = Instant::now();
 
         // 执行被测试的函数（提交修改的文件）
-        match commit_index_to_git_repo(&mut repo, index, "Modify existing file in repo") {
+        match commit_index_to_git_repo(
+            &mut repo,
+            index,
+            "Modify existing file in repo",
+            &CommitOptions::default(),
+        ) {
             Ok(_) => {
                 let duration = start.elapsed();
                 durations.push(duration);
@@ -975,9 +1028,7 @@ fn benchmark_commit_modified_file_existing_repo(iterations: usize) -> BenchmarkR
         }
 
         // 清理测试目录
-        if Path::new(&test_dir).exists() {
-            let _ = std::fs::remove_dir_all(&test_dir);
-        }
+        let _ = remove_repo_dir(Some(repo), &test_dir);
     }
 
     BenchmarkResult::new(durations)
@@ -1036,13 +1087,63 @@ fn benchmark_add_commit_multiple_files_empty_repo() -> BenchmarkResult {
         let file_paths = create_nested_test_files(repo_path).unwrap();
 
         // 开始计时：添加所有文件到索引并提交
-        let index =
-            add_files_to_git_repo_index(&mut repo, file_paths.iter().map(|s| s.as_str()).collect())
-                .unwrap();
+        let (index, _) = add_files_to_git_repo_index(&mut repo, &file_paths).unwrap();
         commit_index_to_git_repo(
             &mut repo,
             index,
             "Add and commit 10 files with nested directory structure",
+            &CommitOptions::default(),
+        )
+        .unwrap();
+
+        let duration = start.elapsed();
+        durations.push(duration);
+
+        // 清理
+        let _ = remove_repo_dir(Some(repo), &temp_dir);
+    }
+
+    BenchmarkResult::new(durations)
+}
+
+// 与 benchmark_add_commit_multiple_files_empty_repo 相同的10个多层目录文件，
+// 通过 TreeBuilder 直接构造 tree 并提交，不写工作目录也不经过 index
+#[allow(dead_code)]
+fn benchmark_commit_tree_snapshot_multiple_files_empty_repo() -> BenchmarkResult {
+    let mut durations = Vec::new();
+
+    for _ in 0..1000 {
+        let start = Instant::now();
+
+        // 创建临时目录
+        let temp_dir = std::env::temp_dir().join(format!("bench_test_{}", std::process::id()));
+        let repo_path = &temp_dir;
+
+        // 创建并配置仓库
+        let mut repo = open_or_init_git_repo(repo_path.to_str().unwrap()).unwrap();
+        config_git_repo_user(&mut repo, "Test User", "test@example.com").unwrap();
+
+        // 构造10个具有多层目录结构的文件并直接提交
+        let files = [
+            "file1.txt",
+            "dir1/file2.txt",
+            "dir1/file3.txt",
+            "dir1/subdir1/file4.txt",
+            "dir2/file5.txt",
+            "dir2/subdir2/file6.txt",
+            "dir2/subdir2/subdir3/file7.txt",
+            "dir3/subdir4/subdir5/subdir6/file8.txt",
+            "dir3/subdir4/subdir5/subdir6/subdir7/file9.txt",
+            "dir4/subdir8/subdir9/subdir10/subdir11/subdir12/file10.txt",
+        ]
+        .into_iter()
+        .map(|path| (path, generate_random_file_content(), git2::FileMode::Blob))
+        .collect();
+        commit_tree_snapshot(
+            &repo,
+            files,
+            "Add and commit 10 files with nested directory structure",
+            None,
         )
         .unwrap();
 
@@ -1050,8 +1151,7 @@ fn benchmark_add_commit_multiple_files_empty_repo() -> BenchmarkResult {
         durations.push(duration);
 
         // 清理
-        drop(repo);
-        let _ = std::fs::remove_dir_all(&temp_dir);
+        let _ = remove_repo_dir(Some(repo), &temp_dir);
     }
 
     BenchmarkResult::new(durations)
@@ -1075,9 +1175,15 @@ fn benchmark_create_tag_empty_repo() -> BenchmarkResult {
         create_test_file(repo_path.to_str().unwrap(), "test_file.txt", &generate_random_file_content()).unwrap();
         
         // 添加文件到索引并提交
-        let index = add_files_to_git_repo_index(&mut repo, vec!["test_file.txt"]).unwrap();
-        commit_index_to_git_repo(&mut repo, index, "Initial commit for tag test").unwrap();
-        
+        let (index, _) = add_files_to_git_repo_index(&mut repo, vec!["test_file.txt"]).unwrap();
+        commit_index_to_git_repo(
+            &mut repo,
+            index,
+            "Initial commit for tag test",
+            &CommitOptions::default(),
+        )
+        .unwrap();
+
         // 开始计时：创建标签
         let start = Instant::now();
         upsert_tag_to_git_repo(&mut repo, "test_tag", "Test tag message", None).unwrap();
@@ -1085,8 +1191,7 @@ fn benchmark_create_tag_empty_repo() -> BenchmarkResult {
         durations.push(duration);
         
         // 清理
-        drop(repo);
-        let _ = std::fs::remove_dir_all(&temp_dir);
+        let _ = remove_repo_dir(Some(repo), &temp_dir);
     }
     
     BenchmarkResult::new(durations)
@@ -1105,9 +1210,7 @@ fn benchmark_upsert_branch_empty_repo() -> BenchmarkResult {
         let test_dir = format!("{}_{}_{}", base_dir, i, std::process::id());
 
         // 确保目录不存在
-        if Path::new(&test_dir).exists() {
-            let _ = std::fs::remove_dir_all(&test_dir);
-        }
+        let _ = remove_repo_dir(None, &test_dir);
 
         // 创建新的 Git 仓库
         let mut repo = match open_or_init_git_repo(&test_dir) {
@@ -1121,7 +1224,7 @@ fn benchmark_upsert_branch_empty_repo() -> BenchmarkResult {
         // 配置用户信息
         if let Err(e) = config_git_repo_user(&mut repo, "Test User", "test@example.com") {
             eprintln!("第 {} 次测试配置用户失败: {}", i + 1, e);
-            let _ = std::fs::remove_dir_all(&test_dir);
+            let _ = remove_repo_dir(Some(repo), &test_dir);
             continue;
         }
 
@@ -1129,26 +1232,31 @@ fn benchmark_upsert_branch_empty_repo() -> BenchmarkResult {
         let content = generate_random_file_content();
         if let Err(e) = create_test_file(&test_dir, "test_file.txt", &content) {
             eprintln!("第 {} 次测试创建文件失败: {}", i + 1, e);
-            let _ = std::fs::remove_dir_all(&test_dir);
+            let _ = remove_repo_dir(Some(repo), &test_dir);
             continue;
         }
 
         // 添加文件到 index
         let index = match add_files_to_git_repo_index(&mut repo, vec!["test_file.txt"]) {
-            Ok(index) => index,
+            Ok((index, _)) => index,
             Err(e) => {
                 eprintln!("第 {} 次测试添加文件到 index 失败: {}", i + 1, e);
-                let _ = std::fs::remove_dir_all(&test_dir);
+                let _ = remove_repo_dir(Some(repo), &test_dir);
                 continue;
             }
         };
 
         // 提交文件
-        let _commit_id = match commit_index_to_git_repo(&mut repo, index, "Initial commit") {
+        let _commit_id = match commit_index_to_git_repo(
+            &mut repo,
+            index,
+            "Initial commit",
+            &CommitOptions::default(),
+        ) {
             Ok(commit_id) => commit_id,
             Err(e) => {
                 eprintln!("第 {} 次测试提交失败: {}", i + 1, e);
-                let _ = std::fs::remove_dir_all(&test_dir);
+                let _ = remove_repo_dir(Some(repo), &test_dir);
                 continue;
             }
         };
@@ -1172,9 +1280,7 @@ fn benchmark_upsert_branch_empty_repo() -> BenchmarkResult {
         }
 
         // 清理测试目录
-        if Path::new(&test_dir).exists() {
-            let _ = std::fs::remove_dir_all(&test_dir);
-        }
+        let _ = remove_repo_dir(Some(repo), &test_dir);
     }
 
     BenchmarkResult::new(durations)
@@ -1193,9 +1299,7 @@ fn benchmark_switch_git_repo_branch() -> BenchmarkResult {
         let test_dir = format!("{}_{}_{}", base_dir, i, std::process::id());
 
         // 确保目录不存在
-        if Path::new(&test_dir).exists() {
-            let _ = std::fs::remove_dir_all(&test_dir);
-        }
+        let _ = remove_repo_dir(None, &test_dir);
 
         // 创建新的 Git 仓库
         let mut repo = match open_or_init_git_repo(&test_dir) {
@@ -1209,7 +1313,7 @@ fn benchmark_switch_git_repo_branch() -> BenchmarkResult {
         // 配置用户信息
         if let Err(e) = config_git_repo_user(&mut repo, "Test User", "test@example.com") {
             eprintln!("第 {} 次测试配置用户失败: {}", i + 1, e);
-            let _ = std::fs::remove_dir_all(&test_dir);
+            let _ = remove_repo_dir(Some(repo), &test_dir);
             continue;
         }
 
@@ -1217,32 +1321,38 @@ fn benchmark_switch_git_repo_branch() -> BenchmarkResult {
         let content1 = generate_random_file_content();
         if let Err(e) = create_test_file(&test_dir, "file1.txt", &content1) {
             eprintln!("第 {} 次测试创建文件1失败: {}", i + 1, e);
-            let _ = std::fs::remove_dir_all(&test_dir);
+            let _ = remove_repo_dir(Some(repo), &test_dir);
             continue;
         }
 
         let index1 = match add_files_to_git_repo_index(&mut repo, vec!["file1.txt"]) {
-            Ok(index) => index,
+            Ok((index, _)) => index,
             Err(e) => {
                 eprintln!("第 {} 次测试添加文件1到 index 失败: {}", i + 1, e);
-                let _ = std::fs::remove_dir_all(&test_dir);
+                let _ = remove_repo_dir(Some(repo), &test_dir);
                 continue;
             }
         };
 
-        let _commit_id1 = match commit_index_to_git_repo(&mut repo, index1, "First commit") {
+        let _commit_id1 = match commit_index_to_git_repo(
+            &mut repo,
+            index1,
+            "First commit",
+            &CommitOptions::default(),
+        ) {
             Ok(commit_id) => commit_id,
             Err(e) => {
                 eprintln!("第 {} 次测试提交1失败: {}", i + 1, e);
-                let _ = std::fs::remove_dir_all(&test_dir);
+                let _ = remove_repo_dir(Some(repo), &test_dir);
                 continue;
             }
         };
 
         // 创建分支 test_branch_1
-        if let Err(e) = upsert_branch_to_git_repo(&mut repo, "test_branch_1", None) {
+        // 丢弃返回的分支引用，避免错误分支中仍借用 repo
+        if let Err(e) = upsert_branch_to_git_repo(&mut repo, "test_branch_1", None).map(|_| ()) {
             eprintln!("第 {} 次测试创建分支失败: {}", i + 1, e);
-            let _ = std::fs::remove_dir_all(&test_dir);
+            let _ = remove_repo_dir(Some(repo), &test_dir);
             continue;
         }
 
@@ -1250,24 +1360,29 @@ fn benchmark_switch_git_repo_branch() -> BenchmarkResult {
         let content2 = generate_random_file_content();
         if let Err(e) = create_test_file(&test_dir, "file2.txt", &content2) {
             eprintln!("第 {} 次测试创建文件2失败: {}", i + 1, e);
-            let _ = std::fs::remove_dir_all(&test_dir);
+            let _ = remove_repo_dir(Some(repo), &test_dir);
             continue;
         }
 
         let index2 = match add_files_to_git_repo_index(&mut repo, vec!["file2.txt"]) {
-            Ok(index) => index,
+            Ok((index, _)) => index,
             Err(e) => {
                 eprintln!("第 {} 次测试添加文件2到 index 失败: {}", i + 1, e);
-                let _ = std::fs::remove_dir_all(&test_dir);
+                let _ = remove_repo_dir(Some(repo), &test_dir);
                 continue;
             }
         };
 
-        let _commit_id2 = match commit_index_to_git_repo(&mut repo, index2, "Second commit") {
+        let _commit_id2 = match commit_index_to_git_repo(
+            &mut repo,
+            index2,
+            "Second commit",
+            &CommitOptions::default(),
+        ) {
             Ok(commit_id) => commit_id,
             Err(e) => {
                 eprintln!("第 {} 次测试提交2失败: {}", i + 1, e);
-                let _ = std::fs::remove_dir_all(&test_dir);
+                let _ = remove_repo_dir(Some(repo), &test_dir);
                 continue;
             }
         };
@@ -1291,30 +1406,27 @@ fn benchmark_switch_git_repo_branch() -> BenchmarkResult {
         }
 
         // 清理测试目录
-        if Path::new(&test_dir).exists() {
-            let _ = std::fs::remove_dir_all(&test_dir);
-        }
+        let _ = remove_repo_dir(Some(repo), &test_dir);
     }
 
     BenchmarkResult::new(durations)
 }
 
-#[allow(dead_code)]
-fn benchmark_reset_git_repo_head() -> BenchmarkResult {
+// 三种 reset 模式共用相同的仓库准备步骤，只有被计时的 reset 调用不同
+fn benchmark_reset_git_repo_head(mode: ResetMode) -> BenchmarkResult {
     println!(
-        "开始性能测试: reset_git_repo_head 重置到指定提交，测试 1000 次"
+        "开始性能测试: reset_git_repo_head_with_mode({:?}) 重置到指定提交，测试 1000 次",
+        mode
     );
 
     let mut durations = Vec::with_capacity(1000);
-    let base_dir = "bench_reset_head";
+    let base_dir = format!("bench_reset_{:?}", mode).to_lowercase();
 
     for i in 0..1000 {
         let test_dir = format!("{}_{}_{}", base_dir, i, std::process::id());
 
         // 确保目录不存在
-        if Path::new(&test_dir).exists() {
-            let _ = std::fs::remove_dir_all(&test_dir);
-        }
+        let _ = remove_repo_dir(None, &test_dir);
 
         // 创建新的 Git 仓库
         let mut repo = match open_or_init_git_repo(&test_dir) {
@@ -1328,7 +1440,7 @@ fn benchmark_reset_git_repo_head() -> BenchmarkResult {
         // 配置用户信息
         if let Err(e) = config_git_repo_user(&mut repo, "Test User", "test@example.com") {
             eprintln!("第 {} 次测试配置用户失败: {}", i + 1, e);
-            let _ = std::fs::remove_dir_all(&test_dir);
+            let _ = remove_repo_dir(Some(repo), &test_dir);
             continue;
         }
 
@@ -1336,24 +1448,29 @@ fn benchmark_reset_git_repo_head() -> BenchmarkResult {
         let content1 = generate_random_file_content();
         if let Err(e) = create_test_file(&test_dir, "initial_file.txt", &content1) {
             eprintln!("第 {} 次测试创建初始文件失败: {}", i + 1, e);
-            let _ = std::fs::remove_dir_all(&test_dir);
+            let _ = remove_repo_dir(Some(repo), &test_dir);
             continue;
         }
 
         let index1 = match add_files_to_git_repo_index(&mut repo, vec!["initial_file.txt"]) {
-            Ok(index) => index,
+            Ok((index, _)) => index,
             Err(e) => {
                 eprintln!("第 {} 次测试添加初始文件到 index 失败: {}", i + 1, e);
-                let _ = std::fs::remove_dir_all(&test_dir);
+                let _ = remove_repo_dir(Some(repo), &test_dir);
                 continue;
             }
         };
 
-        let commit1_oid = match commit_index_to_git_repo(&mut repo, index1, "Initial commit") {
+        let commit1_oid = match commit_index_to_git_repo(
+            &mut repo,
+            index1,
+            "Initial commit",
+            &CommitOptions::default(),
+        ) {
             Ok(commit_id) => commit_id,
             Err(e) => {
                 eprintln!("第 {} 次测试提交初始文件失败: {}", i + 1, e);
-                let _ = std::fs::remove_dir_all(&test_dir);
+                let _ = remove_repo_dir(Some(repo), &test_dir);
                 continue;
             }
         };
@@ -1364,32 +1481,37 @@ fn benchmark_reset_git_repo_head() -> BenchmarkResult {
             Ok(files) => files,
             Err(e) => {
                 eprintln!("第 {} 次测试创建嵌套文件失败: {}", i + 1, e);
-                let _ = std::fs::remove_dir_all(&test_dir);
+                let _ = remove_repo_dir(Some(repo), &test_dir);
                 continue;
             }
         };
 
         let nested_file_refs: Vec<&str> = nested_files.iter().map(|s| s.as_str()).collect();
         let index2 = match add_files_to_git_repo_index(&mut repo, nested_file_refs) {
-            Ok(index) => index,
+            Ok((index, _)) => index,
             Err(e) => {
                 eprintln!("第 {} 次测试添加嵌套文件到 index 失败: {}", i + 1, e);
-                let _ = std::fs::remove_dir_all(&test_dir);
+                let _ = remove_repo_dir(Some(repo), &test_dir);
                 continue;
             }
         };
 
-        if let Err(e) = commit_index_to_git_repo(&mut repo, index2, "Add nested files") {
+        if let Err(e) = commit_index_to_git_repo(
+            &mut repo,
+            index2,
+            "Add nested files",
+            &CommitOptions::default(),
+        ) {
             eprintln!("第 {} 次测试提交嵌套文件失败: {}", i + 1, e);
-            let _ = std::fs::remove_dir_all(&test_dir);
+            let _ = remove_repo_dir(Some(repo), &test_dir);
             continue;
         }
 
-        // 步骤3: 开始计时 - 只测试 reset_git_repo_head 函数的耗时
+        // 步骤3: 开始计时 - 只测试 reset_git_repo_head_with_mode 函数的耗时
         let start = Instant::now();
 
         // 执行被测试的函数（重置到 commit1）
-        match reset_git_repo_head(&mut repo, commit1_oid) {
+        match reset_git_repo_head_with_mode(&mut repo, commit1_oid, mode) {
             Ok(_) => {
                 let duration = start.elapsed();
                 durations.push(duration);
@@ -1404,14 +1526,24 @@ fn benchmark_reset_git_repo_head() -> BenchmarkResult {
         }
 
         // 清理测试目录
-        if Path::new(&test_dir).exists() {
-            let _ = std::fs::remove_dir_all(&test_dir);
-        }
+        let _ = remove_repo_dir(Some(repo), &test_dir);
     }
 
     BenchmarkResult::new(durations)
 }
 
+// benchmark_reset_modes 结果对应的 (场景名, 标题)，顺序为 soft、mixed、hard
+const RESET_MODE_SCENARIOS: [(&str, &str); 3] = [
+    ("reset_soft", "soft 模式重置仓库 HEAD 场景测试"),
+    ("reset_mixed", "mixed 模式重置仓库 HEAD 场景测试"),
+    ("reset_head", "重置仓库 HEAD 场景测试"),
+];
+
+// 在相同的仓库准备步骤下分别测试 soft、mixed、hard 三种模式的 reset 耗时
+fn benchmark_reset_modes() -> [BenchmarkResult; 3] {
+    [ResetMode::Soft, ResetMode::Mixed, ResetMode::Hard].map(benchmark_reset_git_repo_head)
+}
+
 #[allow(dead_code)]
 fn benchmark_clean_git_repo_index() -> BenchmarkResult {
     println!(
@@ -1425,9 +1557,7 @@ fn benchmark_clean_git_repo_index() -> BenchmarkResult {
         let test_dir = format!("{}_{}_{}", base_dir, i, std::process::id());
 
         // 确保目录不存在
-        if Path::new(&test_dir).exists() {
-            let _ = std::fs::remove_dir_all(&test_dir);
-        }
+        let _ = remove_repo_dir(None, &test_dir);
 
         // 创建新的 Git 仓库
         let mut repo = match open_or_init_git_repo(&test_dir) {
@@ -1441,7 +1571,7 @@ fn benchmark_clean_git_repo_index() -> BenchmarkResult {
         // 配置用户信息
         if let Err(e) = config_git_repo_user(&mut repo, "Test User", "test@example.com") {
             eprintln!("第 {} 次测试配置用户失败: {}", i + 1, e);
-            let _ = std::fs::remove_dir_all(&test_dir);
+            let _ = remove_repo_dir(Some(repo), &test_dir);
             continue;
         }
 
@@ -1451,24 +1581,29 @@ fn benchmark_clean_git_repo_index() -> BenchmarkResult {
             Ok(files) => files,
             Err(e) => {
                 eprintln!("第 {} 次测试创建嵌套文件失败: {}", i + 1, e);
-                let _ = std::fs::remove_dir_all(&test_dir);
+                let _ = remove_repo_dir(Some(repo), &test_dir);
                 continue;
             }
         };
 
         let nested_file_refs: Vec<&str> = nested_files.iter().map(|s| s.as_str()).collect();
         let index1 = match add_files_to_git_repo_index(&mut repo, nested_file_refs) {
-            Ok(index) => index,
+            Ok((index, _)) => index,
             Err(e) => {
                 eprintln!("第 {} 次测试添加嵌套文件到 index 失败: {}", i + 1, e);
-                let _ = std::fs::remove_dir_all(&test_dir);
+                let _ = remove_repo_dir(Some(repo), &test_dir);
                 continue;
             }
         };
 
-        if let Err(e) = commit_index_to_git_repo(&mut repo, index1, "Add nested files") {
+        if let Err(e) = commit_index_to_git_repo(
+            &mut repo,
+            index1,
+            "Add nested files",
+            &CommitOptions::default(),
+        ) {
             eprintln!("第 {} 次测试提交嵌套文件失败: {}", i + 1, e);
-            let _ = std::fs::remove_dir_all(&test_dir);
+            let _ = remove_repo_dir(Some(repo), &test_dir);
             continue;
         }
 
@@ -1480,13 +1615,18 @@ fn benchmark_clean_git_repo_index() -> BenchmarkResult {
             Ok(index) => index,
             Err(e) => {
                 eprintln!("第 {} 次测试清理索引失败: {}", i + 1, e);
-                let _ = std::fs::remove_dir_all(&test_dir);
+                let _ = remove_repo_dir(Some(repo), &test_dir);
                 continue;
             }
         };
 
         // 步骤3: 提交清理后的索引
-        match commit_index_to_git_repo(&mut repo, clean_index, "清空所有文件") {
+        match commit_index_to_git_repo(
+            &mut repo,
+            clean_index,
+            "清空所有文件",
+            &CommitOptions::default(),
+        ) {
             Ok(_) => {
                 let duration = start.elapsed();
                 durations.push(duration);
@@ -1501,14 +1641,12 @@ fn benchmark_clean_git_repo_index() -> BenchmarkResult {
         }
 
         // 步骤4: 恢复工作目录到 HEAD
-        if let Err(e) = restore_git_repo_head_to_workdir(&mut repo) {
+        if let Err(e) = restore_git_repo_head_to_workdir(&repo) {
             eprintln!("第 {} 次测试恢复工作目录失败: {}", i + 1, e);
         }
 
         // 清理测试目录
-        if Path::new(&test_dir).exists() {
-            let _ = std::fs::remove_dir_all(&test_dir);
-        }
+        let _ = remove_repo_dir(Some(repo), &test_dir);
     }
 
     BenchmarkResult::new(durations)
@@ -1527,9 +1665,7 @@ fn benchmark_traverse_git_repo_commit_tree_recorder() -> BenchmarkResult {
         let test_dir = format!("{}_{}_{}", base_dir, i, std::process::id());
 
         // 确保目录不存在
-        if Path::new(&test_dir).exists() {
-            let _ = std::fs::remove_dir_all(&test_dir);
-        }
+        let _ = remove_repo_dir(None, &test_dir);
 
         // 创建新的 Git 仓库
         let mut repo = match open_or_init_git_repo(&test_dir) {
@@ -1543,7 +1679,7 @@ fn benchmark_traverse_git_repo_commit_tree_recorder() -> BenchmarkResult {
         // 配置用户信息
         if let Err(e) = config_git_repo_user(&mut repo, "Test User", "test@example.com") {
             eprintln!("第 {} 次测试配置用户失败: {}", i + 1, e);
-            let _ = std::fs::remove_dir_all(&test_dir);
+            let _ = remove_repo_dir(Some(repo), &test_dir);
             continue;
         }
 
@@ -1553,26 +1689,31 @@ fn benchmark_traverse_git_repo_commit_tree_recorder() -> BenchmarkResult {
             Ok(files) => files,
             Err(e) => {
                 eprintln!("第 {} 次测试创建嵌套文件失败: {}", i + 1, e);
-                let _ = std::fs::remove_dir_all(&test_dir);
+                let _ = remove_repo_dir(Some(repo), &test_dir);
                 continue;
             }
         };
 
         let nested_file_refs: Vec<&str> = nested_files.iter().map(|s| s.as_str()).collect();
         let index1 = match add_files_to_git_repo_index(&mut repo, nested_file_refs) {
-            Ok(index) => index,
+            Ok((index, _)) => index,
             Err(e) => {
                 eprintln!("第 {} 次测试添加嵌套文件到 index 失败: {}", i + 1, e);
-                let _ = std::fs::remove_dir_all(&test_dir);
+                let _ = remove_repo_dir(Some(repo), &test_dir);
                 continue;
             }
         };
 
-        let commit_oid = match commit_index_to_git_repo(&mut repo, index1, "Add nested files") {
+        let commit_oid = match commit_index_to_git_repo(
+            &mut repo,
+            index1,
+            "Add nested files",
+            &CommitOptions::default(),
+        ) {
             Ok(commit_id) => commit_id,
             Err(e) => {
                 eprintln!("第 {} 次测试提交嵌套文件失败: {}", i + 1, e);
-                let _ = std::fs::remove_dir_all(&test_dir);
+                let _ = remove_repo_dir(Some(repo), &test_dir);
                 continue;
             }
         };
@@ -1596,9 +1737,7 @@ fn benchmark_traverse_git_repo_commit_tree_recorder() -> BenchmarkResult {
         }
 
         // 清理测试目录
-        if Path::new(&test_dir).exists() {
-            let _ = std::fs::remove_dir_all(&test_dir);
-        }
+        let _ = remove_repo_dir(Some(repo), &test_dir);
     }
 
     BenchmarkResult::new(durations)
@@ -1618,9 +1757,7 @@ fn benchmark_switch_git_repo_branch_no_restore() -> BenchmarkResult {
         let test_dir = format!("{}_{}_{}", base_dir, i, std::process::id());
 
         // 确保目录不存在
-        if Path::new(&test_dir).exists() {
-            let _ = std::fs::remove_dir_all(&test_dir);
-        }
+        let _ = remove_repo_dir(None, &test_dir);
 
         // 创建新的 Git 仓库
         let mut repo = match open_or_init_git_repo(&test_dir) {
@@ -1634,7 +1771,7 @@ fn benchmark_switch_git_repo_branch_no_restore() -> BenchmarkResult {
         // 配置用户信息
         if let Err(e) = config_git_repo_user(&mut repo, "Test User", "test@example.com") {
             eprintln!("第 {} 次测试配置用户失败: {}", i + 1, e);
-            let _ = std::fs::remove_dir_all(&test_dir);
+            let _ = remove_repo_dir(Some(repo), &test_dir);
             continue;
         }
 
@@ -1642,32 +1779,38 @@ fn benchmark_switch_git_repo_branch_no_restore() -> BenchmarkResult {
         let content1 = generate_random_file_content();
         if let Err(e) = create_test_file(&test_dir, "file1.txt", &content1) {
             eprintln!("第 {} 次测试创建文件1失败: {}", i + 1, e);
-            let _ = std::fs::remove_dir_all(&test_dir);
+            let _ = remove_repo_dir(Some(repo), &test_dir);
             continue;
         }
 
         let index1 = match add_files_to_git_repo_index(&mut repo, vec!["file1.txt"]) {
-            Ok(index) => index,
+            Ok((index, _)) => index,
             Err(e) => {
                 eprintln!("第 {} 次测试添加文件1到 index 失败: {}", i + 1, e);
-                let _ = std::fs::remove_dir_all(&test_dir);
+                let _ = remove_repo_dir(Some(repo), &test_dir);
                 continue;
             }
         };
 
-        let _commit_id1 = match commit_index_to_git_repo(&mut repo, index1, "First commit") {
+        let _commit_id1 = match commit_index_to_git_repo(
+            &mut repo,
+            index1,
+            "First commit",
+            &CommitOptions::default(),
+        ) {
             Ok(commit_id) => commit_id,
             Err(e) => {
                 eprintln!("第 {} 次测试提交1失败: {}", i + 1, e);
-                let _ = std::fs::remove_dir_all(&test_dir);
+                let _ = remove_repo_dir(Some(repo), &test_dir);
                 continue;
             }
         };
 
         // 创建分支 test_branch_1
-        if let Err(e) = upsert_branch_to_git_repo(&mut repo, "test_branch_1", None) {
+        // 丢弃返回的分支引用，避免错误分支中仍借用 repo
+        if let Err(e) = upsert_branch_to_git_repo(&mut repo, "test_branch_1", None).map(|_| ()) {
             eprintln!("第 {} 次测试创建分支失败: {}", i + 1, e);
-            let _ = std::fs::remove_dir_all(&test_dir);
+            let _ = remove_repo_dir(Some(repo), &test_dir);
             continue;
         }
 
@@ -1675,24 +1818,29 @@ fn benchmark_switch_git_repo_branch_no_restore() -> BenchmarkResult {
         let content2 = generate_random_file_content();
         if let Err(e) = create_test_file(&test_dir, "file2.txt", &content2) {
             eprintln!("第 {} 次测试创建文件2失败: {}", i + 1, e);
-            let _ = std::fs::remove_dir_all(&test_dir);
+            let _ = remove_repo_dir(Some(repo), &test_dir);
             continue;
         }
 
         let index2 = match add_files_to_git_repo_index(&mut repo, vec!["file2.txt"]) {
-            Ok(index) => index,
+            Ok((index, _)) => index,
             Err(e) => {
                 eprintln!("第 {} 次测试添加文件2到 index 失败: {}", i + 1, e);
-                let _ = std::fs::remove_dir_all(&test_dir);
+                let _ = remove_repo_dir(Some(repo), &test_dir);
                 continue;
             }
         };
 
-        let _commit_id2 = match commit_index_to_git_repo(&mut repo, index2, "Second commit") {
+        let _commit_id2 = match commit_index_to_git_repo(
+            &mut repo,
+            index2,
+            "Second commit",
+            &CommitOptions::default(),
+        ) {
             Ok(commit_id) => commit_id,
             Err(e) => {
                 eprintln!("第 {} 次测试提交2失败: {}", i + 1, e);
-                let _ = std::fs::remove_dir_all(&test_dir);
+                let _ = remove_repo_dir(Some(repo), &test_dir);
                 continue;
             }
         };
@@ -1716,93 +1864,226 @@ fn benchmark_switch_git_repo_branch_no_restore() -> BenchmarkResult {
         }
 
         // 清理测试目录
-        if Path::new(&test_dir).exists() {
-            let _ = std::fs::remove_dir_all(&test_dir);
-        }
+        let _ = remove_repo_dir(Some(repo), &test_dir);
     }
 
     BenchmarkResult::new(durations)
 }
 
 
-#[allow(dead_code)]
-fn run_benchmark() {
+// 性能测试结果的输出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Text,
+    // 输出一个 JSON 文档，key 为场景名，value 为该场景的测试结果，便于 CI 上报
+    #[cfg(feature = "serde")]
+    Json,
+}
+
+// 一个性能测试场景：name 用于 JSON 输出的 key，title 用于文本输出
+pub struct BenchmarkScenario {
+    pub name: &'static str,
+    pub title: &'static str,
+    pub run: fn() -> BenchmarkResult,
+}
+
+#[cfg(feature = "serde")]
+impl BenchmarkResult {
+    // 耗时统一以纳秒为单位输出
+    pub fn to_json_value(&self) -> serde_json::Value {
+        let nanos = |duration: &Duration| duration.as_nanos() as u64;
+        serde_json::json!({
+            "total_runs": self.total_runs,
+            "successful_runs": self.successful_runs,
+            "failed_runs": self.failed_runs,
+            "unit": "ns",
+            "avg": nanos(&self.avg_duration),
+            "pct50": nanos(&self.pct50_duration),
+            "pct90": nanos(&self.pct90_duration),
+            "pct95": nanos(&self.pct95_duration),
+            "min": self.durations.first().map(nanos),
+            "max": self.durations.last().map(nanos),
+        })
+    }
+}
+
+pub fn benchmark_scenarios() -> Vec<BenchmarkScenario> {
+    vec![
+        BenchmarkScenario {
+            name: "open_or_init_new",
+            title: "新建仓库场景测试",
+            run: || benchmark_open_or_init_git_repo_new_scenario(1000),
+        },
+        BenchmarkScenario {
+            name: "open_or_init_existing",
+            title: "打开已存在仓库场景测试",
+            run: || benchmark_open_or_init_git_repo_existing_scenario(1000),
+        },
+        BenchmarkScenario {
+            name: "config_user",
+            title: "配置用户信息场景测试",
+            run: || benchmark_config_git_repo_user(1000),
+        },
+        BenchmarkScenario {
+            name: "add_single_file_empty_repo",
+            title: "空仓库添加单个文件场景测试",
+            run: || benchmark_add_single_file_empty_repo(1000),
+        },
+        BenchmarkScenario {
+            name: "add_single_file_existing_repo",
+            title: "已有文件仓库添加新文件场景测试",
+            run: || benchmark_add_single_file_existing_repo(1000),
+        },
+        BenchmarkScenario {
+            name: "modify_single_file_existing_repo",
+            title: "已有文件仓库修改现有文件场景测试",
+            run: || benchmark_modify_single_file_existing_repo(1000),
+        },
+        BenchmarkScenario {
+            name: "commit_single_file_empty_repo",
+            title: "空仓库提交单个文件场景测试",
+            run: || benchmark_commit_single_file_empty_repo(1000),
+        },
+        BenchmarkScenario {
+            name: "commit_new_file_existing_repo",
+            title: "已有文件仓库提交新文件场景测试",
+            run: || benchmark_commit_new_file_existing_repo(1000),
+        },
+        BenchmarkScenario {
+            name: "commit_modified_file_existing_repo",
+            title: "已有文件仓库提交修改文件场景测试",
+            run: || benchmark_commit_modified_file_existing_repo(1000),
+        },
+        BenchmarkScenario {
+            name: "add_commit_multiple_files_empty_repo",
+            title: "在空仓库中一次性提交10个具有多层目录结构的文件场景测试",
+            run: benchmark_add_commit_multiple_files_empty_repo,
+        },
+        BenchmarkScenario {
+            name: "commit_tree_snapshot_multiple_files_empty_repo",
+            title: "空仓库中通过 TreeBuilder 直接提交多层目录结构的10个文件场景测试",
+            run: benchmark_commit_tree_snapshot_multiple_files_empty_repo,
+        },
+        BenchmarkScenario {
+            name: "create_tag_empty_repo",
+            title: "在空仓库中创建提交并打标签场景测试",
+            run: benchmark_create_tag_empty_repo,
+        },
+        BenchmarkScenario {
+            name: "upsert_branch",
+            title: "创建分支场景测试",
+            run: benchmark_upsert_branch_empty_repo,
+        },
+        BenchmarkScenario {
+            name: "switch_branch",
+            title: "切换分支场景测试",
+            run: benchmark_switch_git_repo_branch,
+        },
+        BenchmarkScenario {
+            name: "switch_branch_no_restore",
+            title: "切换分支场景测试, 不 restore workdir",
+            run: benchmark_switch_git_repo_branch_no_restore,
+        },
+        BenchmarkScenario {
+            name: "clean_index",
+            title: "清理索引场景测试",
+            run: benchmark_clean_git_repo_index,
+        },
+        BenchmarkScenario {
+            name: "traverse_commit_tree",
+            title: "遍历提交树场景测试",
+            run: benchmark_traverse_git_repo_commit_tree_recorder,
+        },
+        BenchmarkScenario {
+            name: "lookup_and_read_blob",
+            title: "查找文件 entry 和读取 blob 内容场景测试",
+            run: benchmark_lookup_and_read_git_repo_blob,
+        },
+    ]
+}
+
+fn run_benchmark_scenarios(scenarios: &[BenchmarkScenario]) -> Vec<BenchmarkResult> {
+    scenarios.iter().map(|scenario| (scenario.run)()).collect()
+}
+
+// 将所有场景的 (场景名, 结果) 序列化为一个 JSON 文档
+#[cfg(feature = "serde")]
+fn format_benchmark_results_json(results: &[(&'static str, BenchmarkResult)]) -> String {
+    let mut document = serde_json::Map::new();
+    for (name, result) in results {
+        document.insert(name.to_string(), result.to_json_value());
+    }
+    serde_json::to_string_pretty(&document).unwrap()
+}
+
+// 运行名称包含 filter 的场景，返回 (场景名, 标题, 结果)。先按注册顺序运行 benchmark_scenarios，
+// 再由 benchmark_reset_modes 在相同的准备步骤下一起运行三种 reset 模式
+fn run_with_titles(filter: &str) -> Vec<(&'static str, &'static str, BenchmarkResult)> {
+    let scenarios: Vec<BenchmarkScenario> = benchmark_scenarios()
+        .into_iter()
+        .filter(|scenario| scenario.name.contains(filter))
+        .collect();
+    let results = run_benchmark_scenarios(&scenarios);
+    let mut titled: Vec<_> = scenarios
+        .iter()
+        .zip(results)
+        .map(|(scenario, result)| (scenario.name, scenario.title, result))
+        .collect();
+    if RESET_MODE_SCENARIOS
+        .iter()
+        .any(|(name, _)| name.contains(filter))
+    {
+        for ((name, title), result) in RESET_MODE_SCENARIOS
+            .into_iter()
+            .zip(benchmark_reset_modes())
+        {
+            if name.contains(filter) {
+                titled.push((name, title, result));
+            }
+        }
+    }
+    titled
+}
+
+// 运行所有场景，返回 (场景名, 结果)
+pub fn run_all() -> Vec<(&'static str, BenchmarkResult)> {
+    run("")
+}
+
+// 只运行名称包含 filter 的场景，filter 为空时运行所有场景
+pub fn run(filter: &str) -> Vec<(&'static str, BenchmarkResult)> {
+    run_with_titles(filter)
+        .into_iter()
+        .map(|(name, _, result)| (name, result))
+        .collect()
+}
+
+pub fn run_benchmark() {
+    run_benchmark_with_format(OutputFormat::Text);
+}
+
+pub fn run_benchmark_with_format(format: OutputFormat) {
     println!("=== Git 仓库操作性能基准测试 ===");
 
-    // 测试新建仓库场景
-    let new_result = benchmark_open_or_init_git_repo_new_scenario(1000);
-    // 测试打开已存在仓库场景
-    let existing_result = benchmark_open_or_init_git_repo_existing_scenario(1000);
-    // 测试配置用户信息场景
-    let config_result = benchmark_config_git_repo_user(1000);
-    // 测试添加文件到空仓库场景
-    let add_empty_result = benchmark_add_single_file_empty_repo(1000);
-    // 测试添加文件到已有文件仓库场景
-    let add_existing_result = benchmark_add_single_file_existing_repo(1000);
-    // 测试修改已有文件场景
-    let modify_existing_result = benchmark_modify_single_file_existing_repo(1000);
-    // 测试提交文件到空仓库场景
-    let commit_empty_result = benchmark_commit_single_file_empty_repo(1000);
-    // 测试提交新文件到已有文件仓库场景
-    let commit_new_result = benchmark_commit_new_file_existing_repo(1000);
-    // 测试提交修改文件到已有文件仓库场景
-    let commit_modified_result = benchmark_commit_modified_file_existing_repo(1000);
-    // 测试在空仓库中一次性提交10个具有多层目录结构的文件场景
-    let add_commit_multiple_result = benchmark_add_commit_multiple_files_empty_repo();
-    // 测试在空仓库中创建提交并打标签场景
-    let create_tag_result = benchmark_create_tag_empty_repo();
-    // 测试在空仓库中创建分支场景
-    let upsert_branch_result = benchmark_upsert_branch_empty_repo();
-    // 测试切换分支场景
-    let switch_branch_result = benchmark_switch_git_repo_branch();
-    // 测试切换分支场景 (不 restore)
-    let switch_branch_result_no_restore = benchmark_switch_git_repo_branch_no_restore();
-    // 测试重置仓库 HEAD 场景
-    let reset_head_result = benchmark_reset_git_repo_head();
-    // 测试清理索引场景
-    let clean_index_result = benchmark_clean_git_repo_index();
-    // 测试遍历提交树场景
-    let traverse_commit_tree_result = benchmark_traverse_git_repo_commit_tree_recorder();
-    // 测试查找文件 entry 和读取 blob 内容场景
-    let lookup_read_blob_result = benchmark_lookup_and_read_git_repo_blob();
+    let results = run_with_titles("");
 
     // 打印结果
-    println!("\n1. 新建仓库场景测试");
-    new_result.print_summary();
-    println!("\n2. 打开已存在仓库场景测试");
-    existing_result.print_summary();
-    println!("\n3. 配置用户信息场景测试");
-    config_result.print_summary();
-    println!("\n4. 空仓库添加单个文件场景测试");
-    add_empty_result.print_summary();
-    println!("\n5. 已有文件仓库添加新文件场景测试");
-    add_existing_result.print_summary();
-    println!("\n6. 已有文件仓库修改现有文件场景测试");
-    modify_existing_result.print_summary();
-    println!("\n7. 空仓库提交单个文件场景测试");
-    commit_empty_result.print_summary();
-    println!("\n8. 已有文件仓库提交新文件场景测试");
-    commit_new_result.print_summary();
-    println!("\n9. 已有文件仓库提交修改文件场景测试");
-    commit_modified_result.print_summary();
-    println!("\n10. 在空仓库中一次性提交10个具有多层目录结构的文件场景测试");
-    add_commit_multiple_result.print_summary();    
-    println!("\n11. 在空仓库中创建提交并打标签场景测试");
-    create_tag_result.print_summary();
-    println!("\n12. 创建分支场景测试");
-    upsert_branch_result.print_summary();
-    println!("\n13. 切换分支场景测试");
-    switch_branch_result.print_summary();
-    println!("\n14. 切换分支场景测试, 不 restore workdir");
-    switch_branch_result_no_restore.print_summary();
-    println!("\n15. 重置仓库 HEAD 场景测试");
-    reset_head_result.print_summary();
-    println!("\n16. 清理索引场景测试");
-    clean_index_result.print_summary();
-    println!("\n17. 遍历提交树场景测试");
-    traverse_commit_tree_result.print_summary();
-    println!("\n18. 查找文件 entry 和读取 blob 内容场景测试");
-    lookup_read_blob_result.print_summary();
+    match format {
+        OutputFormat::Text => {
+            for (i, (name, title, result)) in results.iter().enumerate() {
+                println!("\n{}. {} ({})", i + 1, title, name);
+                result.print_summary();
+            }
+        }
+        #[cfg(feature = "serde")]
+        OutputFormat::Json => {
+            let results: Vec<_> = results
+                .into_iter()
+                .map(|(name, _, result)| (name, result))
+                .collect();
+            println!("{}", format_benchmark_results_json(&results));
+        }
+    }
 }
 
 
@@ -1816,4 +2097,74 @@ mod tests {
         // 通过单测驱动 run_benchmark 函数
         run_benchmark();
     }
+
+    #[test]
+    fn test_run_filters_scenarios_by_name() {
+        let results = run("config_user");
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].0, "config_user");
+        assert_eq!(results[0].1.total_runs, 1000);
+
+        assert!(run("no_such_scenario").is_empty());
+    }
+
+    #[test]
+    fn test_remove_repo_dir() {
+        let test_dir =
+            std::env::temp_dir().join(format!("bench_remove_repo_dir_{}", std::process::id()));
+        let repo = open_or_init_git_repo(test_dir.to_str().unwrap()).unwrap();
+        create_test_file(test_dir.to_str().unwrap(), "test_file.txt", "content").unwrap();
+
+        remove_repo_dir(Some(repo), &test_dir).unwrap();
+
+        assert!(!test_dir.exists());
+        // 目录已不存在时也应成功
+        remove_repo_dir(None, &test_dir).unwrap();
+    }
+
+    #[test]
+    fn test_format_summary_sub_microsecond_unit() {
+        let result = BenchmarkResult::new(vec![Duration::from_nanos(500)]);
+
+        let unit = DurationUnit::auto(result.avg_duration);
+        assert_eq!(unit, DurationUnit::Ns);
+        let summary = result.format_summary(unit);
+        assert!(summary.contains("平均耗时: 500ns"));
+        assert!(!summary.contains("0.00ms"));
+
+        let summary_us = result.format_summary(DurationUnit::Us);
+        assert!(summary_us.contains("平均耗时: 0.50us"));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_run_benchmark_scenarios_json() {
+        let scenarios = [
+            BenchmarkScenario {
+                name: "open_or_init_existing",
+                title: "打开已存在仓库场景测试",
+                run: || benchmark_open_or_init_git_repo_existing_scenario(10),
+            },
+            BenchmarkScenario {
+                name: "config_user",
+                title: "配置用户信息场景测试",
+                run: || benchmark_config_git_repo_user(10),
+            },
+        ];
+
+        let results: Vec<_> = scenarios
+            .iter()
+            .map(|scenario| scenario.name)
+            .zip(run_benchmark_scenarios(&scenarios))
+            .collect();
+        let report = format_benchmark_results_json(&results);
+
+        let document: serde_json::Value = serde_json::from_str(&report).unwrap();
+        let object = document.as_object().unwrap();
+        assert_eq!(object.len(), 2);
+        for name in ["open_or_init_existing", "config_user"] {
+            assert!(object[name]["avg"].is_u64());
+            assert_eq!(object[name]["total_runs"], 10);
+        }
+    }
 }
@@ -0,0 +1,8296 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+pub mod backend;
+#[cfg(feature = "bench")]
+pub mod bench;
+pub mod remote;
+pub mod stash;
+#[cfg(test)]
+pub(crate) mod test_util;
+
+pub fn open_or_init_git_repo(
+    dir: impl AsRef<Path>,
+) -> Result<git2::Repository, Box<dyn std::error::Error>> {
+    let dir = dir.as_ref();
+    let git_dir = dir.join(".git");
+    if git_dir.exists() {
+        let result = git2::Repository::open(dir)?;
+        return Ok(result);
+    }
+    // 裸仓库没有 .git 目录，不能当作普通目录删除
+    if let Ok(repo) = git2::Repository::open_bare(dir) {
+        return Ok(repo);
+    }
+    // 目录已存在但不是 Git 仓库，删除后重新初始化
+    if dir.exists() {
+        std::fs::remove_dir_all(dir)?;
+    }
+    std::fs::create_dir_all(dir)?;
+    let result =
+        git2::Repository::init_opts(dir, git2::RepositoryInitOptions::new().initial_head("main"))?;
+    Ok(result)
+}
+
+// 打开或创建裸仓库（git init --bare），只有对象库和引用，没有工作目录，HEAD 初始指向 main
+pub fn open_or_init_bare_git_repo(
+    dir: impl AsRef<Path>,
+) -> Result<git2::Repository, Box<dyn std::error::Error>> {
+    let dir = dir.as_ref();
+    if let Ok(repo) = git2::Repository::open_bare(dir) {
+        return Ok(repo);
+    }
+    std::fs::create_dir_all(dir)?;
+    let result = git2::Repository::init_opts(
+        dir,
+        git2::RepositoryInitOptions::new()
+            .bare(true)
+            .initial_head("main"),
+    )?;
+    Ok(result)
+}
+
+// 需要工作目录的操作在裸仓库上调用时返回的错误，通过 Box<dyn Error> 返回，可用 downcast_ref 取得
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NotSupportedOnBare {
+    pub operation: &'static str,
+}
+
+impl std::fmt::Display for NotSupportedOnBare {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "裸仓库没有工作目录，不支持 {}", self.operation)
+    }
+}
+
+impl std::error::Error for NotSupportedOnBare {}
+
+// 返回仓库的工作目录，裸仓库返回 NotSupportedOnBare
+pub fn require_workdir<'r>(
+    repo: &'r git2::Repository,
+    operation: &'static str,
+) -> Result<&'r Path, NotSupportedOnBare> {
+    repo.workdir().ok_or(NotSupportedOnBare { operation })
+}
+
+// 将调用方传入的路径规范化为仓库内的相对路径：去掉 "." 分量，工作目录下的绝对路径转换为相对路径，
+// 拒绝 ".." 和工作目录之外的路径。git2 在写入 index / 查找 tree 时会统一使用 "/" 作为分隔符
+pub fn normalize_repo_path(
+    workdir: Option<&Path>,
+    path: &Path,
+) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let relative_path = match (path.is_absolute(), workdir) {
+        (true, Some(workdir)) => path
+            .strip_prefix(workdir)
+            .map_err(|_| format!("路径 {} 不在工作目录中", path.display()))?,
+        (true, None) => {
+            return Err(format!("仓库没有工作目录，无法使用绝对路径 {}", path.display()).into());
+        }
+        (false, _) => path,
+    };
+
+    let mut normalized = PathBuf::new();
+    for component in relative_path.components() {
+        match component {
+            std::path::Component::Normal(part) => normalized.push(part),
+            std::path::Component::CurDir => {}
+            _ => return Err(format!("路径 {} 不是仓库内的相对路径", path.display()).into()),
+        }
+    }
+    if normalized.as_os_str().is_empty() {
+        return Err(format!("路径 {} 为空", path.display()).into());
+    }
+    Ok(normalized)
+}
+
+// 以 "/" 分隔的路径字符串，用于报告、TreeEntry 等展示场景，非 UTF-8 字符会被替换
+pub fn repo_path_display(path: &Path) -> String {
+    path.components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+// 操作的结构化结果：记录变更、移除、跳过的条目和创建的对象，由调用方决定如何展示
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct OperationReport {
+    // 被新增或更新的条目（配置项、文件路径等）
+    pub changed: Vec<String>,
+    // 被移除的条目
+    pub removed: Vec<String>,
+    // 无需处理而跳过的条目
+    pub skipped: Vec<String>,
+    // 被 .gitignore 忽略而排除的路径
+    pub ignored: Vec<String>,
+    // 操作过程中写入对象库的对象
+    pub created_oids: Vec<git2::Oid>,
+}
+
+impl OperationReport {
+    // 没有任何变更（全部被跳过）
+    pub fn is_noop(&self) -> bool {
+        self.changed.is_empty() && self.removed.is_empty() && self.created_oids.is_empty()
+    }
+
+    pub fn merge(&mut self, other: OperationReport) {
+        self.changed.extend(other.changed);
+        self.removed.extend(other.removed);
+        self.skipped.extend(other.skipped);
+        self.ignored.extend(other.ignored);
+        self.created_oids.extend(other.created_oids);
+    }
+}
+
+impl std::fmt::Display for OperationReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for item in &self.changed {
+            writeln!(f, "变更: {}", item)?;
+        }
+        for item in &self.removed {
+            writeln!(f, "移除: {}", item)?;
+        }
+        for item in &self.skipped {
+            writeln!(f, "跳过: {}", item)?;
+        }
+        for item in &self.ignored {
+            writeln!(f, "忽略: {}", item)?;
+        }
+        for oid in &self.created_oids {
+            writeln!(f, "创建对象: {}", oid)?;
+        }
+        Ok(())
+    }
+}
+
+pub fn config_git_repo_kv_str(
+    config: &mut git2::Config,
+    name: &str,
+    value: &str,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let mut need_update = false;
+
+    // 先尝试获取现有值，如果不存在则认为需要更新
+    match config.get_string(name) {
+        Ok(old_value) => {
+            if old_value != value {
+                need_update = true;
+            }
+        }
+        Err(_) => {
+            // 配置项不存在，需要设置
+            need_update = true;
+        }
+    }
+
+    if need_update {
+        config.set_str(name, value)?;
+    }
+    Ok(need_update)
+}
+
+pub fn config_git_repo_kv_bool(
+    config: &mut git2::Config,
+    name: &str,
+    value: bool,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    // 配置项不存在或值不同时才写入
+    let need_update = config.get_bool(name).ok() != Some(value);
+    if need_update {
+        config.set_bool(name, value)?;
+    }
+    Ok(need_update)
+}
+
+pub fn config_git_repo_kv_i32(
+    config: &mut git2::Config,
+    name: &str,
+    value: i32,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let need_update = config.get_i32(name).ok() != Some(value);
+    if need_update {
+        config.set_i32(name, value)?;
+    }
+    Ok(need_update)
+}
+
+pub fn config_git_repo_kv_i64(
+    config: &mut git2::Config,
+    name: &str,
+    value: i64,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let need_update = config.get_i64(name).ok() != Some(value);
+    if need_update {
+        config.set_i64(name, value)?;
+    }
+    Ok(need_update)
+}
+
+// 多值配置项（如 remote.origin.fetch），现有的值与 values（含顺序）不同时整体替换。
+// 写入和删除只作用于 local 级别（.git/config），因此只与 local 级别中已有的值比较，
+// global / system 中同名的值不参与比较也不会被修改
+pub fn config_git_repo_kv_multivar(
+    config: &mut git2::Config,
+    name: &str,
+    values: &[&str],
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let mut old_values = Vec::new();
+    {
+        // 不是多级配置（如 git2::Config::new() 得到的空配置）时直接读取 config 本身
+        let local_config = config.open_level(git2::ConfigLevel::Local).ok();
+        let mut entries = local_config
+            .as_ref()
+            .unwrap_or(config)
+            .multivar(name, None)?;
+        while let Some(entry) = entries.next() {
+            old_values.push(entry?.value().unwrap_or_default().to_string());
+        }
+    }
+
+    if old_values == values {
+        return Ok(false);
+    }
+
+    // 删除所有旧值，配置项不存在时会返回 NotFound，忽略即可
+    if let Err(e) = config.remove_multivar(name, ".*")
+        && e.code() != git2::ErrorCode::NotFound
+    {
+        return Err(e.into());
+    }
+    for value in values {
+        // "$^" 不匹配任何已有的值，因此每次都是追加
+        config.set_multivar(name, "$^", value)?;
+    }
+    Ok(true)
+}
+
+// 可以写入 git 配置的值类型，config_git_repo_upsert 根据参数类型选择对应的写入方式
+pub trait ConfigValue {
+    fn upsert(
+        self,
+        config: &mut git2::Config,
+        name: &str,
+    ) -> Result<bool, Box<dyn std::error::Error>>;
+}
+
+impl ConfigValue for &str {
+    fn upsert(
+        self,
+        config: &mut git2::Config,
+        name: &str,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        config_git_repo_kv_str(config, name, self)
+    }
+}
+
+impl ConfigValue for bool {
+    fn upsert(
+        self,
+        config: &mut git2::Config,
+        name: &str,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        config_git_repo_kv_bool(config, name, self)
+    }
+}
+
+impl ConfigValue for i32 {
+    fn upsert(
+        self,
+        config: &mut git2::Config,
+        name: &str,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        config_git_repo_kv_i32(config, name, self)
+    }
+}
+
+impl ConfigValue for i64 {
+    fn upsert(
+        self,
+        config: &mut git2::Config,
+        name: &str,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        config_git_repo_kv_i64(config, name, self)
+    }
+}
+
+impl ConfigValue for &[&str] {
+    fn upsert(
+        self,
+        config: &mut git2::Config,
+        name: &str,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        config_git_repo_kv_multivar(config, name, self)
+    }
+}
+
+// 按值的类型写入配置项，值未变化时跳过，返回是否发生了写入
+pub fn config_git_repo_upsert<V: ConfigValue>(
+    config: &mut git2::Config,
+    name: &str,
+    value: V,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    value.upsert(config, name)
+}
+
+pub fn config_git_repo_user(
+    repo: &mut git2::Repository,
+    name: &str,
+    email: &str,
+) -> Result<OperationReport, Box<dyn std::error::Error>> {
+    let mut config = repo.config()?;
+    let mut report = OperationReport::default();
+    // 两项都需要检查和记录，任一项更新都不能跳过另一项
+    for (key, value) in [("user.name", name), ("user.email", email)] {
+        let item = format!("{} = {}", key, value);
+        if config_git_repo_kv_str(&mut config, key, value)? {
+            report.changed.push(item);
+        } else {
+            report.skipped.push(item);
+        }
+    }
+    Ok(report)
+}
+
+// index.write() 遇到 index.lock 被占用时的重试策略
+#[derive(Debug, Clone)]
+pub struct IndexWriteRetry {
+    // 最多尝试的次数（包含第一次）
+    pub max_attempts: u32,
+    // 第一次重试前的等待时间，之后每次翻倍
+    pub initial_backoff: std::time::Duration,
+}
+
+impl Default for IndexWriteRetry {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_backoff: std::time::Duration::from_millis(10),
+        }
+    }
+}
+
+// 写入 index，如果 index 被其他进程锁住（ErrorCode::Locked）则退避重试
+pub fn write_git_repo_index_with_retry(
+    index: &mut git2::Index,
+    retry: &IndexWriteRetry,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut backoff = retry.initial_backoff;
+    let mut attempt = 1;
+    loop {
+        match index.write() {
+            Ok(()) => return Ok(()),
+            Err(e) if e.code() == git2::ErrorCode::Locked && attempt < retry.max_attempts => {
+                std::thread::sleep(backoff);
+                backoff *= 2;
+                attempt += 1;
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+// 同 add_files_to_git_repo_index_with_retry，使用默认的重试策略
+pub fn add_files_to_git_repo_index<I, P>(
+    repo: &mut git2::Repository,
+    file_relative_paths: I,
+) -> Result<(git2::Index, OperationReport), Box<dyn std::error::Error>>
+where
+    I: IntoIterator<Item = P>,
+    P: AsRef<Path>,
+{
+    add_files_to_git_repo_index_with_retry(repo, file_relative_paths, &IndexWriteRetry::default())
+}
+
+// 返回写入后的 index 和操作报告：新增或内容有变化的文件记为 changed（blob oid 记入 created_oids），
+// 与 index 中已有条目相同的文件和磁盘上不存在的文件记为 skipped（已跟踪的文件不会从 index 中移除，
+// 需要暂存删除时使用 remove_files_from_git_repo）
+pub fn add_files_to_git_repo_index_with_retry<I, P>(
+    repo: &mut git2::Repository,
+    file_relative_paths: I,
+    retry: &IndexWriteRetry,
+) -> Result<(git2::Index, OperationReport), Box<dyn std::error::Error>>
+where
+    I: IntoIterator<Item = P>,
+    P: AsRef<Path>,
+{
+    let options = AddOptions {
+        skip_ignored: false,
+        retry: retry.clone(),
+    };
+    add_files_to_git_repo_index_with_options(repo, file_relative_paths, &options)
+}
+
+// 暂存文件的选项
+#[derive(Debug, Clone)]
+pub struct AddOptions {
+    // 跳过被 .gitignore 忽略且尚未跟踪的文件并记入报告的 ignored（与 git add 一致），默认开启
+    pub skip_ignored: bool,
+    pub retry: IndexWriteRetry,
+}
+
+impl Default for AddOptions {
+    fn default() -> Self {
+        Self {
+            skip_ignored: true,
+            retry: IndexWriteRetry::default(),
+        }
+    }
+}
+
+// 同 add_files_to_git_repo_index_with_retry，skip_ignored 开启时被忽略的文件记为 ignored 而不加入 index
+pub fn add_files_to_git_repo_index_with_options<I, P>(
+    repo: &mut git2::Repository,
+    file_relative_paths: I,
+    options: &AddOptions,
+) -> Result<(git2::Index, OperationReport), Box<dyn std::error::Error>>
+where
+    I: IntoIterator<Item = P>,
+    P: AsRef<Path>,
+{
+    let workdir = require_workdir(repo, "git add")?;
+    let mut index = repo.index()?;
+    let mut report = OperationReport::default();
+    // core.filemode 为 false 时文件系统的权限位不可信（如 Windows），沿用 index 中已记录的可执行位
+    let trust_filemode = repo.config()?.get_bool("core.filemode").unwrap_or(true);
+
+    for file_relative_path in file_relative_paths {
+        let path = normalize_repo_path(Some(workdir), file_relative_path.as_ref())?;
+        let file_path = workdir.join(&path);
+
+        // 已跟踪的文件即使匹配 .gitignore 也照常更新
+        if options.skip_ignored
+            && path_exists_no_follow(&file_path)
+            && index.get_path(&path, 0).is_none()
+            && repo.is_path_ignored(&path)?
+        {
+            report.ignored.push(repo_path_display(&path));
+            continue;
+        }
+
+        // 工作目录中的普通文件是 materialize_git_repo_symlinks 生成的副本时不能当作普通文件暂存，
+        // 副本与链接目标内容一致时跳过，否则说明副本被修改过，返回错误
+        if let Some(entry) = index.get_path(&path, 0)
+            && entry.mode == u32::from(git2::FileMode::Link)
+            && entry.flags & GIT_INDEX_ENTRY_VALID != 0
+            && fs::symlink_metadata(&file_path).is_ok_and(|metadata| metadata.is_file())
+        {
+            let unchanged = match materialized_link_target(repo, workdir, &entry)? {
+                Some(target_path) => fs::read(&target_path)? == fs::read(&file_path)?,
+                None => false,
+            };
+            if !unchanged {
+                return Err(format!(
+                    "{} 是符号链接的副本，内容与链接目标不一致，请修改链接目标",
+                    repo_path_display(&path)
+                )
+                .into());
+            }
+            report.skipped.push(repo_path_display(&path));
+            continue;
+        }
+
+        if path_exists_no_follow(&file_path) {
+            let previous = index.get_path(&path, 0).map(|entry| (entry.id, entry.mode));
+            index.add_path(&path)?;
+            if let Some(mut entry) = index.get_path(&path, 0) {
+                let executable = u32::from(git2::FileMode::BlobExecutable);
+                if !trust_filemode
+                    && previous.map(|(_, mode)| mode) == Some(executable)
+                    && entry.mode == u32::from(git2::FileMode::Blob)
+                {
+                    entry.mode = executable;
+                    index.add(&entry)?;
+                }
+                // 内容和模式都与 index 中已有的条目相同时没有变更
+                if previous == Some((entry.id, entry.mode)) {
+                    report.skipped.push(repo_path_display(&path));
+                } else {
+                    report.changed.push(repo_path_display(&path));
+                    report.created_oids.push(entry.id);
+                }
+            }
+        } else {
+            // 文件不存在时不修改 index，暂存删除需要显式调用 remove_files_from_git_repo
+            report.skipped.push(repo_path_display(&path));
+        }
+    }
+    write_git_repo_index_with_retry(&mut index, &options.retry)?;
+    Ok((index, report))
+}
+
+// 路径本身是否存在，不跟随符号链接，指向不存在目标的符号链接也视为存在
+fn path_exists_no_follow(path: &Path) -> bool {
+    fs::symlink_metadata(path).is_ok()
+}
+
+// git add -A：暂存工作目录中所有新增、修改和删除的文件（遵循 .gitignore），不需要逐个列出路径。
+// 报告中新增和修改的文件记为 changed（blob oid 记入 created_oids），已删除的文件记为 removed
+pub fn add_all_changes_to_index(
+    repo: &mut git2::Repository,
+) -> Result<(git2::Index, OperationReport), Box<dyn std::error::Error>> {
+    let workdir = require_workdir(repo, "git add -A")?;
+    let mut index = repo.index()?;
+    let mut changed = Vec::new();
+    let mut removed = Vec::new();
+
+    // 先更新已跟踪的文件（包括删除），再添加未跟踪的文件
+    index.update_all(
+        ["*"],
+        Some(&mut |path: &Path, _: &[u8]| {
+            if path_exists_no_follow(&workdir.join(path)) {
+                changed.push(path.to_path_buf());
+            } else {
+                removed.push(repo_path_display(path));
+            }
+            0
+        }),
+    )?;
+    index.add_all(
+        ["*"],
+        git2::IndexAddOption::DEFAULT,
+        Some(&mut |path: &Path, _: &[u8]| {
+            if !changed.iter().any(|changed_path| changed_path == path) {
+                changed.push(path.to_path_buf());
+            }
+            0
+        }),
+    )?;
+    write_git_repo_index_with_retry(&mut index, &IndexWriteRetry::default())?;
+
+    let mut report = OperationReport {
+        removed,
+        ..Default::default()
+    };
+    for path in changed {
+        if let Some(entry) = index.get_path(&path, 0) {
+            report.created_oids.push(entry.id);
+        }
+        report.changed.push(repo_path_display(&path));
+    }
+    Ok((index, report))
+}
+
+// 修改 index 中已跟踪文件的模式（git update-index --chmod=+x/-x），不修改工作目录中的文件权限。
+// 在 core.filemode 为 false 的平台（如 Windows）上，之后再次暂存该文件时 libgit2 会沿用 index 中的模式
+pub fn set_index_entry_mode(
+    repo: &mut git2::Repository,
+    file_relative_path: impl AsRef<Path>,
+    mode: git2::FileMode,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if !matches!(mode, git2::FileMode::Blob | git2::FileMode::BlobExecutable) {
+        return Err(format!("只能设置普通文件或可执行文件模式，不支持 {:?}", mode).into());
+    }
+    let path = normalize_repo_path(repo.workdir(), file_relative_path.as_ref())?;
+    let mut index = repo.index()?;
+    let mut entry = index
+        .get_path(&path, 0)
+        .ok_or_else(|| format!("文件 {} 未被跟踪", repo_path_display(&path)))?;
+    entry.mode = u32::from(mode);
+    index.add(&entry)?;
+    write_git_repo_index_with_retry(&mut index, &IndexWriteRetry::default())?;
+    Ok(())
+}
+
+// 显式地在 index 中记录文件删除（git rm --cached），不依赖文件在磁盘上是否存在
+pub fn stage_removal(
+    repo: &mut git2::Repository,
+    file_relative_path: impl AsRef<Path>,
+) -> Result<OperationReport, Box<dyn std::error::Error>> {
+    let (_, report) = remove_files_from_git_repo(repo, [file_relative_path], true)?;
+    Ok(report)
+}
+
+// git rm [-r] [--cached] <path>...：从 index 中移除文件（目录会移除其下所有已跟踪的文件），
+// cached_only 为 false 时同时删除工作目录中的文件及删除后变空的目录。
+// 任一路径未被跟踪时返回错误，不做任何修改
+pub fn remove_files_from_git_repo<I, P>(
+    repo: &mut git2::Repository,
+    file_relative_paths: I,
+    cached_only: bool,
+) -> Result<(git2::Index, OperationReport), Box<dyn std::error::Error>>
+where
+    I: IntoIterator<Item = P>,
+    P: AsRef<Path>,
+{
+    let workdir = if cached_only {
+        repo.workdir()
+    } else {
+        Some(require_workdir(repo, "git rm")?)
+    };
+    let mut index = repo.index()?;
+
+    let mut to_remove = Vec::new();
+    for file_relative_path in file_relative_paths {
+        let path = repo_path_display(&normalize_repo_path(
+            repo.workdir(),
+            file_relative_path.as_ref(),
+        )?);
+        let dir_prefix = format!("{}/", path);
+        let matched: Vec<String> = index
+            .iter()
+            .map(|entry| String::from_utf8_lossy(&entry.path).to_string())
+            .filter(|entry_path| *entry_path == path || entry_path.starts_with(&dir_prefix))
+            .collect();
+        if matched.is_empty() {
+            return Err(format!("路径 {} 没有匹配任何已跟踪的文件", path).into());
+        }
+        for entry_path in matched {
+            if !to_remove.contains(&entry_path) {
+                to_remove.push(entry_path);
+            }
+        }
+    }
+
+    let mut report = OperationReport::default();
+    for entry_path in to_remove {
+        // 冲突中的文件各个 stage 一并移除
+        index.remove_path(Path::new(&entry_path))?;
+        if !cached_only && let Some(workdir) = workdir {
+            let file_path = workdir.join(&entry_path);
+            if path_exists_no_follow(&file_path) {
+                fs::remove_file(&file_path)?;
+            }
+            // 删除变空的父目录，直到工作目录根
+            let mut parent = file_path.parent();
+            while let Some(dir) = parent
+                && dir != workdir
+                && fs::remove_dir(dir).is_ok()
+            {
+                parent = dir.parent();
+            }
+        }
+        report.removed.push(entry_path);
+    }
+    write_git_repo_index_with_retry(&mut index, &IndexWriteRetry::default())?;
+    Ok((index, report))
+}
+
+// 提交选项，通过 CommitOptionsBuilder 构造
+#[derive(Debug, Clone)]
+pub struct CommitOptions {
+    // 作者 (name, email)，None 时按 resolve_signature 的顺序确定
+    author: Option<(String, String)>,
+    // 提交者 (name, email)，None 时按 resolve_signature 的顺序确定
+    committer: Option<(String, String)>,
+    // 作者和提交者的时间，None 时使用当前时间
+    time: Option<git2::Time>,
+    // 为 false 时，如果 index 的 tree 与父 commit 的 tree 相同，则不创建新的 commit
+    allow_empty: bool,
+    // 提交后更新的引用，同时以它当前指向的 commit 作为父节点；
+    // None 时只创建 commit 对象而不更新任何引用（类似 git commit-tree -p HEAD），父节点为当前 HEAD
+    update_ref: Option<String>,
+}
+
+impl Default for CommitOptions {
+    fn default() -> Self {
+        CommitOptions {
+            author: None,
+            committer: None,
+            time: None,
+            allow_empty: false,
+            update_ref: Some("HEAD".to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CommitOptionsBuilder {
+    options: CommitOptions,
+}
+
+impl CommitOptionsBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn author(mut self, name: &str, email: &str) -> Self {
+        self.options.author = Some((name.to_string(), email.to_string()));
+        self
+    }
+
+    pub fn committer(mut self, name: &str, email: &str) -> Self {
+        self.options.committer = Some((name.to_string(), email.to_string()));
+        self
+    }
+
+    // seconds 为 unix 时间戳，offset_minutes 为时区偏移（如东八区为 480）
+    pub fn time(mut self, seconds: i64, offset_minutes: i32) -> Self {
+        self.options.time = Some(git2::Time::new(seconds, offset_minutes));
+        self
+    }
+
+    pub fn allow_empty(mut self, allow_empty: bool) -> Self {
+        self.options.allow_empty = allow_empty;
+        self
+    }
+
+    pub fn update_ref(mut self, ref_name: Option<&str>) -> Self {
+        self.options.update_ref = ref_name.map(|s| s.to_string());
+        self
+    }
+
+    pub fn build(self) -> CommitOptions {
+        self.options
+    }
+}
+
+// 签名身份的用途：作者读取 GIT_AUTHOR_* 环境变量，提交者和 tagger 读取 GIT_COMMITTER_*
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignatureRole {
+    Author,
+    Committer,
+}
+
+// 显式签名、环境变量和仓库配置都没有时使用的默认身份
+pub const DEFAULT_SIGNATURE_NAME: &str = "git2-rs-demo";
+pub const DEFAULT_SIGNATURE_EMAIL: &str = "git2-rs-demo@localhost";
+
+// 按 显式传入 → 环境变量 → 仓库配置（user.name / user.email）→ 默认身份 的顺序确定签名
+// 适用于 CI 容器等没有配置 user.name / user.email 的环境
+pub fn resolve_signature(
+    repo: &git2::Repository,
+    explicit: Option<&git2::Signature>,
+    role: SignatureRole,
+) -> Result<git2::Signature<'static>, Box<dyn std::error::Error>> {
+    resolve_signature_with_env(repo, explicit, role, |key| std::env::var(key).ok())
+}
+
+// env 用于读取环境变量，便于测试时注入
+fn resolve_signature_with_env<E>(
+    repo: &git2::Repository,
+    explicit: Option<&git2::Signature>,
+    role: SignatureRole,
+    env: E,
+) -> Result<git2::Signature<'static>, Box<dyn std::error::Error>>
+where
+    E: Fn(&str) -> Option<String>,
+{
+    if let Some(signature) = explicit {
+        return Ok(signature.to_owned());
+    }
+
+    let (name_key, email_key) = match role {
+        SignatureRole::Author => ("GIT_AUTHOR_NAME", "GIT_AUTHOR_EMAIL"),
+        SignatureRole::Committer => ("GIT_COMMITTER_NAME", "GIT_COMMITTER_EMAIL"),
+    };
+    if let (Some(name), Some(email)) = (env(name_key), env(email_key)) {
+        return Ok(git2::Signature::now(&name, &email)?);
+    }
+
+    if let Ok(signature) = repo.signature() {
+        return Ok(signature.to_owned());
+    }
+
+    Ok(git2::Signature::now(
+        DEFAULT_SIGNATURE_NAME,
+        DEFAULT_SIGNATURE_EMAIL,
+    )?)
+}
+
+// 根据指定的身份和时间构造签名，身份缺省时按 resolve_signature 的顺序确定，时间缺省时使用当前时间
+pub fn commit_signature(
+    repo: &git2::Repository,
+    identity: &Option<(String, String)>,
+    role: SignatureRole,
+    time: Option<git2::Time>,
+) -> Result<git2::Signature<'static>, Box<dyn std::error::Error>> {
+    let (name, email) = match identity {
+        Some((name, email)) => (name.clone(), email.clone()),
+        None => {
+            let signature = resolve_signature(repo, None, role)?;
+            (
+                signature
+                    .name()
+                    .ok_or("签名名称不是合法的 UTF-8")?
+                    .to_string(),
+                signature
+                    .email()
+                    .ok_or("签名邮箱不是合法的 UTF-8")?
+                    .to_string(),
+            )
+        }
+    };
+    let signature = match time {
+        Some(time) => git2::Signature::new(&name, &email, &time)?,
+        None => git2::Signature::now(&name, &email)?,
+    };
+    Ok(signature)
+}
+
+// 提交 index，父 commit 为 options.update_ref（未指定时为 HEAD）当前指向的 commit，引用不存在时为根提交
+// allow_empty 为 false 时，如果 index 的 tree 与父 commit 的 tree 相同，则不创建新的 commit，直接返回父 commit
+pub fn commit_index_to_git_repo(
+    repo: &mut git2::Repository,
+    mut index: git2::Index,
+    message: &str,
+    options: &CommitOptions,
+) -> Result<git2::Oid, Box<dyn std::error::Error>> {
+    // 写入 repo 的对象库，不依赖 index 所属的仓库，裸仓库可以提交 git2::Index::new() 创建的内存 index
+    let tree_id = index.write_tree_to(repo)?;
+    let tree = repo.find_tree(tree_id)?;
+
+    let author = commit_signature(repo, &options.author, SignatureRole::Author, options.time)?;
+    let committer = commit_signature(
+        repo,
+        &options.committer,
+        SignatureRole::Committer,
+        options.time,
+    )?;
+
+    // 获取要更新的引用（不更新引用时为 HEAD）当前指向的 commit，如果是第一次提交则为 None
+    let parent_ref = options.update_ref.as_deref().unwrap_or("HEAD");
+    let parent_commit = match repo.find_reference(parent_ref).and_then(|r| r.resolve()) {
+        Ok(reference) => Some(reference.peel_to_commit()?),
+        Err(_) => None,
+    };
+
+    if let Some(commit) = &parent_commit
+        && !options.allow_empty
+        && commit.tree_id() == tree_id
+    {
+        return Ok(commit.id());
+    }
+
+    let parents: Vec<&git2::Commit> = match &parent_commit {
+        Some(commit) => vec![commit],
+        None => vec![],
+    };
+
+    let commit_id = repo.commit(
+        options.update_ref.as_deref(),
+        &author,
+        &committer,
+        message,
+        &tree,
+        &parents,
+    )?;
+
+    Ok(commit_id)
+}
+
+// 可复现提交使用的固定签名身份
+pub const REPRODUCIBLE_SIGNATURE_NAME: &str = "reproducible";
+pub const REPRODUCIBLE_SIGNATURE_EMAIL: &str = "reproducible@localhost";
+
+// 使用固定的签名身份和时间（epoch，时区偏移 0）提交，相同内容在不同环境中得到相同的 commit id
+pub fn commit_reproducible(
+    repo: &mut git2::Repository,
+    index: git2::Index,
+    message: &str,
+    epoch: i64,
+) -> Result<git2::Oid, Box<dyn std::error::Error>> {
+    let options = CommitOptionsBuilder::new()
+        .author(REPRODUCIBLE_SIGNATURE_NAME, REPRODUCIBLE_SIGNATURE_EMAIL)
+        .committer(REPRODUCIBLE_SIGNATURE_NAME, REPRODUCIBLE_SIGNATURE_EMAIL)
+        .time(epoch, 0)
+        .allow_empty(true)
+        .build();
+    commit_index_to_git_repo(repo, index, message, &options)
+}
+
+// 构造 tree 时的目录节点：叶子记录对象 oid 和模式（blob、符号链接或已存在的子 tree），子目录递归构建
+enum SnapshotNode {
+    Entry(git2::Oid, i32),
+    Dir(SnapshotDir),
+}
+
+type SnapshotDir = std::collections::BTreeMap<String, SnapshotNode>;
+
+// 自底向上用 TreeBuilder 写入目录节点对应的 tree，返回 tree id
+fn write_snapshot_tree(
+    repo: &git2::Repository,
+    entries: &SnapshotDir,
+) -> Result<git2::Oid, Box<dyn std::error::Error>> {
+    let mut builder = repo.treebuilder(None)?;
+    for (name, node) in entries {
+        match node {
+            SnapshotNode::Entry(oid, mode) => {
+                builder.insert(name, *oid, *mode)?;
+            }
+            SnapshotNode::Dir(children) => {
+                let tree_id = write_snapshot_tree(repo, children)?;
+                builder.insert(name, tree_id, i32::from(git2::FileMode::Tree))?;
+            }
+        }
+    }
+    Ok(builder.write()?)
+}
+
+// 按 / 分隔的路径找到条目所在的目录节点（中间目录不存在时创建），返回该目录和条目名
+fn snapshot_parent_dir<'m>(
+    root: &'m mut SnapshotDir,
+    path: &str,
+) -> Result<(&'m mut SnapshotDir, String), Box<dyn std::error::Error>> {
+    let components: Vec<&str> = path.split('/').collect();
+    if components
+        .iter()
+        .any(|component| component.is_empty() || *component == "." || *component == "..")
+    {
+        return Err(format!("非法的文件路径: {:?}", path).into());
+    }
+
+    let (name, dirs) = components.split_last().unwrap();
+    let mut current = root;
+    for dir in dirs {
+        let node = current
+            .entry(dir.to_string())
+            .or_insert_with(|| SnapshotNode::Dir(std::collections::BTreeMap::new()));
+        current = match node {
+            SnapshotNode::Dir(children) => children,
+            SnapshotNode::Entry(..) => {
+                return Err(format!("路径 {} 与同名文件冲突", path).into());
+            }
+        };
+    }
+    Ok((current, name.to_string()))
+}
+
+// 直接由 (路径, 内容, 模式) 列表构造多层 tree 并创建 commit，完全不经过工作目录和 index，裸仓库同样可用。
+// 路径使用 / 分隔；只创建 commit 对象，不更新任何引用，需要时可调用 update_git_repo_head_to_commit
+pub fn commit_tree_snapshot<P, C>(
+    repo: &git2::Repository,
+    files: Vec<(P, C, git2::FileMode)>,
+    message: &str,
+    parent: Option<git2::Oid>,
+) -> Result<git2::Oid, Box<dyn std::error::Error>>
+where
+    P: AsRef<str>,
+    C: AsRef<[u8]>,
+{
+    let mut root = std::collections::BTreeMap::new();
+    for (path, content, mode) in files {
+        let path = path.as_ref();
+        if !matches!(
+            mode,
+            git2::FileMode::Blob | git2::FileMode::BlobExecutable | git2::FileMode::Link
+        ) {
+            return Err(format!("文件 {} 不支持模式 {:?}", path, mode).into());
+        }
+        let (dir, file_name) = snapshot_parent_dir(&mut root, path)?;
+        if dir.contains_key(&file_name) {
+            return Err(format!("路径 {} 重复或与同名目录冲突", path).into());
+        }
+        let blob_id = write_blob_to_git_repo(repo, content.as_ref())?;
+        dir.insert(file_name, SnapshotNode::Entry(blob_id, i32::from(mode)));
+    }
+
+    let tree_id = write_snapshot_tree(repo, &root)?;
+    let tree = repo.find_tree(tree_id)?;
+    let author = resolve_signature(repo, None, SignatureRole::Author)?;
+    let committer = resolve_signature(repo, None, SignatureRole::Committer)?;
+    let parent_commit = match parent {
+        Some(oid) => Some(repo.find_commit(oid)?),
+        None => None,
+    };
+    let parents: Vec<&git2::Commit> = parent_commit.iter().collect();
+
+    let commit_id = repo.commit(None, &author, &committer, message, &tree, &parents)?;
+    Ok(commit_id)
+}
+
+// 将 HEAD（或 HEAD 指向的分支）更新到指定 commit，兼容还没有任何提交的空仓库
+pub fn update_git_repo_head_to_commit(
+    repo: &git2::Repository,
+    commit_oid: git2::Oid,
+    log_message: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let head_ref = repo.find_reference("HEAD")?;
+    match head_ref.symbolic_target() {
+        Some(branch_ref_name) => {
+            repo.reference(branch_ref_name, commit_oid, true, log_message)?;
+        }
+        None => {
+            repo.set_head_detached(commit_oid)?;
+        }
+    }
+    Ok(())
+}
+
+// 使用 signer 对 commit 内容进行签名后再提交，签名写入 gpgsig 头
+pub fn commit_signed<F>(
+    repo: &mut git2::Repository,
+    mut index: git2::Index,
+    message: &str,
+    signer: F,
+) -> Result<git2::Oid, Box<dyn std::error::Error>>
+where
+    F: Fn(&str) -> Result<String, Box<dyn std::error::Error>>,
+{
+    let tree_id = index.write_tree()?;
+    let tree = repo.find_tree(tree_id)?;
+
+    let author = resolve_signature(repo, None, SignatureRole::Author)?;
+    let committer = resolve_signature(repo, None, SignatureRole::Committer)?;
+
+    // 获取 HEAD 引用，如果是第一次提交则为 None
+    let parent_commit = match repo.head() {
+        Ok(head) => Some(head.peel_to_commit()?),
+        Err(_) => None,
+    };
+
+    let parents: Vec<&git2::Commit> = match &parent_commit {
+        Some(commit) => vec![commit],
+        None => vec![],
+    };
+
+    // 先生成未签名的 commit 内容，再交给 signer 签名
+    let commit_buf = repo.commit_create_buffer(&author, &committer, message, &tree, &parents)?;
+    let commit_content = commit_buf.as_str().ok_or("commit 内容不是合法的 UTF-8")?;
+    let commit_signature = signer(commit_content)?;
+
+    // commit_signed 只写入对象，不会移动 HEAD，需要手动更新
+    let commit_id = repo.commit_signed(commit_content, &commit_signature, Some("gpgsig"))?;
+    update_git_repo_head_to_commit(repo, commit_id, &format!("commit (signed): {}", message))?;
+
+    Ok(commit_id)
+}
+
+// 根据 user.signingkey / gpg.program 调用 gpg 对内容进行分离签名，等价于 git 的默认行为
+pub fn sign_with_gpg_program(
+    gpg_program: &str,
+    signing_key: &str,
+    content: &str,
+) -> Result<String, Box<dyn std::error::Error>> {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    let mut child = Command::new(gpg_program)
+        .args(["--status-fd=2", "-bsau", signing_key])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    child
+        .stdin
+        .take()
+        .ok_or("无法打开 gpg 的标准输入")?
+        .write_all(content.as_bytes())?;
+
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(format!(
+            "gpg 签名失败: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+        .into());
+    }
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+// 读取 commit.gpgsign 配置，开启时走签名提交，否则与 commit_index_to_git_repo 行为一致
+pub fn commit_maybe_signed(
+    repo: &mut git2::Repository,
+    index: git2::Index,
+    message: &str,
+) -> Result<git2::Oid, Box<dyn std::error::Error>> {
+    let config = repo.config()?.snapshot()?;
+    let gpg_sign = config.get_bool("commit.gpgsign").unwrap_or(false);
+    if !gpg_sign {
+        return commit_index_to_git_repo(repo, index, message, &CommitOptions::default());
+    }
+
+    // 没有配置 user.signingkey 时与 git 一致，使用提交者身份作为签名 key
+    let signing_key = match config.get_string("user.signingkey") {
+        Ok(key) => key,
+        Err(_) => {
+            let signature = resolve_signature(repo, None, SignatureRole::Committer)?;
+            format!(
+                "{} <{}>",
+                signature.name().unwrap_or(""),
+                signature.email().unwrap_or("")
+            )
+        }
+    };
+    let gpg_program = config
+        .get_string("gpg.program")
+        .unwrap_or_else(|_| "gpg".to_string());
+
+    commit_signed(repo, index, message, |content| {
+        sign_with_gpg_program(&gpg_program, &signing_key, content)
+    })
+}
+
+pub fn upsert_tag_to_git_repo<'a>(
+    repo: &'a mut git2::Repository,
+    tag_name: &str,
+    message: &str,
+    target_oid: Option<git2::Oid>,
+) -> Result<git2::Reference<'a>, Box<dyn std::error::Error>> {
+    upsert_tag_to_git_repo_with_signature(repo, tag_name, message, target_oid, None)
+}
+
+// tagger 为 None 时按 resolve_signature 的顺序确定 tagger 身份
+pub fn upsert_tag_to_git_repo_with_signature<'a>(
+    repo: &'a mut git2::Repository,
+    tag_name: &str,
+    message: &str,
+    target_oid: Option<git2::Oid>,
+    tagger: Option<&git2::Signature>,
+) -> Result<git2::Reference<'a>, Box<dyn std::error::Error>> {
+    let signature = resolve_signature(repo, tagger, SignatureRole::Committer)?;
+
+    // 确定标签指向的目标对象，如果没有指定则使用 HEAD
+    let target_commit = match target_oid {
+        Some(oid) => repo.find_commit(oid)?,
+        None => {
+            let head = repo.head()?;
+            let oid = head.target().unwrap();
+            repo.find_commit(oid)?
+        }
+    };
+
+    let target = target_commit.as_object();
+
+    let tag_ref_name = format!("refs/tags/{}", tag_name);
+
+    // 创建标签，已存在时覆盖
+    repo.tag(tag_name, target, &signature, message, true)?;
+
+    // 返回标签引用
+    let tag_ref = repo.find_reference(&tag_ref_name)?;
+
+    Ok(tag_ref)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnnotatedTagInfo {
+    pub name: String,
+    // tag 对象自身的 oid
+    pub tag_id: git2::Oid,
+    // 标签直接指向的对象及其类型（通常是 commit，也可以是 tree、blob 或另一个 tag）
+    pub target: git2::Oid,
+    pub target_kind: git2::ObjectType,
+    pub tagger_name: Option<String>,
+    pub tagger_email: Option<String>,
+    pub tag_time: Option<i64>,
+    pub message: Option<String>,
+}
+
+// git cat-file -p <tag>：读取附注标签的信息，轻量标签（引用直接指向目标对象）返回 None，标签不存在时返回错误
+pub fn read_tag(
+    repo: &git2::Repository,
+    tag_name: &str,
+) -> Result<Option<AnnotatedTagInfo>, Box<dyn std::error::Error>> {
+    let tag_ref_name = format!("refs/tags/{}", tag_name);
+    let tag_ref = repo.find_reference(&tag_ref_name)?;
+    let tag_oid = tag_ref.target().ok_or("标签引用不是直接引用")?;
+
+    // 轻量标签的目标不是 tag 对象
+    let tag = match repo.find_object(tag_oid, None)?.into_tag() {
+        Ok(tag) => tag,
+        Err(_) => return Ok(None),
+    };
+
+    let tagger = tag.tagger();
+    Ok(Some(AnnotatedTagInfo {
+        name: tag.name().unwrap_or(tag_name).to_string(),
+        tag_id: tag.id(),
+        target: tag.target_id(),
+        target_kind: tag.target_type().ok_or("无法识别标签指向的对象类型")?,
+        tagger_name: tagger
+            .as_ref()
+            .and_then(|t| t.name().map(|s| s.to_string())),
+        tagger_email: tagger
+            .as_ref()
+            .and_then(|t| t.email().map(|s| s.to_string())),
+        tag_time: tagger.as_ref().map(|t| t.when().seconds()),
+        message: tag.message().map(|s| s.to_string()),
+    }))
+}
+
+// 读取 refs/tags/<name> 指向的附注标签的 tagger、消息、目标对象 oid 及其类型，
+// 用于读回 upsert_tag_to_git_repo 写入的内容。与 read_tag 相同，轻量标签返回 None，标签不存在时返回错误
+pub fn get_git_repo_tag_info(
+    repo: &git2::Repository,
+    tag_name: &str,
+) -> Result<Option<AnnotatedTagInfo>, Box<dyn std::error::Error>> {
+    read_tag(repo, tag_name)
+}
+
+#[derive(Debug)]
+pub struct TagListEntry {
+    pub name: String,
+    // 逐层剥离 tag 对象后最终指向的对象（通常是 commit）
+    pub target: git2::Oid,
+    // 附注标签的 tagger 和消息，轻量标签为 None
+    pub annotation: Option<AnnotatedTagInfo>,
+}
+
+impl TagListEntry {
+    pub fn is_annotated(&self) -> bool {
+        self.annotation.is_some()
+    }
+}
+
+// 列出标签（git tag -l <glob>），glob 如 "release-*"，为 None 时列出全部标签，按名称排序
+pub fn list_git_repo_tags(
+    repo: &git2::Repository,
+    glob: Option<&str>,
+) -> Result<Vec<TagListEntry>, Box<dyn std::error::Error>> {
+    let tag_names = repo.tag_names(glob)?;
+    let mut tags = Vec::new();
+    // 跳过名称不是合法 UTF-8 的标签
+    for tag_name in tag_names.iter().flatten() {
+        let tag_ref = repo.find_reference(&format!("refs/tags/{}", tag_name))?;
+        tags.push(TagListEntry {
+            name: tag_name.to_string(),
+            target: tag_ref.peel(git2::ObjectType::Any)?.id(),
+            annotation: read_tag(repo, tag_name)?,
+        });
+    }
+    tags.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(tags)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeletedTag {
+    pub name: String,
+    // refs/tags/<name> 删除前指向的对象，附注标签为 tag 对象，轻量标签为 commit
+    pub target: git2::Oid,
+    pub is_annotated: bool,
+    // 附注标签的 tag 对象是否已不被任何引用（包括嵌套的 tag 对象）引用，可以被 gc 回收
+    pub tag_object_unreachable: bool,
+}
+
+// 删除标签（git tag -d），并报告附注标签的 tag 对象是否因此变得不可达
+pub fn delete_git_repo_tag(
+    repo: &mut git2::Repository,
+    tag_name: &str,
+) -> Result<DeletedTag, Box<dyn std::error::Error>> {
+    let tag_ref_name = format!("refs/tags/{}", tag_name);
+    let target = repo
+        .find_reference(&tag_ref_name)?
+        .target()
+        .ok_or("标签引用不是直接引用")?;
+    let is_annotated = repo.find_tag(target).is_ok();
+
+    repo.tag_delete(tag_name)?;
+
+    let mut tag_object_unreachable = is_annotated;
+    if is_annotated {
+        // 沿每个引用的 tag 链查找，仍能到达该 tag 对象则说明它还可达
+        'references: for reference in repo.references()? {
+            let mut oid = match reference?.target() {
+                Some(oid) => oid,
+                None => continue,
+            };
+            loop {
+                if oid == target {
+                    tag_object_unreachable = false;
+                    break 'references;
+                }
+                match repo.find_tag(oid) {
+                    Ok(tag) => oid = tag.target_id(),
+                    Err(_) => break,
+                }
+            }
+        }
+    }
+
+    Ok(DeletedTag {
+        name: tag_name.to_string(),
+        target,
+        is_annotated,
+        tag_object_unreachable,
+    })
+}
+
+pub fn upsert_branch_to_git_repo<'a>(
+    repo: &'a mut git2::Repository,
+    branch_name: &str,
+    target_oid: Option<git2::Oid>,
+) -> Result<git2::Reference<'a>, Box<dyn std::error::Error>> {
+    // 确定分支指向的目标 commit，如果没有指定则使用 HEAD
+    let target_commit = match target_oid {
+        Some(oid) => repo.find_commit(oid)?,
+        None => {
+            let head = repo.head()?;
+            let oid = head.target().unwrap();
+            repo.find_commit(oid)?
+        }
+    };
+
+    let branch_ref_name = format!("refs/heads/{}", branch_name);
+
+    // 创建分支，已存在时覆盖
+    repo.branch(branch_name, &target_commit, true)?;
+
+    // 返回分支引用
+    let branch_ref = repo.find_reference(&branch_ref_name)?;
+
+    Ok(branch_ref)
+}
+
+// 重命名本地分支（git branch -m/-M），upstream 配置（branch.<name>.*）和 reflog 会随分支一起迁移，
+// 重命名当前分支时 HEAD 也会指向新分支。force 为 false 且新分支已存在时返回错误
+pub fn rename_git_repo_branch<'a>(
+    repo: &'a mut git2::Repository,
+    old_branch_name: &str,
+    new_branch_name: &str,
+    force: bool,
+) -> Result<git2::Reference<'a>, Box<dyn std::error::Error>> {
+    let mut branch = repo.find_branch(old_branch_name, git2::BranchType::Local)?;
+    let renamed = match branch.rename(new_branch_name, force) {
+        Ok(renamed) => renamed,
+        Err(e) if e.code() == git2::ErrorCode::Exists => {
+            return Err(format!("分支 {} 已存在", new_branch_name).into());
+        }
+        Err(e) => return Err(e.into()),
+    };
+    Ok(renamed.into_reference())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BranchInfo {
+    // 本地分支为 "main"，远程跟踪分支为 "origin/main"
+    pub name: String,
+    pub is_remote: bool,
+    pub tip: git2::Oid,
+    // 最新 commit 消息的首行
+    pub summary: String,
+    pub is_head: bool,
+    // 本地分支跟踪的远程分支，如 "origin/main"
+    pub upstream: Option<String>,
+}
+
+// 列出分支及其元数据（git branch -a -vv），filter 为 None 时同时返回本地分支和远程跟踪分支，按名称排序
+pub fn list_git_repo_branches(
+    repo: &git2::Repository,
+    filter: Option<git2::BranchType>,
+) -> Result<Vec<BranchInfo>, Box<dyn std::error::Error>> {
+    let mut branches = Vec::new();
+    for branch in repo.branches(filter)? {
+        let (branch, branch_type) = branch?;
+        // 跳过名称不是合法 UTF-8 的分支和 origin/HEAD 这样的符号引用
+        let Some(name) = branch.name()?.map(|name| name.to_string()) else {
+            continue;
+        };
+        let Some(tip) = branch.get().target() else {
+            continue;
+        };
+        let commit = repo.find_commit(tip)?;
+        // 只有本地分支才有 upstream；没有配置 upstream 或其指向的远程分支不存在时为 None
+        let upstream = if branch_type == git2::BranchType::Local {
+            match branch.upstream() {
+                Ok(upstream) => upstream.name()?.map(|name| name.to_string()),
+                Err(e) if e.code() == git2::ErrorCode::NotFound => None,
+                Err(e) => return Err(e.into()),
+            }
+        } else {
+            None
+        };
+        branches.push(BranchInfo {
+            name,
+            is_remote: branch_type == git2::BranchType::Remote,
+            tip,
+            summary: String::from_utf8_lossy(commit.summary_bytes().unwrap_or_default())
+                .to_string(),
+            is_head: branch.is_head(),
+            upstream,
+        });
+    }
+    branches.sort_by(|a, b| (a.is_remote, &a.name).cmp(&(b.is_remote, &b.name)));
+    Ok(branches)
+}
+
+// 设置本地分支跟踪的远程分支（git branch --set-upstream-to=origin/main），直接写入
+// branch.<name>.remote 和 branch.<name>.merge，远程分支尚未 fetch 时也可以设置。remote_branch 为 None 时取消跟踪
+pub fn set_git_branch_upstream(
+    repo: &mut git2::Repository,
+    branch_name: &str,
+    remote_branch: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    repo.find_branch(branch_name, git2::BranchType::Local)?;
+    let remote_key = format!("branch.{}.remote", branch_name);
+    let merge_key = format!("branch.{}.merge", branch_name);
+    let mut config = repo.config()?;
+
+    let Some(remote_branch) = remote_branch else {
+        for key in [&remote_key, &merge_key] {
+            match config.remove(key) {
+                Ok(()) => {}
+                Err(e) if e.code() == git2::ErrorCode::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        return Ok(());
+    };
+
+    // 远程名称本身可能包含 "/"，取能匹配的最长远程名称
+    let remotes = repo.remotes()?;
+    let (remote_name, remote_branch_name) = remotes
+        .iter()
+        .flatten()
+        .filter_map(|remote| {
+            let rest = remote_branch.strip_prefix(remote)?.strip_prefix('/')?;
+            Some((remote, rest))
+        })
+        .max_by_key(|(remote, _)| remote.len())
+        .ok_or_else(|| format!("{} 不属于任何已配置的远程仓库", remote_branch))?;
+
+    config_git_repo_kv_str(&mut config, &remote_key, remote_name)?;
+    config_git_repo_kv_str(
+        &mut config,
+        &merge_key,
+        &format!("refs/heads/{}", remote_branch_name),
+    )?;
+    Ok(())
+}
+
+// 读取本地分支跟踪的远程分支，如 "origin/main"，没有配置时返回 None
+pub fn get_upstream(
+    repo: &git2::Repository,
+    branch_name: &str,
+) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    let config = repo.config()?.snapshot()?;
+    let remote = config.get_string(&format!("branch.{}.remote", branch_name));
+    let merge = config.get_string(&format!("branch.{}.merge", branch_name));
+    let (Ok(remote), Ok(merge)) = (remote, merge) else {
+        return Ok(None);
+    };
+    let merge_branch = merge.strip_prefix("refs/heads/").unwrap_or(&merge);
+    Ok(Some(format!("{}/{}", remote, merge_branch)))
+}
+
+// 返回本地分支最新 commit 的提交时间（秒级时间戳），可用于按最近使用排序分支
+pub fn branch_commit_time(
+    repo: &git2::Repository,
+    branch_name: &str,
+) -> Result<i64, Box<dyn std::error::Error>> {
+    let branch = repo.find_branch(branch_name, git2::BranchType::Local)?;
+    let tip_commit = branch.get().peel_to_commit()?;
+    Ok(tip_commit.committer().when().seconds())
+}
+
+// 列出仓库中的引用全名（refs/heads、refs/tags、refs/remotes 等），可按 glob 过滤
+pub fn list_refs(
+    repo: &git2::Repository,
+    glob: Option<&str>,
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let references = match glob {
+        Some(pattern) => repo.references_glob(pattern)?,
+        None => repo.references()?,
+    };
+
+    let mut ref_names = Vec::new();
+    for reference in references {
+        let reference = reference?;
+        // 跳过名称不是合法 UTF-8 的引用
+        if let Some(name) = reference.name() {
+            ref_names.push(name.to_string());
+        }
+    }
+    Ok(ref_names)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReferenceInfo {
+    pub name: String,
+    // 直接引用或符号引用（如 HEAD、refs/remotes/origin/HEAD）
+    pub ref_type: git2::ReferenceType,
+    // 符号引用指向的引用名
+    pub symbolic_target: Option<String>,
+    // 引用最终指向的对象，符号引用会被解析；解析失败（如未出生的分支）时为 None
+    pub target: Option<git2::Oid>,
+    // 指向附注标签对象时，剥离 tag 后的对象（for-each-ref 的 %(*objectname)）
+    pub peeled: Option<git2::Oid>,
+}
+
+// git for-each-ref：遍历匹配 glob（如 "refs/tags/*"）的引用，为 None 时遍历全部引用，按名称排序
+pub fn iterate_git_repo_references(
+    repo: &git2::Repository,
+    glob: Option<&str>,
+) -> Result<Vec<ReferenceInfo>, Box<dyn std::error::Error>> {
+    let references = match glob {
+        Some(pattern) => repo.references_glob(pattern)?,
+        None => repo.references()?,
+    };
+
+    let mut infos = Vec::new();
+    for reference in references {
+        let reference = reference?;
+        // 跳过名称不是合法 UTF-8 的引用
+        let Some(name) = reference.name() else {
+            continue;
+        };
+        let ref_type = reference.kind().unwrap_or(git2::ReferenceType::Direct);
+        let target = match ref_type {
+            git2::ReferenceType::Symbolic => reference.resolve().ok().and_then(|r| r.target()),
+            git2::ReferenceType::Direct => reference.target(),
+        };
+        let peeled = match target.map(|oid| repo.find_tag(oid)) {
+            Some(Ok(_)) => Some(reference.peel(git2::ObjectType::Any)?.id()),
+            _ => None,
+        };
+        infos.push(ReferenceInfo {
+            name: name.to_string(),
+            ref_type,
+            symbolic_target: reference.symbolic_target().map(|s| s.to_string()),
+            target,
+            peeled,
+        });
+    }
+    infos.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(infos)
+}
+
+// checkout 时对工作目录已有修改的处理策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckoutStrategy {
+    // 强制覆盖工作目录中的修改，使其与目标完全一致
+    Force,
+    // 只更新未被修改的文件，不覆盖本地修改，也不恢复已删除的文件
+    Safe,
+    // 同 Safe，但会重新创建工作目录中缺失的文件
+    SafeCreate,
+}
+
+// 统一构造 CheckoutBuilder，避免各处手写的配置互相不一致
+pub fn build_checkout<'cb>(
+    strategy: CheckoutStrategy,
+    remove_untracked: bool,
+    remove_ignored: bool,
+) -> git2::build::CheckoutBuilder<'cb> {
+    let mut checkout = git2::build::CheckoutBuilder::new();
+    match strategy {
+        CheckoutStrategy::Force => {
+            checkout.force();
+        }
+        CheckoutStrategy::Safe => {
+            checkout.safe();
+        }
+        CheckoutStrategy::SafeCreate => {
+            checkout.safe().recreate_missing(true);
+        }
+    }
+    checkout
+        .remove_untracked(remove_untracked)
+        .remove_ignored(remove_ignored);
+    checkout
+}
+
+pub fn switch_git_repo_branch<'a>(
+    repo: &'a mut git2::Repository,
+    branch_name: &str,
+    update_workdir: bool,
+) -> Result<git2::Reference<'a>, Box<dyn std::error::Error>> {
+    if update_workdir {
+        require_workdir(repo, "切换分支并更新工作目录")?;
+    }
+    // 查找分支引用
+    let branch_ref_name = format!("refs/heads/{}", branch_name);
+    // 检查分支是否存在
+    let branch_ref = repo.find_reference(&branch_ref_name)?;
+
+    // HEAD 已经指向目标分支，无需重复 set_head 和 checkout
+    let head_target = repo
+        .find_reference("HEAD")?
+        .symbolic_target()
+        .map(|s| s.to_string());
+    if head_target.as_deref() == Some(branch_ref_name.as_str()) {
+        return Ok(branch_ref);
+    }
+
+    // 设置 HEAD 指向目标分支
+    repo.set_head(&branch_ref_name)?;
+
+    // 重置索引到目标 tree
+    let mut index = repo.index()?;
+    let commit_id = branch_ref.resolve()?.target().unwrap();
+    let target_tree = repo.find_commit(commit_id)?.tree()?;
+    index.read_tree(&target_tree)?;
+    index.write()?;
+
+    if update_workdir {
+        // 如果需要更新工作目录，则进行 checkout 操作
+        let head = repo.head()?;
+        let oid = head.target().unwrap();
+        let commit = repo.find_commit(oid)?;
+        let tree = commit.tree()?;
+
+        // 执行 checkout 操作，更新工作目录文件
+        // 强制覆盖工作目录中的文件，并移除未跟踪的文件
+        repo.checkout_tree(
+            tree.as_object(),
+            Some(&mut build_checkout(CheckoutStrategy::Force, true, false)),
+        )?;
+
+        materialize_git_repo_symlinks(repo)?;
+    }
+
+    // 返回分支引用
+    let updated_branch_ref = repo.find_reference(&branch_ref_name)?;
+    Ok(updated_branch_ref)
+}
+
+// 检出任意 commit 并分离 HEAD（git checkout --detach <commit>），index 总会重置到该 commit 的 tree，
+// update_workdir 为 true 时与 switch_git_repo_branch 一样强制覆盖工作目录并移除未跟踪的文件
+pub fn checkout_git_repo_commit(
+    repo: &mut git2::Repository,
+    commit_oid: git2::Oid,
+    update_workdir: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if update_workdir {
+        require_workdir(repo, "检出 commit 并更新工作目录")?;
+    }
+    let target_tree = repo.find_commit(commit_oid)?.tree()?;
+
+    repo.set_head_detached(commit_oid)?;
+
+    let mut index = repo.index()?;
+    index.read_tree(&target_tree)?;
+    index.write()?;
+
+    if update_workdir {
+        repo.checkout_tree(
+            target_tree.as_object(),
+            Some(&mut build_checkout(CheckoutStrategy::Force, true, false)),
+        )?;
+        materialize_git_repo_symlinks(repo)?;
+    }
+    Ok(())
+}
+
+// git reset 的三种模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResetMode {
+    // 只移动 HEAD（git reset --soft）
+    Soft,
+    // 移动 HEAD 并重置索引（git reset --mixed）
+    Mixed,
+    // 移动 HEAD、重置索引和工作目录（git reset --hard）
+    Hard,
+}
+
+// 与 git reset 一致：HEAD 指向分支时移动的是该分支，HEAD 保持在分支上；
+// 只有 HEAD 本身处于分离状态时才直接移动 HEAD
+pub fn reset_git_repo_head(
+    repo: &mut git2::Repository,
+    target_commit_oid: git2::Oid,
+) -> Result<(), Box<dyn std::error::Error>> {
+    reset_git_repo_head_with_mode(repo, target_commit_oid, ResetMode::Hard)
+}
+
+pub fn reset_git_repo_head_with_mode(
+    repo: &mut git2::Repository,
+    target_commit_oid: git2::Oid,
+    mode: ResetMode,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if mode == ResetMode::Hard {
+        require_workdir(repo, "git reset --hard")?;
+    }
+    // 查找目标 commit
+    let target_commit = repo.find_commit(target_commit_oid)?;
+
+    // 获取目标 commit 的 tree
+    let target_tree = target_commit.tree()?;
+
+    // 1. 重置 HEAD（或 HEAD 指向的分支）到目标 commit
+    update_git_repo_head_to_commit(
+        repo,
+        target_commit_oid,
+        &format!("reset HEAD to {}", target_commit_oid),
+    )?;
+    if mode == ResetMode::Soft {
+        return Ok(());
+    }
+
+    // 2. 重置索引到目标 tree
+    let mut index = repo.index()?;
+    index.read_tree(&target_tree)?;
+    index.write()?;
+    if mode == ResetMode::Mixed {
+        return Ok(());
+    }
+
+    // 3. 重置工作目录到目标 tree (hard reset)，移除未跟踪的文件，保留被忽略的文件
+    repo.checkout_tree(
+        target_tree.as_object(),
+        Some(&mut build_checkout(CheckoutStrategy::Force, true, false)),
+    )?;
+
+    materialize_git_repo_symlinks(repo)?;
+
+    Ok(())
+}
+
+pub fn clean_git_repo_index(
+    repo: &mut git2::Repository,
+) -> Result<git2::Index, Box<dyn std::error::Error>> {
+    // 获取仓库的索引
+    let mut index = repo.index()?;
+    
+    // 清空索引中的所有条目
+    index.clear()?;
+    
+    // 写入索引更改
+    index.write()?;
+    
+    Ok(index)
+}
+
+// index 中的一个条目，对应 git ls-files --stage --debug 的输出
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexEntryInfo {
+    pub path: String,
+    pub oid: git2::Oid,
+    // 文件模式，如 0o100644、0o100755、0o120000（符号链接）
+    pub mode: u32,
+    // 0 为正常条目，1/2/3 为冲突中的 ancestor/ours/theirs
+    pub stage: u16,
+    pub size: u32,
+    pub mtime_seconds: i32,
+    pub mtime_nanoseconds: u32,
+}
+
+// 列出 index 中的所有条目（git ls-files --stage），按路径和 stage 排序
+pub fn list_git_repo_index_entries(
+    repo: &git2::Repository,
+) -> Result<Vec<IndexEntryInfo>, Box<dyn std::error::Error>> {
+    let index = repo.index()?;
+    Ok(index
+        .iter()
+        .map(|entry| IndexEntryInfo {
+            path: String::from_utf8_lossy(&entry.path).to_string(),
+            oid: entry.id,
+            mode: entry.mode,
+            stage: index_entry_stage(&entry),
+            size: entry.file_size,
+            mtime_seconds: entry.mtime.seconds(),
+            mtime_nanoseconds: entry.mtime.nanoseconds(),
+        })
+        .collect())
+}
+
+// 将当前 index 中暂存的内容写回工作目录（git checkout-index -a -f）
+pub fn checkout_index(repo: &mut git2::Repository) -> Result<(), Box<dyn std::error::Error>> {
+    let mut index = repo.index()?;
+
+    // 强制覆盖工作目录中的文件
+    repo.checkout_index(
+        Some(&mut index),
+        Some(&mut build_checkout(CheckoutStrategy::Force, false, false)),
+    )?;
+
+    Ok(())
+}
+
+#[derive(Debug, Clone)]
+pub struct TreeEntry {
+    // 遍历时为条目自身的名称，按路径查找时为查找的路径
+    pub relative_path: String,
+    // 从 tree 根开始的完整路径，用 / 分隔
+    pub path: String,
+    pub oid: git2::Oid,
+    pub kind: git2::ObjectType,
+    // tree 中记录的文件模式，如 0o100644、0o100755（可执行）、0o120000（符号链接）、0o040000（目录）
+    pub filemode: i32,
+}
+
+impl TreeEntry {
+    // 符号链接同样以 blob 存储（内容为链接目标），只能通过 filemode 区分
+    pub fn is_symlink(&self) -> bool {
+        self.filemode == i32::from(git2::FileMode::Link)
+    }
+}
+
+pub fn traverse_git_repo_commit_tree_recorder(
+    repo: &git2::Repository,
+    commit_oid: Option<git2::Oid>,
+) -> Result<Vec<TreeEntry>, Box<dyn std::error::Error>> {
+    let mut recorder = Vec::new();
+
+    // 确定要遍历的 commit，如果没有指定则使用 HEAD
+    let target_commit = match commit_oid {
+        Some(oid) => repo.find_commit(oid)?,
+        None => {
+            let head = repo.head()?;
+            let oid = head.target().unwrap();
+            repo.find_commit(oid)?
+        }
+    };
+
+    // 获取 commit 对应的 tree
+    let tree = target_commit.tree()?;
+
+    // 遍历 tree 中的所有条目
+    tree.walk(git2::TreeWalkMode::PreOrder, |root, entry| {
+        let entry_kind = match entry.kind() {
+            Some(git2::ObjectType::Tree) | Some(git2::ObjectType::Blob) => entry.kind().unwrap(),
+            _ => return git2::TreeWalkResult::Ok,
+        };
+
+        recorder.push(TreeEntry {
+            relative_path: entry.name().unwrap().to_string(),
+            // root 为父目录路径，已带有结尾的 "/"
+            path: format!("{}{}", root, entry.name().unwrap()),
+            kind: entry_kind,
+            oid: entry.id(),
+            filemode: entry.filemode(),
+        });
+
+        git2::TreeWalkResult::Ok
+    })?;
+
+    Ok(recorder)
+}
+
+// git mktree 的多层版本：由 (path, oid, 模式) 条目构造 tree，自动创建中间目录，
+// 是 traverse_git_repo_commit_tree_recorder 的逆操作。
+// 目录条目下如果还有其他条目，则该目录由这些条目重新构造（忽略目录条目自身的 oid），否则直接引用该 tree
+pub fn build_tree_from_entries(
+    repo: &git2::Repository,
+    entries: &[TreeEntry],
+) -> Result<git2::Oid, Box<dyn std::error::Error>> {
+    let mut root = std::collections::BTreeMap::new();
+
+    for entry in entries
+        .iter()
+        .filter(|entry| entry.kind != git2::ObjectType::Tree)
+    {
+        if entry.kind != git2::ObjectType::Blob {
+            return Err(format!("条目 {} 的类型 {} 不能放入 tree", entry.path, entry.kind).into());
+        }
+        let (dir, name) = snapshot_parent_dir(&mut root, &entry.path)?;
+        if dir.contains_key(&name) {
+            return Err(format!("路径 {} 重复或与同名目录冲突", entry.path).into());
+        }
+        dir.insert(name, SnapshotNode::Entry(entry.oid, entry.filemode));
+    }
+
+    // 先处理层级更深的目录条目，上层目录已由其下条目构造时不再整体引用
+    let mut tree_entries: Vec<&TreeEntry> = entries
+        .iter()
+        .filter(|entry| entry.kind == git2::ObjectType::Tree)
+        .collect();
+    tree_entries.sort_by_key(|entry| std::cmp::Reverse(entry.path.matches('/').count()));
+    for entry in tree_entries {
+        let (dir, name) = snapshot_parent_dir(&mut root, &entry.path)?;
+        match dir.get(&name) {
+            Some(SnapshotNode::Dir(_)) => {}
+            Some(SnapshotNode::Entry(..)) => {
+                return Err(format!("路径 {} 重复或与同名文件冲突", entry.path).into());
+            }
+            None => {
+                dir.insert(
+                    name,
+                    SnapshotNode::Entry(entry.oid, i32::from(git2::FileMode::Tree)),
+                );
+            }
+        }
+    }
+
+    write_snapshot_tree(repo, &root)
+}
+
+// 将 tree 展开为 "完整路径 -> blob oid" 的映射，便于与工作目录扫描结果直接比较
+pub fn tree_path_oid_map(
+    repo: &git2::Repository,
+    tree_oid: git2::Oid,
+) -> Result<std::collections::HashMap<String, git2::Oid>, Box<dyn std::error::Error>> {
+    let tree = repo.find_tree(tree_oid)?;
+
+    let mut path_oids = std::collections::HashMap::new();
+    tree.walk(git2::TreeWalkMode::PreOrder, |root, entry| {
+        if entry.kind() == Some(git2::ObjectType::Blob)
+            && let Some(name) = entry.name()
+        {
+            // root 为父目录路径，已带有结尾的 "/"
+            path_oids.insert(format!("{}{}", root, name), entry.id());
+        }
+        git2::TreeWalkResult::Ok
+    })?;
+    Ok(path_oids)
+}
+
+// 将 commit 的 tree 转换为嵌套的 JSON 对象：目录为对象，文件为其 blob oid 字符串
+#[cfg(feature = "serde")]
+pub fn tree_to_nested_json(
+    repo: &git2::Repository,
+    commit_oid: Option<git2::Oid>,
+) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
+    // 确定要遍历的 commit，如果没有指定则使用 HEAD
+    let target_commit = match commit_oid {
+        Some(oid) => repo.find_commit(oid)?,
+        None => repo.head()?.peel_to_commit()?,
+    };
+
+    tree_to_json_value(repo, &target_commit.tree()?)
+}
+
+#[cfg(feature = "serde")]
+pub fn tree_to_json_value(
+    repo: &git2::Repository,
+    tree: &git2::Tree,
+) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
+    let mut object = serde_json::Map::new();
+    for entry in tree.iter() {
+        let name = entry
+            .name()
+            .ok_or("tree entry 名称不是合法的 UTF-8")?
+            .to_string();
+        match entry.kind() {
+            Some(git2::ObjectType::Tree) => {
+                let subtree = repo.find_tree(entry.id())?;
+                object.insert(name, tree_to_json_value(repo, &subtree)?);
+            }
+            Some(git2::ObjectType::Blob) => {
+                object.insert(name, serde_json::Value::String(entry.id().to_string()));
+            }
+            // 子模块等其他类型的条目不输出
+            _ => {}
+        }
+    }
+    Ok(serde_json::Value::Object(object))
+}
+
+pub fn lookup_entry_from_git_repo_commit_tree_by_path(
+    repo: &git2::Repository,
+    commit_oid: Option<git2::Oid>,
+    target_path: impl AsRef<Path>,
+) -> Result<Option<TreeEntry>, Box<dyn std::error::Error>> {
+    let target_path = normalize_repo_path(repo.workdir(), target_path.as_ref())?;
+
+    // 确定要查找的 commit，如果没有指定则使用 HEAD
+    let target_commit = match commit_oid {
+        Some(oid) => repo.find_commit(oid)?,
+        None => {
+            let head = repo.head()?;
+            let oid = head.target().unwrap();
+            repo.find_commit(oid)?
+        }
+    };
+
+    // 获取 commit 对应的 tree
+    let tree = target_commit.tree()?;
+
+    // 使用 get_path 方法查找指定路径的条目
+    match tree.get_path(&target_path) {
+        Ok(tree_entry) => {
+            let entry = TreeEntry {
+                relative_path: repo_path_display(&target_path),
+                path: repo_path_display(&target_path),
+                oid: tree_entry.id(),
+                kind: tree_entry.kind().unwrap_or(git2::ObjectType::Any),
+                filemode: tree_entry.filemode(),
+            };
+            Ok(Some(entry))
+        }
+        Err(_) => Ok(None), // 路径不存在
+    }
+}
+
+// 仅判断路径是否存在于 commit 的 tree 中，不构造 TreeEntry
+pub fn path_exists_in_commit(
+    repo: &git2::Repository,
+    commit_oid: Option<git2::Oid>,
+    target_path: impl AsRef<Path>,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let target_path = normalize_repo_path(repo.workdir(), target_path.as_ref())?;
+
+    // 确定要查找的 commit，如果没有指定则使用 HEAD
+    let target_commit = match commit_oid {
+        Some(oid) => repo.find_commit(oid)?,
+        None => repo.head()?.peel_to_commit()?,
+    };
+
+    let tree = target_commit.tree()?;
+    Ok(tree.get_path(&target_path).is_ok())
+}
+
+#[derive(Debug)]
+pub struct SubmoduleInfo {
+    pub name: String,
+    pub path: String,
+    pub url: Option<String>,
+    // 父仓库 HEAD 中记录的子模块 commit，尚未提交时为 None
+    pub head_oid: Option<git2::Oid>,
+}
+
+// 列出仓库中配置的所有子模块
+pub fn list_submodules(
+    repo: &git2::Repository,
+) -> Result<Vec<SubmoduleInfo>, Box<dyn std::error::Error>> {
+    let mut submodules = Vec::new();
+    for submodule in repo.submodules()? {
+        submodules.push(SubmoduleInfo {
+            name: submodule.name().unwrap_or_default().to_string(),
+            path: submodule.path().to_string_lossy().to_string(),
+            url: submodule.url().map(|url| url.to_string()),
+            head_oid: submodule.head_id(),
+        });
+    }
+    Ok(submodules)
+}
+
+// 更新子模块到父仓库记录的 commit，init 为 true 时会先初始化并 clone 子模块仓库。
+// 返回更新后子模块检出的 commit
+pub fn update_submodule(
+    repo: &git2::Repository,
+    name: &str,
+    init: bool,
+) -> Result<Option<git2::Oid>, Box<dyn std::error::Error>> {
+    let mut submodule = repo.find_submodule(name)?;
+
+    let mut update_options = git2::SubmoduleUpdateOptions::new();
+    // 子模块中缺少目标 commit 时允许从 url fetch
+    update_options.allow_fetch(true);
+    submodule.update(init, Some(&mut update_options))?;
+
+    Ok(submodule.head_id())
+}
+
+// git hash-object -t <type> --stdin：计算内容写入对象库后的 oid，但不写入，
+// 可在写入 blob 前先用 object_exists 判断是否已存在相同内容
+pub fn hash_git_object(
+    content: &[u8],
+    kind: git2::ObjectType,
+) -> Result<git2::Oid, Box<dyn std::error::Error>> {
+    Ok(git2::Oid::hash_object(kind, content)?)
+}
+
+// 将内存中的内容直接写入对象库（git hash-object -w --stdin），不经过工作目录，裸仓库同样可用
+pub fn write_blob_to_git_repo(
+    repo: &git2::Repository,
+    content: &[u8],
+) -> Result<git2::Oid, Box<dyn std::error::Error>> {
+    Ok(repo.blob(content)?)
+}
+
+// 批量写入 blob，复用同一个对象库句柄，返回的 oid 与输入顺序一致
+pub fn write_blobs_to_git_repo<I, B>(
+    repo: &git2::Repository,
+    contents: I,
+) -> Result<Vec<git2::Oid>, Box<dyn std::error::Error>>
+where
+    I: IntoIterator<Item = B>,
+    B: AsRef<[u8]>,
+{
+    let odb = repo.odb()?;
+    let mut oids = Vec::new();
+    for content in contents {
+        oids.push(odb.write(git2::ObjectType::Blob, content.as_ref())?);
+    }
+    Ok(oids)
+}
+
+// git cat-file -e：直接查询对象库中是否存在该对象，不解析对象内容
+pub fn object_exists(
+    repo: &git2::Repository,
+    oid: git2::Oid,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    Ok(repo.odb()?.exists(oid))
+}
+
+// git cat-file -t：只读取对象头获取类型，对象不存在时返回 None
+pub fn object_type(
+    repo: &git2::Repository,
+    oid: git2::Oid,
+) -> Result<Option<git2::ObjectType>, Box<dyn std::error::Error>> {
+    match repo.odb()?.read_header(oid) {
+        Ok((_, kind)) => Ok(Some(kind)),
+        Err(e) if e.code() == git2::ErrorCode::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+// 对象库中对象的原始内容，data 为不带 "<type> <size>\0" 头的负载
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawObject {
+    pub kind: git2::ObjectType,
+    pub size: usize,
+    pub data: Vec<u8>,
+}
+
+// git cat-file <type> <oid>：读取 blob / tree / commit / tag 的原始负载，tree 为二进制格式
+pub fn read_git_repo_object_raw(
+    repo: &git2::Repository,
+    oid: git2::Oid,
+) -> Result<RawObject, Box<dyn std::error::Error>> {
+    let odb = repo.odb()?;
+    let object = odb.read(oid)?;
+    Ok(RawObject {
+        kind: object.kind(),
+        size: object.len(),
+        data: object.data().to_vec(),
+    })
+}
+
+pub fn read_git_repo_blob_content(
+    repo: &git2::Repository,
+    blob_oid: git2::Oid,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    // 根据 OID 查找 blob 对象
+    let blob = repo.find_blob(blob_oid)?;
+
+    // 获取 blob 的内容
+    let content = blob.content().to_vec();
+
+    Ok(content)
+}
+
+// 流式读取 blob 内容：松散对象由对象库边解压边读取，不会一次性占用整个 blob 大小的内存；
+// 对象库不支持流式读取（如 packfile 中的对象）时退化为一次读取后分块返回
+pub enum BlobStream<'odb> {
+    Odb {
+        reader: git2::OdbReader<'odb>,
+        size: usize,
+    },
+    Buffered {
+        object: git2::OdbObject<'odb>,
+        position: usize,
+    },
+}
+
+impl BlobStream<'_> {
+    // blob 的总字节数
+    pub fn size(&self) -> usize {
+        match self {
+            BlobStream::Odb { size, .. } => *size,
+            BlobStream::Buffered { object, .. } => object.len(),
+        }
+    }
+}
+
+impl std::io::Read for BlobStream<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            BlobStream::Odb { reader, .. } => reader.read(buf),
+            BlobStream::Buffered { object, position } => {
+                let remaining = &object.data()[*position..];
+                let n = remaining.len().min(buf.len());
+                buf[..n].copy_from_slice(&remaining[..n]);
+                *position += n;
+                Ok(n)
+            }
+        }
+    }
+}
+
+// 打开 blob 的流式读取器，odb 通过 repo.odb() 获取；对象不是 blob 时返回错误
+pub fn open_git_repo_blob_stream<'odb>(
+    odb: &'odb git2::Odb,
+    blob_oid: git2::Oid,
+) -> Result<BlobStream<'odb>, Box<dyn std::error::Error>> {
+    let (_, kind) = odb.read_header(blob_oid)?;
+    if kind != git2::ObjectType::Blob {
+        return Err(format!("对象 {} 的类型是 {}，不是 blob", blob_oid, kind).into());
+    }
+    match odb.reader(blob_oid) {
+        Ok((reader, size, _)) => Ok(BlobStream::Odb { reader, size }),
+        Err(_) => Ok(BlobStream::Buffered {
+            object: odb.read(blob_oid)?,
+            position: 0,
+        }),
+    }
+}
+
+// 与 git 相同的二进制判定：前 8000 字节中包含 NUL 字节即视为二进制
+const BINARY_PROBE_BYTES: usize = 8000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlobProbe {
+    pub size: usize,
+    pub is_binary: bool,
+}
+
+// 获取 blob 的大小并判断是否为二进制，只读取对象头和内容开头的部分，适合在读取大文件前预检
+pub fn probe_git_repo_blob(
+    repo: &git2::Repository,
+    blob_oid: git2::Oid,
+) -> Result<BlobProbe, Box<dyn std::error::Error>> {
+    use std::io::Read;
+
+    let odb = repo.odb()?;
+    let stream = open_git_repo_blob_stream(&odb, blob_oid)?;
+    let size = stream.size();
+    let mut head = Vec::with_capacity(size.min(BINARY_PROBE_BYTES));
+    stream
+        .take(BINARY_PROBE_BYTES as u64)
+        .read_to_end(&mut head)?;
+    Ok(BlobProbe {
+        size,
+        is_binary: head.contains(&0),
+    })
+}
+
+// 按行读取文本 blob，二进制 blob 返回错误；同时兼容 \n 与 \r\n 换行，并去掉 UTF-8 BOM
+pub fn blob_lines(
+    repo: &git2::Repository,
+    blob_oid: git2::Oid,
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let blob = repo.find_blob(blob_oid)?;
+    if blob.is_binary() {
+        return Err(format!("blob {} 是二进制内容，无法按行读取", blob_oid).into());
+    }
+
+    let content = blob.content();
+    let content = content.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(content);
+    // 非 UTF-8 的字节使用替换字符，避免整个文件读取失败
+    let text = String::from_utf8_lossy(content);
+    Ok(text.lines().map(|line| line.to_string()).collect())
+}
+
+// 统计 commit tree 中指定扩展名（如 "rs"、".txt"）文件的总行数，extensions 为空时统计所有文件
+// 二进制文件会被跳过
+pub fn count_lines(
+    repo: &git2::Repository,
+    commit_oid: Option<git2::Oid>,
+    extensions: &[&str],
+) -> Result<usize, Box<dyn std::error::Error>> {
+    // 确定要统计的 commit，如果没有指定则使用 HEAD
+    let target_commit = match commit_oid {
+        Some(oid) => repo.find_commit(oid)?,
+        None => repo.head()?.peel_to_commit()?,
+    };
+
+    let mut blob_oids = Vec::new();
+    target_commit
+        .tree()?
+        .walk(git2::TreeWalkMode::PreOrder, |_, entry| {
+            if entry.kind() != Some(git2::ObjectType::Blob) {
+                return git2::TreeWalkResult::Ok;
+            }
+            let extension = entry
+                .name()
+                .and_then(|name| Path::new(name).extension())
+                .and_then(|extension| extension.to_str());
+            let matched = extensions.is_empty()
+                || extensions
+                    .iter()
+                    .any(|wanted| Some(wanted.trim_start_matches('.')) == extension);
+            if matched {
+                blob_oids.push(entry.id());
+            }
+            git2::TreeWalkResult::Ok
+        })?;
+
+    let mut total = 0;
+    for blob_oid in blob_oids {
+        if repo.find_blob(blob_oid)?.is_binary() {
+            continue;
+        }
+        total += blob_lines(repo, blob_oid)?.len();
+    }
+    Ok(total)
+}
+
+pub fn restore_git_repo_head_to_workdir(
+    repo: &git2::Repository,
+) -> Result<(), Box<dyn std::error::Error>> {
+    require_workdir(repo, "git restore")?;
+    // 获取 HEAD 引用
+    let head_ref = repo.head()?;
+    
+    // 获取 HEAD 指向的 commit
+    let head_commit = head_ref.peel_to_commit()?;
+    
+    // 获取 commit 的 tree
+    let head_tree = head_commit.tree()?;
+    
+    // 使用 checkout 将工作目录恢复到 HEAD 状态，不移除未跟踪和被忽略的文件
+    repo.checkout_tree(
+        head_tree.as_object(),
+        Some(&mut build_checkout(CheckoutStrategy::Force, false, false)),
+    )?;
+    
+    Ok(())
+}
+
+// index 条目的 assume-unchanged 标记（git update-index --assume-unchanged）
+const GIT_INDEX_ENTRY_VALID: u16 = 0x8000;
+// index 条目 flags 中记录冲突 stage（0 为正常条目，1/2/3 为 base/ours/theirs）的位
+const GIT_INDEX_ENTRY_STAGE_MASK: u16 = 0x3000;
+const GIT_INDEX_ENTRY_STAGE_SHIFT: u16 = 12;
+
+// index 条目的冲突 stage
+fn index_entry_stage(entry: &git2::IndexEntry) -> u16 {
+    (entry.flags & GIT_INDEX_ENTRY_STAGE_MASK) >> GIT_INDEX_ENTRY_STAGE_SHIFT
+}
+
+// 符号链接条目指向的工作目录内已存在的普通文件，链接目标在工作目录外或不是普通文件时返回 None
+fn materialized_link_target(
+    repo: &git2::Repository,
+    workdir: &Path,
+    entry: &git2::IndexEntry,
+) -> Result<Option<PathBuf>, Box<dyn std::error::Error>> {
+    let link_path = workdir.join(String::from_utf8_lossy(&entry.path).as_ref());
+    let target = String::from_utf8_lossy(repo.find_blob(entry.id)?.content()).to_string();
+    let Some(target_path) = link_path.parent().map(|parent| parent.join(&target)) else {
+        return Ok(None);
+    };
+    let Ok(target_path) = target_path.canonicalize() else {
+        return Ok(None);
+    };
+    if !target_path.starts_with(workdir.canonicalize()?) || !target_path.is_file() {
+        return Ok(None);
+    }
+    Ok(Some(target_path))
+}
+
+// core.symlinks 为 false（如没有创建符号链接权限的 Windows）时，checkout 会把符号链接写成内容为链接目标的普通文件。
+// 将其中指向工作目录内普通文件的条目替换为目标文件内容的副本，并标记为 assume-unchanged，避免 status 报告修改，
+// 返回被替换的路径。支持符号链接的平台上不做任何修改
+pub fn materialize_git_repo_symlinks(
+    repo: &git2::Repository,
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let workdir = require_workdir(repo, "替换符号链接")?.to_path_buf();
+    if repo.config()?.get_bool("core.symlinks").unwrap_or(true) {
+        return Ok(Vec::new());
+    }
+
+    let mut index = repo.index()?;
+    let links: Vec<git2::IndexEntry> = index
+        .iter()
+        .filter(|entry| {
+            entry.mode == u32::from(git2::FileMode::Link) && index_entry_stage(entry) == 0
+        })
+        .collect();
+    let mut materialized = Vec::new();
+    for mut entry in links {
+        // 只替换指向工作目录内已存在普通文件的链接
+        let Some(target_path) = materialized_link_target(repo, &workdir, &entry)? else {
+            continue;
+        };
+        let link_path = workdir.join(String::from_utf8_lossy(&entry.path).as_ref());
+        fs::copy(&target_path, &link_path)?;
+        entry.flags |= GIT_INDEX_ENTRY_VALID;
+        index.add(&entry)?;
+        materialized.push(String::from_utf8_lossy(&entry.path).to_string());
+    }
+    if !materialized.is_empty() {
+        write_git_repo_index_with_retry(&mut index, &IndexWriteRetry::default())?;
+    }
+    Ok(materialized)
+}
+
+// 从指定 commit 中恢复单个文件或目录到工作目录（git checkout <rev> -- <path>），按 tree 中记录的模式写入，
+// update_index 为 true 时同时更新 index（否则等价于 git restore --source=<rev> <path>），其他文件不受影响
+pub fn restore_git_repo_path_from_commit(
+    repo: &mut git2::Repository,
+    commit_oid: git2::Oid,
+    path: impl AsRef<Path>,
+    update_index: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    require_workdir(repo, "从 commit 恢复文件")?;
+    let path = normalize_repo_path(repo.workdir(), path.as_ref())?;
+    let tree = repo.find_commit(commit_oid)?.tree()?;
+    if tree.get_path(&path).is_err() {
+        return Err(format!(
+            "commit {} 中不存在路径 {}",
+            commit_oid,
+            repo_path_display(&path)
+        )
+        .into());
+    }
+
+    let mut checkout = build_checkout(CheckoutStrategy::Force, false, false);
+    checkout
+        .path(&path)
+        .disable_pathspec_match(true)
+        .update_index(update_index);
+    repo.checkout_tree(tree.as_object(), Some(&mut checkout))?;
+    Ok(())
+}
+
+// 文件变更类型，对应 git diff --name-status 的状态字母
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Added,
+    Deleted,
+    Modified,
+    Renamed,
+    Copied,
+    TypeChanged,
+}
+
+impl ChangeKind {
+    pub fn from_delta(delta: git2::Delta) -> Option<Self> {
+        match delta {
+            git2::Delta::Added | git2::Delta::Untracked => Some(ChangeKind::Added),
+            git2::Delta::Deleted => Some(ChangeKind::Deleted),
+            git2::Delta::Modified => Some(ChangeKind::Modified),
+            git2::Delta::Renamed => Some(ChangeKind::Renamed),
+            git2::Delta::Copied => Some(ChangeKind::Copied),
+            git2::Delta::Typechange => Some(ChangeKind::TypeChanged),
+            _ => None,
+        }
+    }
+
+    pub fn status_char(&self) -> char {
+        match self {
+            ChangeKind::Added => 'A',
+            ChangeKind::Deleted => 'D',
+            ChangeKind::Modified => 'M',
+            ChangeKind::Renamed => 'R',
+            ChangeKind::Copied => 'C',
+            ChangeKind::TypeChanged => 'T',
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileChange {
+    pub kind: ChangeKind,
+    // 新增的文件没有 old_path，删除的文件没有 new_path
+    pub old_path: Option<String>,
+    pub new_path: Option<String>,
+}
+
+impl FileChange {
+    pub fn from_diff_delta(delta: &git2::DiffDelta) -> Option<Self> {
+        let kind = ChangeKind::from_delta(delta.status())?;
+        let path_of = |file: git2::DiffFile| file.path().map(|p| p.to_string_lossy().to_string());
+        Some(FileChange {
+            kind,
+            old_path: if kind == ChangeKind::Added {
+                None
+            } else {
+                path_of(delta.old_file())
+            },
+            new_path: if kind == ChangeKind::Deleted {
+                None
+            } else {
+                path_of(delta.new_file())
+            },
+        })
+    }
+}
+
+// 将 diff 中的变更转换为 FileChange 列表，忽略未修改的条目
+pub fn diff_to_file_changes(diff: &git2::Diff) -> Vec<FileChange> {
+    diff.deltas()
+        .filter_map(|delta| FileChange::from_diff_delta(&delta))
+        .collect()
+}
+
+// 两个 commit 之间的文件变更列表（git diff --name-status -M [-C --find-copies-harder]）
+pub fn name_status(
+    repo: &git2::Repository,
+    old_commit_oid: git2::Oid,
+    new_commit_oid: git2::Oid,
+    detect_copies: bool,
+) -> Result<Vec<FileChange>, Box<dyn std::error::Error>> {
+    let old_tree = repo.find_commit(old_commit_oid)?.tree()?;
+    let new_tree = repo.find_commit(new_commit_oid)?.tree()?;
+
+    let mut diff_options = git2::DiffOptions::new();
+    // 复制检测需要把未修改的文件也作为候选来源
+    diff_options.include_unmodified(detect_copies);
+    let mut diff =
+        repo.diff_tree_to_tree(Some(&old_tree), Some(&new_tree), Some(&mut diff_options))?;
+
+    let mut find_options = git2::DiffFindOptions::new();
+    find_options.renames(true);
+    if detect_copies {
+        find_options.copies(true).copies_from_unmodified(true);
+    }
+    diff.find_similar(Some(&mut find_options))?;
+
+    Ok(diff_to_file_changes(&diff))
+}
+
+// diff 中的一行，origin 为 '+'、'-'、' ' 等行首标记
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffLine {
+    pub origin: char,
+    pub old_lineno: Option<u32>,
+    pub new_lineno: Option<u32>,
+    pub content: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffHunk {
+    // 形如 "@@ -1,2 +1,3 @@" 的 hunk 头
+    pub header: String,
+    pub old_start: u32,
+    pub old_lines: u32,
+    pub new_start: u32,
+    pub new_lines: u32,
+    pub lines: Vec<DiffLine>,
+    // 单词级别的变更，仅在 GitDiffOptions::word_diff 开启时填充
+    pub word_changes: Vec<WordChange>,
+}
+
+// hunk 中的一处单词级别变更，范围为在 hunk 旧文本/新文本（上下文行加删除行/新增行）中的字节偏移
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WordChange {
+    pub old_range: std::ops::Range<usize>,
+    pub new_range: std::ops::Range<usize>,
+    pub old_text: String,
+    pub new_text: String,
+}
+
+impl DiffHunk {
+    // hunk 变更前后的完整文本
+    pub fn old_text(&self) -> String {
+        self.side_text(&[' ', '-'])
+    }
+
+    pub fn new_text(&self) -> String {
+        self.side_text(&[' ', '+'])
+    }
+
+    fn side_text(&self, origins: &[char]) -> String {
+        self.lines
+            .iter()
+            .filter(|line| origins.contains(&line.origin))
+            .map(|line| line.content.as_str())
+            .collect()
+    }
+
+    // 类似 git diff --word-diff 的输出，删除的部分用 [-...-] 包裹，新增的部分用 {+...+} 包裹
+    pub fn word_diff_text(&self) -> String {
+        // 没有单词变更时（例如未开启 word_diff）按新文本输出
+        if self.word_changes.is_empty() {
+            return self.new_text();
+        }
+        let old_text = self.old_text();
+        let mut text = String::new();
+        let mut old_pos = 0;
+        for change in &self.word_changes {
+            text.push_str(&old_text[old_pos..change.old_range.start]);
+            if !change.old_text.is_empty() {
+                text.push_str(&format!("[-{}-]", change.old_text));
+            }
+            if !change.new_text.is_empty() {
+                text.push_str(&format!("{{+{}+}}", change.new_text));
+            }
+            old_pos = change.old_range.end;
+        }
+        text.push_str(&old_text[old_pos..]);
+        text
+    }
+}
+
+// 将文本切分为单词：连续的 ASCII 字母数字、连续的空白各为一个单词，其余字符（标点、中文等）各自为一个单词
+fn split_words(text: &str) -> Vec<std::ops::Range<usize>> {
+    let kind = |c: char| {
+        if c.is_ascii_alphanumeric() || c == '_' {
+            1
+        } else if c.is_whitespace() {
+            2
+        } else {
+            0
+        }
+    };
+    let mut words: Vec<std::ops::Range<usize>> = Vec::new();
+    let mut last_kind = 0;
+    for (idx, c) in text.char_indices() {
+        let c_kind = kind(c);
+        match words.last_mut() {
+            Some(word) if c_kind != 0 && c_kind == last_kind => word.end = idx + c.len_utf8(),
+            _ => words.push(idx..idx + c.len_utf8()),
+        }
+        last_kind = c_kind;
+    }
+    words
+}
+
+// 将文本按行切分，每行包含结尾的换行符
+fn split_lines(text: &str) -> Vec<std::ops::Range<usize>> {
+    let mut lines = Vec::new();
+    let mut start = 0;
+    for line in text.split_inclusive('\n') {
+        lines.push(start..start + line.len());
+        start += line.len();
+    }
+    lines
+}
+
+// 单词级 LCS 表的单元格数上限（u32 表约 16MB），超过时退化为按行比较
+const WORD_DIFF_MAX_LCS_CELLS: usize = 4 * 1024 * 1024;
+
+// 对 hunk 的新旧文本做单词级别的最长公共子序列比较，返回变更的单词区间。
+// 单词数过多时按行比较，行数仍然过多时把整个 hunk 作为一处变更
+pub fn word_diff_hunk(hunk: &DiffHunk) -> Vec<WordChange> {
+    let old_text = hunk.old_text();
+    let new_text = hunk.new_text();
+    let within_limit = |old: &[std::ops::Range<usize>], new: &[std::ops::Range<usize>]| {
+        (old.len() + 1).saturating_mul(new.len() + 1) <= WORD_DIFF_MAX_LCS_CELLS
+    };
+
+    let (old_words, new_words) = (split_words(&old_text), split_words(&new_text));
+    if within_limit(&old_words, &new_words) {
+        return diff_token_ranges(&old_text, &old_words, &new_text, &new_words);
+    }
+    let (old_lines, new_lines) = (split_lines(&old_text), split_lines(&new_text));
+    if within_limit(&old_lines, &new_lines) {
+        return diff_token_ranges(&old_text, &old_lines, &new_text, &new_lines);
+    }
+    if old_text == new_text {
+        return Vec::new();
+    }
+    vec![WordChange {
+        old_text: old_text.clone(),
+        new_text: new_text.clone(),
+        old_range: 0..old_text.len(),
+        new_range: 0..new_text.len(),
+    }]
+}
+
+// 对切分好的新旧 token（单词或行）做最长公共子序列比较，返回变更的 token 区间
+fn diff_token_ranges(
+    old_text: &str,
+    old_words: &[std::ops::Range<usize>],
+    new_text: &str,
+    new_words: &[std::ops::Range<usize>],
+) -> Vec<WordChange> {
+    let old_word = |i: usize| &old_text[old_words[i].clone()];
+    let new_word = |j: usize| &new_text[new_words[j].clone()];
+
+    // lcs[i][j] 为 old_words[i..] 与 new_words[j..] 的最长公共子序列长度
+    let (n, m) = (old_words.len(), new_words.len());
+    let mut lcs = vec![vec![0u32; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if old_word(i) == new_word(j) {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut changes = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && old_word(i) == new_word(j) {
+            i += 1;
+            j += 1;
+            continue;
+        }
+        // 收集连续的不相同单词作为一处变更
+        let (old_start, new_start) = (i, j);
+        while (i < n || j < m) && !(i < n && j < m && old_word(i) == new_word(j)) {
+            if j >= m || (i < n && lcs[i + 1][j] >= lcs[i][j + 1]) {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        let byte_range =
+            |words: &[std::ops::Range<usize>], start: usize, end: usize, len: usize| {
+                if start < end {
+                    words[start].start..words[end - 1].end
+                } else {
+                    // 纯新增或纯删除时，另一侧为插入点处的空范围
+                    let pos = words.get(start).map_or(len, |word| word.start);
+                    pos..pos
+                }
+            };
+        let old_range = byte_range(old_words, old_start, i, old_text.len());
+        let new_range = byte_range(new_words, new_start, j, new_text.len());
+        changes.push(WordChange {
+            old_text: old_text[old_range.clone()].to_string(),
+            new_text: new_text[new_range.clone()].to_string(),
+            old_range,
+            new_range,
+        });
+    }
+    changes
+}
+
+// 单个文件的结构化 diff，文件不存在的一侧 oid 为零
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileDiff {
+    pub change: FileChange,
+    pub old_oid: git2::Oid,
+    pub new_oid: git2::Oid,
+    // 二进制文件没有 hunk；超过 GitDiffOptions::max_file_size 的文件同样不加载内容，也会被标记为二进制
+    pub is_binary: bool,
+    // 超过 GitDiffOptions::max_file_size 而跳过了内容比较
+    pub is_too_large: bool,
+    // 变更前后的 blob 大小（字节），文件不存在的一侧为 0
+    pub old_size: u64,
+    pub new_size: u64,
+    pub hunks: Vec<DiffHunk>,
+}
+
+impl FileDiff {
+    // (新增行数, 删除行数)，二进制文件为 (0, 0)
+    pub fn line_stats(&self) -> (usize, usize) {
+        let lines = self.hunks.iter().flat_map(|hunk| hunk.lines.iter());
+        lines.fold((0, 0), |(additions, deletions), line| match line.origin {
+            '+' => (additions + 1, deletions),
+            '-' => (additions, deletions + 1),
+            _ => (additions, deletions),
+        })
+    }
+
+    // 变更后的路径，删除的文件返回原路径
+    pub fn path(&self) -> &str {
+        self.change
+            .new_path
+            .as_deref()
+            .or(self.change.old_path.as_deref())
+            .unwrap_or_default()
+    }
+}
+
+// 将 diff 转换为按文件组织的 hunk 和行详情，忽略未修改的条目
+pub fn diff_to_file_diffs(diff: &git2::Diff) -> Result<Vec<FileDiff>, Box<dyn std::error::Error>> {
+    let mut file_diffs = Vec::new();
+    for (delta_idx, delta) in diff.deltas().enumerate() {
+        let Some(change) = FileChange::from_diff_delta(&delta) else {
+            continue;
+        };
+
+        let mut hunks = Vec::new();
+        let patch = git2::Patch::from_diff(diff, delta_idx)?;
+        // 二进制文件没有 patch 文本
+        if let Some(patch) = &patch {
+            for hunk_idx in 0..patch.num_hunks() {
+                let (hunk, line_count) = patch.hunk(hunk_idx)?;
+                let mut lines = Vec::with_capacity(line_count);
+                for line_idx in 0..line_count {
+                    let line = patch.line_in_hunk(hunk_idx, line_idx)?;
+                    lines.push(DiffLine {
+                        origin: line.origin(),
+                        old_lineno: line.old_lineno(),
+                        new_lineno: line.new_lineno(),
+                        content: String::from_utf8_lossy(line.content()).to_string(),
+                    });
+                }
+                hunks.push(DiffHunk {
+                    header: String::from_utf8_lossy(hunk.header())
+                        .trim_end()
+                        .to_string(),
+                    old_start: hunk.old_start(),
+                    old_lines: hunk.old_lines(),
+                    new_start: hunk.new_start(),
+                    new_lines: hunk.new_lines(),
+                    lines,
+                    word_changes: Vec::new(),
+                });
+            }
+        }
+
+        // 生成 patch 后 libgit2 才会加载 blob 并确定大小和是否为二进制
+        let delta = patch.as_ref().map_or(delta, |patch| patch.delta());
+        file_diffs.push(FileDiff {
+            change,
+            old_oid: delta.old_file().id(),
+            new_oid: delta.new_file().id(),
+            is_binary: delta.flags().is_binary(),
+            is_too_large: false,
+            old_size: delta.old_file().size(),
+            new_size: delta.new_file().size(),
+            hunks,
+        });
+    }
+    Ok(file_diffs)
+}
+
+// 生成 diff 时的选项
+#[derive(Debug, Clone)]
+pub struct GitDiffOptions {
+    // 每个 hunk 前后保留的上下文行数（git diff -U<n>）
+    pub context_lines: u32,
+    // 是否在行级 diff 的基础上计算单词级别的变更（git diff --word-diff）
+    pub word_diff: bool,
+    // 超过该大小（字节）的文件只报告 blob 大小，不加载内容比较，避免大文件占用过多内存
+    pub max_file_size: Option<u64>,
+}
+
+impl Default for GitDiffOptions {
+    fn default() -> Self {
+        GitDiffOptions {
+            context_lines: 3,
+            word_diff: false,
+            max_file_size: None,
+        }
+    }
+}
+
+impl GitDiffOptions {
+    pub fn to_git2(&self) -> git2::DiffOptions {
+        let mut diff_options = git2::DiffOptions::new();
+        diff_options.context_lines(self.context_lines);
+        if let Some(max_file_size) = self.max_file_size {
+            diff_options.max_size(max_file_size as i64);
+        }
+        diff_options
+    }
+}
+
+// 按选项生成两个 commit 之间的 git2::Diff，并检测重命名
+pub fn build_commit_diff<'r>(
+    repo: &'r git2::Repository,
+    old_commit_oid: git2::Oid,
+    new_commit_oid: git2::Oid,
+    options: &GitDiffOptions,
+) -> Result<git2::Diff<'r>, Box<dyn std::error::Error>> {
+    let old_tree = repo.find_commit(old_commit_oid)?.tree()?;
+    let new_tree = repo.find_commit(new_commit_oid)?.tree()?;
+    let mut diff_options = options.to_git2();
+    let mut diff =
+        repo.diff_tree_to_tree(Some(&old_tree), Some(&new_tree), Some(&mut diff_options))?;
+
+    let mut find_options = git2::DiffFindOptions::new();
+    find_options.renames(true);
+    diff.find_similar(Some(&mut find_options))?;
+    Ok(diff)
+}
+
+// 两个 commit 之间的结构化 diff（git diff -M old new），包含每个文件的 hunk 和行详情
+pub fn diff_git_repo_commits(
+    repo: &git2::Repository,
+    old_commit_oid: git2::Oid,
+    new_commit_oid: git2::Oid,
+) -> Result<Vec<FileDiff>, Box<dyn std::error::Error>> {
+    diff_git_repo_commits_with_options(
+        repo,
+        old_commit_oid,
+        new_commit_oid,
+        &GitDiffOptions::default(),
+    )
+}
+
+pub fn diff_git_repo_commits_with_options(
+    repo: &git2::Repository,
+    old_commit_oid: git2::Oid,
+    new_commit_oid: git2::Oid,
+    options: &GitDiffOptions,
+) -> Result<Vec<FileDiff>, Box<dyn std::error::Error>> {
+    let diff = build_commit_diff(repo, old_commit_oid, new_commit_oid, options)?;
+    let mut file_diffs = diff_to_file_diffs(&diff)?;
+    if let Some(max_file_size) = options.max_file_size {
+        for file_diff in file_diffs.iter_mut() {
+            file_diff.is_too_large =
+                file_diff.old_size > max_file_size || file_diff.new_size > max_file_size;
+        }
+    }
+    if options.word_diff {
+        for hunk in file_diffs
+            .iter_mut()
+            .flat_map(|file_diff| file_diff.hunks.iter_mut())
+        {
+            hunk.word_changes = word_diff_hunk(hunk);
+        }
+    }
+    Ok(file_diffs)
+}
+
+// 将 diff 渲染为标准的 unified diff 文本（以 diff --git 开头），上下文行数由生成 diff 时的 GitDiffOptions 决定
+pub fn render_diff_as_unified_text(
+    diff: &git2::Diff,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let mut text = Vec::new();
+    diff.print(git2::DiffFormat::Patch, |_delta, _hunk, line| {
+        // 文件头和 hunk 头的 content 已包含完整内容，其余行需要补上行首标记
+        if matches!(line.origin(), '+' | '-' | ' ') {
+            text.push(line.origin() as u8);
+        }
+        text.extend_from_slice(line.content());
+        true
+    })?;
+    Ok(String::from_utf8_lossy(&text).to_string())
+}
+
+// diff 的统计信息，对应 git diff --stat
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DiffStats {
+    // 每个文件的 (路径, 新增行数, 删除行数)
+    pub files: Vec<FileLineChanges>,
+    // 二进制文件的路径，不计入行数
+    pub binary_files: Vec<String>,
+    pub insertions: usize,
+    pub deletions: usize,
+}
+
+impl DiffStats {
+    pub fn from_file_diffs(file_diffs: &[FileDiff]) -> Self {
+        let mut stats = DiffStats::default();
+        for file_diff in file_diffs {
+            let (additions, deletions) = file_diff.line_stats();
+            if file_diff.is_binary {
+                stats.binary_files.push(file_diff.path().to_string());
+            }
+            stats.insertions += additions;
+            stats.deletions += deletions;
+            stats
+                .files
+                .push((file_diff.path().to_string(), additions, deletions));
+        }
+        stats
+    }
+
+    pub fn files_changed(&self) -> usize {
+        self.files.len()
+    }
+
+    // 格式化为 git diff --stat 风格的文本，变更较多时 +/- 柱状图按比例缩放到最多 50 个字符
+    pub fn to_stat_string(&self) -> String {
+        const MAX_BAR_WIDTH: usize = 50;
+        let path_width = self
+            .files
+            .iter()
+            .map(|(path, _, _)| path.len())
+            .max()
+            .unwrap_or(0);
+        let max_changes = self
+            .files
+            .iter()
+            .map(|(_, additions, deletions)| additions + deletions)
+            .max()
+            .unwrap_or(0);
+        let count_width = max_changes.to_string().len();
+        let scale = |count: usize| {
+            if max_changes <= MAX_BAR_WIDTH || count == 0 {
+                count
+            } else {
+                (count * MAX_BAR_WIDTH / max_changes).max(1)
+            }
+        };
+
+        let mut text = String::new();
+        for (path, additions, deletions) in &self.files {
+            if self.binary_files.contains(path) {
+                text.push_str(&format!(" {:<path_width$} | Bin\n", path));
+                continue;
+            }
+            text.push_str(&format!(
+                " {:<path_width$} | {:>count_width$} {}{}\n",
+                path,
+                additions + deletions,
+                "+".repeat(scale(*additions)),
+                "-".repeat(scale(*deletions)),
+            ));
+        }
+
+        let plural = |count: usize, word: &str| {
+            if count == 1 {
+                format!("{} {}", count, word)
+            } else {
+                format!("{} {}s", count, word)
+            }
+        };
+        let files_changed = if self.files_changed() == 1 {
+            "1 file changed".to_string()
+        } else {
+            format!("{} files changed", self.files_changed())
+        };
+        text.push_str(&format!(" {}", files_changed));
+        if self.insertions > 0 {
+            text.push_str(&format!(", {}(+)", plural(self.insertions, "insertion")));
+        }
+        if self.deletions > 0 {
+            text.push_str(&format!(", {}(-)", plural(self.deletions, "deletion")));
+        }
+        text.push('\n');
+        text
+    }
+}
+
+// 两个 commit 之间每个文件及总计的新增、删除行数
+pub fn diff_stats(
+    repo: &git2::Repository,
+    old_commit_oid: git2::Oid,
+    new_commit_oid: git2::Oid,
+) -> Result<DiffStats, Box<dyn std::error::Error>> {
+    let file_diffs = diff_git_repo_commits(repo, old_commit_oid, new_commit_oid)?;
+    Ok(DiffStats::from_file_diffs(&file_diffs))
+}
+
+// 工作目录相对 index 的未暂存变更（git diff），被 .gitignore 忽略的文件不会出现
+// include_untracked 为 true 时未跟踪的文件也作为新增文件返回，便于决定要暂存哪些路径
+pub fn diff_git_repo_workdir_to_index(
+    repo: &git2::Repository,
+    include_untracked: bool,
+) -> Result<Vec<FileDiff>, Box<dyn std::error::Error>> {
+    require_workdir(repo, "git diff")?;
+    let mut diff_options = git2::DiffOptions::new();
+    diff_options
+        .include_untracked(include_untracked)
+        .recurse_untracked_dirs(include_untracked)
+        .show_untracked_content(include_untracked)
+        .include_ignored(false);
+    let diff = repo.diff_index_to_workdir(None, Some(&mut diff_options))?;
+    diff_to_file_diffs(&diff)
+}
+
+// index 相对 HEAD 的已暂存变更（git diff --cached -M），即 commit_index_to_git_repo 将要记录的内容
+// HEAD 尚未指向任何 commit 时，index 中的所有文件都视为新增
+pub fn diff_git_repo_index_to_head(
+    repo: &git2::Repository,
+) -> Result<Vec<FileDiff>, Box<dyn std::error::Error>> {
+    let head_tree = match repo.head() {
+        Ok(head) => Some(head.peel_to_tree()?),
+        Err(e) if e.code() == git2::ErrorCode::UnbornBranch => None,
+        Err(e) => return Err(e.into()),
+    };
+    let index = repo.index()?;
+    let mut diff = repo.diff_tree_to_index(head_tree.as_ref(), Some(&index), None)?;
+
+    let mut find_options = git2::DiffFindOptions::new();
+    find_options.renames(true);
+    diff.find_similar(Some(&mut find_options))?;
+
+    diff_to_file_diffs(&diff)
+}
+
+// 补丁应用的位置，对应 git apply / git apply --cached / git apply --index
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatchTarget {
+    Workdir,
+    Index,
+    Both,
+}
+
+impl PatchTarget {
+    fn to_git2(self) -> git2::ApplyLocation {
+        match self {
+            PatchTarget::Workdir => git2::ApplyLocation::WorkDir,
+            PatchTarget::Index => git2::ApplyLocation::Index,
+            PatchTarget::Both => git2::ApplyLocation::Both,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PatchApplyResult {
+    // 已应用的文件路径
+    pub applied: Vec<String>,
+    // 无法干净应用的文件路径，存在冲突时整个补丁都不会被应用
+    pub conflicts: Vec<String>,
+}
+
+impl PatchApplyResult {
+    pub fn is_clean(&self) -> bool {
+        self.conflicts.is_empty()
+    }
+}
+
+// 将 unified diff 文本应用到工作目录和/或 index（git apply）
+// 先逐个文件检查能否干净应用，有冲突时不修改任何内容并在结果中列出冲突的文件
+pub fn apply_patch_to_git_repo(
+    repo: &git2::Repository,
+    patch_text: &str,
+    target: PatchTarget,
+) -> Result<PatchApplyResult, Box<dyn std::error::Error>> {
+    let diff = git2::Diff::from_buffer(patch_text.as_bytes())?;
+    let path_of = |delta: &git2::DiffDelta| {
+        delta
+            .new_file()
+            .path()
+            .or_else(|| delta.old_file().path())
+            .map(repo_path_display)
+            .unwrap_or_default()
+    };
+    let paths: Vec<String> = diff.deltas().map(|delta| path_of(&delta)).collect();
+
+    let mut result = PatchApplyResult::default();
+    for path in &paths {
+        let mut apply_options = git2::ApplyOptions::new();
+        apply_options
+            .check(true)
+            .delta_callback(|delta| delta.is_some_and(|delta| path_of(&delta) == *path));
+        match repo.apply(&diff, target.to_git2(), Some(&mut apply_options)) {
+            Ok(()) => result.applied.push(path.clone()),
+            Err(e) if e.code() == git2::ErrorCode::ApplyFail => result.conflicts.push(path.clone()),
+            // 路径在 index 或工作目录中不存在也视为冲突
+            Err(e) if e.code() == git2::ErrorCode::NotFound => result.conflicts.push(path.clone()),
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    if !result.is_clean() {
+        result.applied.clear();
+        return Ok(result);
+    }
+    repo.apply(&diff, target.to_git2(), None)?;
+    Ok(result)
+}
+
+// 单个已跟踪文件工作目录相对 index 的 diff，路径按字面匹配
+fn unstaged_file_diff<'r>(
+    repo: &'r git2::Repository,
+    path: &Path,
+) -> Result<git2::Diff<'r>, Box<dyn std::error::Error>> {
+    require_workdir(repo, "git add -p")?;
+    let path = normalize_repo_path(repo.workdir(), path)?;
+    if repo.index()?.get_path(&path, 0).is_none() {
+        return Err(format!(
+            "文件 {} 未被跟踪，无法按 hunk 暂存",
+            repo_path_display(&path)
+        )
+        .into());
+    }
+    let mut diff_options = git2::DiffOptions::new();
+    diff_options.pathspec(&path).disable_pathspec_match(true);
+    Ok(repo.diff_index_to_workdir(None, Some(&mut diff_options))?)
+}
+
+// 列出文件未暂存的 hunk（git add -p 中逐个询问的部分），供调用方选择要暂存的序号
+pub fn list_git_repo_unstaged_hunks(
+    repo: &git2::Repository,
+    path: impl AsRef<Path>,
+) -> Result<Vec<DiffHunk>, Box<dyn std::error::Error>> {
+    let diff = unstaged_file_diff(repo, path.as_ref())?;
+    Ok(diff_to_file_diffs(&diff)?
+        .into_iter()
+        .next()
+        .map(|file_diff| file_diff.hunks)
+        .unwrap_or_default())
+}
+
+// 只暂存文件中序号在 hunk_indices 内的 hunk（序号对应 list_git_repo_unstaged_hunks 的结果），
+// 未选中的修改保留在工作目录中，返回 index 中该文件新的 blob oid
+pub fn stage_git_repo_hunks(
+    repo: &mut git2::Repository,
+    path: impl AsRef<Path>,
+    hunk_indices: &[usize],
+) -> Result<git2::Oid, Box<dyn std::error::Error>> {
+    let path = normalize_repo_path(repo.workdir(), path.as_ref())?;
+    let diff = unstaged_file_diff(repo, &path)?;
+    let hunk_count = diff_to_file_diffs(&diff)?
+        .first()
+        .map_or(0, |file_diff| file_diff.hunks.len());
+    if let Some(index) = hunk_indices.iter().find(|index| **index >= hunk_count) {
+        return Err(format!("hunk 序号 {} 超出范围，共 {} 个 hunk", index, hunk_count).into());
+    }
+
+    let mut next_hunk = 0;
+    let mut apply_options = git2::ApplyOptions::new();
+    apply_options.hunk_callback(|_| {
+        next_hunk += 1;
+        hunk_indices.contains(&(next_hunk - 1))
+    });
+    repo.apply(&diff, git2::ApplyLocation::Index, Some(&mut apply_options))?;
+
+    let entry = repo
+        .index()?
+        .get_path(&path, 0)
+        .ok_or_else(|| format!("index 中没有 {}", repo_path_display(&path)))?;
+    Ok(entry.id)
+}
+
+// 单个文件在 index 或工作目录中的状态，对应 git status --short 的状态字母
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileStatus {
+    Unmodified,
+    Added,
+    Modified,
+    Deleted,
+    Renamed,
+    TypeChanged,
+    Untracked,
+    Ignored,
+    Conflicted,
+}
+
+impl FileStatus {
+    // HEAD 与 index 之间的状态
+    pub fn index_from(status: git2::Status) -> Self {
+        if status.is_conflicted() {
+            FileStatus::Conflicted
+        } else if status.is_index_new() {
+            FileStatus::Added
+        } else if status.is_index_modified() {
+            FileStatus::Modified
+        } else if status.is_index_deleted() {
+            FileStatus::Deleted
+        } else if status.is_index_renamed() {
+            FileStatus::Renamed
+        } else if status.is_index_typechange() {
+            FileStatus::TypeChanged
+        } else if status.is_wt_new() {
+            FileStatus::Untracked
+        } else if status.is_ignored() {
+            FileStatus::Ignored
+        } else {
+            FileStatus::Unmodified
+        }
+    }
+
+    // index 与工作目录之间的状态
+    pub fn workdir_from(status: git2::Status) -> Self {
+        if status.is_conflicted() {
+            FileStatus::Conflicted
+        } else if status.is_wt_new() {
+            FileStatus::Untracked
+        } else if status.is_wt_modified() {
+            FileStatus::Modified
+        } else if status.is_wt_deleted() {
+            FileStatus::Deleted
+        } else if status.is_wt_renamed() {
+            FileStatus::Renamed
+        } else if status.is_wt_typechange() {
+            FileStatus::TypeChanged
+        } else if status.is_ignored() {
+            FileStatus::Ignored
+        } else {
+            FileStatus::Unmodified
+        }
+    }
+
+    pub fn status_char(&self) -> char {
+        match self {
+            FileStatus::Unmodified => ' ',
+            FileStatus::Added => 'A',
+            FileStatus::Modified => 'M',
+            FileStatus::Deleted => 'D',
+            FileStatus::Renamed => 'R',
+            FileStatus::TypeChanged => 'T',
+            FileStatus::Untracked => '?',
+            FileStatus::Ignored => '!',
+            FileStatus::Conflicted => 'U',
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatusEntry {
+    // 文件当前的路径（重命名时为新路径）
+    pub path: String,
+    pub index_status: FileStatus,
+    pub workdir_status: FileStatus,
+    // 暂存区中的重命名 (旧路径, 新路径)
+    pub index_rename: Option<(String, String)>,
+    // 工作目录中的重命名 (旧路径, 新路径)
+    pub workdir_rename: Option<(String, String)>,
+}
+
+impl StatusEntry {
+    // git status --short 格式的两个状态字母，如 "M "、"??"
+    pub fn short_code(&self) -> String {
+        format!(
+            "{}{}",
+            self.index_status.status_char(),
+            self.workdir_status.status_char()
+        )
+    }
+}
+
+// get_git_repo_status 的选项
+#[derive(Debug, Clone)]
+pub struct GitStatusOptions {
+    // 是否包含未跟踪的文件（递归列出未跟踪目录中的文件）
+    pub include_untracked: bool,
+    // 是否包含被忽略的文件
+    pub include_ignored: bool,
+    // 是否检测暂存区和工作目录中的重命名
+    pub detect_renames: bool,
+}
+
+impl Default for GitStatusOptions {
+    fn default() -> Self {
+        GitStatusOptions {
+            include_untracked: true,
+            include_ignored: false,
+            detect_renames: true,
+        }
+    }
+}
+
+// 等价于 git status，返回每个有变化的文件在 index 和工作目录中的状态
+pub fn get_git_repo_status(
+    repo: &git2::Repository,
+    options: &GitStatusOptions,
+) -> Result<Vec<StatusEntry>, Box<dyn std::error::Error>> {
+    require_workdir(repo, "git status")?;
+    let mut status_options = git2::StatusOptions::new();
+    status_options
+        .include_untracked(options.include_untracked)
+        .recurse_untracked_dirs(options.include_untracked)
+        .include_ignored(options.include_ignored)
+        .renames_head_to_index(options.detect_renames)
+        .renames_index_to_workdir(options.detect_renames);
+
+    let rename_of = |delta: Option<git2::DiffDelta>| -> Option<(String, String)> {
+        let delta = delta?;
+        if delta.status() != git2::Delta::Renamed {
+            return None;
+        }
+        let old_path = delta.old_file().path()?;
+        let new_path = delta.new_file().path()?;
+        Some((repo_path_display(old_path), repo_path_display(new_path)))
+    };
+
+    let statuses = repo.statuses(Some(&mut status_options))?;
+    let mut entries = Vec::new();
+    for entry in statuses.iter() {
+        let status = entry.status();
+        let index_rename = rename_of(entry.head_to_index());
+        let workdir_rename = rename_of(entry.index_to_workdir());
+        let path = match (&workdir_rename, &index_rename) {
+            (Some((_, new_path)), _) | (None, Some((_, new_path))) => new_path.clone(),
+            _ => String::from_utf8_lossy(entry.path_bytes()).to_string(),
+        };
+        entries.push(StatusEntry {
+            path,
+            index_status: FileStatus::index_from(status),
+            workdir_status: FileStatus::workdir_from(status),
+            index_rename,
+            workdir_rename,
+        });
+    }
+    Ok(entries)
+}
+
+// 对合并提交分别计算相对每个父节点的文件变更（git diff-tree -m），按父节点顺序返回
+pub fn merge_commit_diffs(
+    repo: &git2::Repository,
+    merge_commit_oid: git2::Oid,
+) -> Result<Vec<Vec<FileChange>>, Box<dyn std::error::Error>> {
+    let merge_commit = repo.find_commit(merge_commit_oid)?;
+
+    let mut diffs = Vec::new();
+    for parent_oid in merge_commit.parent_ids() {
+        diffs.push(name_status(repo, parent_oid, merge_commit_oid, false)?);
+    }
+    Ok(diffs)
+}
+
+// (路径, 新增行数, 删除行数)
+pub type FileLineChanges = (String, usize, usize);
+
+// 统计两个 commit 之间每个文件新增和删除的行数
+pub fn per_file_line_changes(
+    repo: &git2::Repository,
+    old_commit_oid: git2::Oid,
+    new_commit_oid: git2::Oid,
+) -> Result<Vec<FileLineChanges>, Box<dyn std::error::Error>> {
+    let old_tree = repo.find_commit(old_commit_oid)?.tree()?;
+    let new_tree = repo.find_commit(new_commit_oid)?.tree()?;
+    let diff = repo.diff_tree_to_tree(Some(&old_tree), Some(&new_tree), None)?;
+
+    let mut changes = Vec::new();
+    for (delta_idx, delta) in diff.deltas().enumerate() {
+        // 删除的文件只有 old_file 有路径
+        let path = delta
+            .new_file()
+            .path()
+            .or_else(|| delta.old_file().path())
+            .map(|path| path.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        // 二进制文件没有行级 patch，记为 0
+        let (additions, deletions) = match git2::Patch::from_diff(&diff, delta_idx)? {
+            Some(patch) => {
+                let (_, additions, deletions) = patch.line_stats()?;
+                (additions, deletions)
+            }
+            None => (0, 0),
+        };
+        changes.push((path, additions, deletions));
+    }
+    Ok(changes)
+}
+
+// 解析 revspec（git rev-parse），如 "HEAD~2"、"main@{1}"、"v1.0^{commit}"、"HEAD:src/lib.rs"
+// 返回解析到的对象 id 和类型，需要 commit 时可以在 spec 后追加 "^{commit}"
+pub fn resolve_git_revspec(
+    repo: &git2::Repository,
+    spec: &str,
+) -> Result<(git2::Oid, git2::ObjectType), Box<dyn std::error::Error>> {
+    let object = repo
+        .revparse_single(spec)
+        .map_err(|e| format!("无法解析 revspec {}: {}", spec, e.message()))?;
+    Ok((object.id(), object.kind().unwrap_or(git2::ObjectType::Any)))
+}
+
+// 计算两个 commit 的最佳共同祖先（git merge-base a b），没有共同历史时返回 None
+pub fn merge_base(
+    repo: &git2::Repository,
+    a: git2::Oid,
+    b: git2::Oid,
+) -> Result<Option<git2::Oid>, Box<dyn std::error::Error>> {
+    match repo.merge_base(a, b) {
+        Ok(oid) => Ok(Some(oid)),
+        Err(e) if e.code() == git2::ErrorCode::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+// commit 是否是 ancestor 的后代，commit 与 ancestor 相同时返回 false
+// 可在 reset / merge 前判断是否为快进：is_descendant_of(repo, target, HEAD) 为 true 时不会丢失提交
+pub fn is_descendant_of(
+    repo: &git2::Repository,
+    commit: git2::Oid,
+    ancestor: git2::Oid,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    Ok(repo.graph_descendant_of(commit, ancestor)?)
+}
+
+// merge_git_repo_branch 的选项
+#[derive(Debug, Clone, Default)]
+pub struct MergeOptions {
+    // 只允许快进（git merge --ff-only），无法快进时返回 MergeError::NotFastForward
+    pub ff_only: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MergeOutcome {
+    // 目标已经包含在当前分支中，无需合并
+    UpToDate,
+    // 当前分支快进到了该 commit
+    FastForward(git2::Oid),
+    // 创建了合并提交
+    Merged(git2::Oid),
+    // 合并产生冲突的路径，仓库保持在合并中状态（MERGE_HEAD），解决冲突后提交或调用 cleanup_state 放弃
+    Conflicts(Vec<String>),
+}
+
+// 合并过程中可以被调用方区分处理的错误，通过 Box<dyn Error> 返回，可用 downcast_ref 取得
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MergeError {
+    // 启用 ff_only 但历史已经分叉
+    NotFastForward { head: git2::Oid, target: git2::Oid },
+}
+
+impl std::fmt::Display for MergeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MergeError::NotFastForward { head, target } => {
+                write!(f, "无法快进: HEAD {} 不是 {} 的祖先", head, target)
+            }
+        }
+    }
+}
+
+impl std::error::Error for MergeError {}
+
+// 生成 git 默认格式的合并提交消息，如 "Merge branch 'a' into main"、"Merge branches 'a', 'b' and 'c' into main"，
+// conflicts 不为空时追加冲突文件列表
+pub fn default_merge_message(branch_names: &[&str], into: &str, conflicts: &[String]) -> String {
+    let quoted: Vec<String> = branch_names
+        .iter()
+        .map(|name| format!("'{}'", name))
+        .collect();
+    let branches = match quoted.split_last() {
+        Some((last, rest)) if !rest.is_empty() => {
+            format!("branches {} and {}", rest.join(", "), last)
+        }
+        _ => format!("branch {}", quoted.join("")),
+    };
+    let mut message = format!("Merge {} into {}\n", branches, into);
+    if !conflicts.is_empty() {
+        message.push_str("\nConflicts:\n");
+        for path in conflicts {
+            message.push_str(&format!("\t{}\n", path));
+        }
+    }
+    message
+}
+
+// 当前分支的短名称，分离 HEAD 时为 "HEAD"
+fn head_shorthand(repo: &git2::Repository) -> Result<String, Box<dyn std::error::Error>> {
+    let head = repo.head()?;
+    if repo.head_detached()? {
+        return Ok("HEAD".to_string());
+    }
+    Ok(head.shorthand().unwrap_or("HEAD").to_string())
+}
+
+// 将本地分支合并到当前分支（git merge <branch>），能快进时直接快进，否则创建合并提交
+// 检出使用 Safe 策略，工作目录中与合并冲突的本地修改会导致失败而不是被覆盖
+pub fn merge_git_repo_branch(
+    repo: &mut git2::Repository,
+    branch_name: &str,
+    options: &MergeOptions,
+) -> Result<MergeOutcome, Box<dyn std::error::Error>> {
+    merge_git_repo_branch_with_message(repo, branch_name, options, |message| message.to_string())
+}
+
+// message_fn 接收 default_merge_message 生成的消息，返回实际使用的合并提交消息；
+// 产生冲突时生成的消息（含冲突列表）写入 MERGE_MSG，供 conclude_merge_git_repo 使用
+pub fn merge_git_repo_branch_with_message<F>(
+    repo: &mut git2::Repository,
+    branch_name: &str,
+    options: &MergeOptions,
+    message_fn: F,
+) -> Result<MergeOutcome, Box<dyn std::error::Error>>
+where
+    F: FnOnce(&str) -> String,
+{
+    let branch = repo.find_branch(branch_name, git2::BranchType::Local)?;
+    let their_commit = branch.get().peel_to_commit()?;
+    let annotated = repo.reference_to_annotated_commit(branch.get())?;
+    let (analysis, _) = repo.merge_analysis(&[&annotated])?;
+
+    if analysis.is_up_to_date() {
+        return Ok(MergeOutcome::UpToDate);
+    }
+
+    if analysis.is_fast_forward() || analysis.is_unborn() {
+        repo.checkout_tree(
+            their_commit.as_object(),
+            Some(&mut build_checkout(CheckoutStrategy::Safe, false, false)),
+        )?;
+        update_git_repo_head_to_commit(
+            repo,
+            their_commit.id(),
+            &format!("merge {}: Fast-forward", branch_name),
+        )?;
+        return Ok(MergeOutcome::FastForward(their_commit.id()));
+    }
+
+    let head_commit = repo.head()?.peel_to_commit()?;
+    if options.ff_only {
+        return Err(Box::new(MergeError::NotFastForward {
+            head: head_commit.id(),
+            target: their_commit.id(),
+        }));
+    }
+
+    repo.merge(
+        &[&annotated],
+        None,
+        Some(&mut build_checkout(CheckoutStrategy::Safe, false, false)),
+    )?;
+    let mut index = repo.index()?;
+    let into = head_shorthand(repo)?;
+    if index.has_conflicts() {
+        let paths = index_conflict_paths(&index)?;
+        let message = message_fn(&default_merge_message(&[branch_name], &into, &paths));
+        fs::write(repo.path().join("MERGE_MSG"), message)?;
+        return Ok(MergeOutcome::Conflicts(paths));
+    }
+
+    let tree = repo.find_tree(index.write_tree()?)?;
+    let author = resolve_signature(repo, None, SignatureRole::Author)?;
+    let committer = resolve_signature(repo, None, SignatureRole::Committer)?;
+    let message = message_fn(&default_merge_message(&[branch_name], &into, &[]));
+    let merge_oid = repo.commit(
+        Some("HEAD"),
+        &author,
+        &committer,
+        &message,
+        &tree,
+        &[&head_commit, &their_commit],
+    )?;
+    repo.cleanup_state()?;
+    Ok(MergeOutcome::Merged(merge_oid))
+}
+
+// 解决冲突并暂存后完成进行中的合并（git commit），父节点为 HEAD 和 MERGE_HEAD 中的 commit，
+// 消息使用 MERGE_MSG 中合并时生成的内容
+pub fn conclude_merge_git_repo(
+    repo: &mut git2::Repository,
+) -> Result<git2::Oid, Box<dyn std::error::Error>> {
+    if repo.state() != git2::RepositoryState::Merge {
+        return Err("当前没有进行中的合并".into());
+    }
+    let mut index = repo.index()?;
+    if index.has_conflicts() {
+        return Err(format!("仍有未解决的冲突: {:?}", index_conflict_paths(&index)?).into());
+    }
+
+    let mut parent_oids = vec![repo.head()?.peel_to_commit()?.id()];
+    repo.mergehead_foreach(|oid| {
+        parent_oids.push(*oid);
+        true
+    })?;
+    let parents = parent_oids
+        .iter()
+        .map(|oid| repo.find_commit(*oid))
+        .collect::<Result<Vec<_>, _>>()?;
+    let parent_refs: Vec<&git2::Commit> = parents.iter().collect();
+
+    let tree = repo.find_tree(index.write_tree()?)?;
+    let message = repo.message()?;
+    let author = resolve_signature(repo, None, SignatureRole::Author)?;
+    let committer = resolve_signature(repo, None, SignatureRole::Committer)?;
+    let merge_oid = repo.commit(
+        Some("HEAD"),
+        &author,
+        &committer,
+        &message,
+        &tree,
+        &parent_refs,
+    )?;
+    repo.cleanup_state()?;
+    Ok(merge_oid)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RebaseOutcome {
+    // 变基完成，分支指向 head；rewritten 为本次调用中重写的 (原 commit, 新 commit)，
+    // 已包含在 onto 中的等价补丁会被跳过而不出现在列表里
+    Completed {
+        head: git2::Oid,
+        rewritten: Vec<(git2::Oid, git2::Oid)>,
+    },
+    // 第 step 步（从 0 开始）应用 commit 时产生冲突，变基保持进行中状态，
+    // 解决冲突并暂存后调用 continue_rebase_git_repo，或调用 abort_rebase_git_repo 放弃
+    Conflicts {
+        step: usize,
+        commit: git2::Oid,
+        paths: Vec<String>,
+    },
+}
+
+// index 中存在冲突的路径
+pub fn index_conflict_paths(
+    index: &git2::Index,
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let mut paths = Vec::new();
+    for conflict in index.conflicts()? {
+        let conflict = conflict?;
+        let entry = conflict.our.or(conflict.their).or(conflict.ancestor);
+        if let Some(entry) = entry {
+            paths.push(String::from_utf8_lossy(&entry.path).to_string());
+        }
+    }
+    Ok(paths)
+}
+
+// 冲突中某一方的文件版本
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConflictSide {
+    pub oid: git2::Oid,
+    pub mode: u32,
+}
+
+// index 中一个路径的冲突条目，某一方删除了文件时对应的版本为 None
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConflictEntry {
+    pub path: String,
+    // stage 1：共同祖先
+    pub ancestor: Option<ConflictSide>,
+    // stage 2：当前分支
+    pub ours: Option<ConflictSide>,
+    // stage 3：被合并进来的一方
+    pub theirs: Option<ConflictSide>,
+}
+
+// 列出 index 中所有冲突的路径及其各 stage 的版本
+pub fn list_git_repo_conflicts(
+    repo: &git2::Repository,
+) -> Result<Vec<ConflictEntry>, Box<dyn std::error::Error>> {
+    index_conflict_entries(&repo.index()?)
+}
+
+// 读取 index（仓库 index 或内存中合并得到的 index）中的冲突条目
+fn index_conflict_entries(
+    index: &git2::Index,
+) -> Result<Vec<ConflictEntry>, Box<dyn std::error::Error>> {
+    let side = |entry: &Option<git2::IndexEntry>| {
+        entry.as_ref().map(|entry| ConflictSide {
+            oid: entry.id,
+            mode: entry.mode,
+        })
+    };
+
+    let mut entries = Vec::new();
+    for conflict in index.conflicts()? {
+        let conflict = conflict?;
+        let Some(entry) = conflict
+            .our
+            .as_ref()
+            .or(conflict.their.as_ref())
+            .or(conflict.ancestor.as_ref())
+        else {
+            continue;
+        };
+        entries.push(ConflictEntry {
+            path: String::from_utf8_lossy(&entry.path).to_string(),
+            ancestor: side(&conflict.ancestor),
+            ours: side(&conflict.our),
+            theirs: side(&conflict.their),
+        });
+    }
+    Ok(entries)
+}
+
+// 内存中三方合并树的结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TreeMergeOutcome {
+    // 无冲突，合并结果树已写入对象库
+    Merged(git2::Oid),
+    // 存在冲突，未写入任何树
+    Conflicts(Vec<ConflictEntry>),
+}
+
+// 在内存中三方合并 ancestor/ours/theirs（可以是 commit 或 tree 的 OID），
+// 不修改 index 和工作目录，可用于裸仓库
+pub fn merge_trees_in_memory(
+    repo: &git2::Repository,
+    ancestor: git2::Oid,
+    ours: git2::Oid,
+    theirs: git2::Oid,
+) -> Result<TreeMergeOutcome, Box<dyn std::error::Error>> {
+    let peel_tree =
+        |oid| -> Result<git2::Tree, git2::Error> { repo.find_object(oid, None)?.peel_to_tree() };
+    let ancestor_tree = peel_tree(ancestor)?;
+    let our_tree = peel_tree(ours)?;
+    let their_tree = peel_tree(theirs)?;
+
+    let mut merged_index = repo.merge_trees(&ancestor_tree, &our_tree, &their_tree, None)?;
+    if merged_index.has_conflicts() {
+        return Ok(TreeMergeOutcome::Conflicts(index_conflict_entries(
+            &merged_index,
+        )?));
+    }
+    Ok(TreeMergeOutcome::Merged(merged_index.write_tree_to(repo)?))
+}
+
+// 冲突的解决方式
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Resolution {
+    // 使用当前分支的版本（git checkout --ours）
+    Ours,
+    // 使用被合并进来的版本（git checkout --theirs）
+    Theirs,
+    // 使用手动合并后的内容
+    Content(Vec<u8>),
+}
+
+// 解决单个路径的冲突：把选定的内容写入工作目录并暂存，index 中该路径的冲突条目随之清除。
+// 暂存时沿用选定一方的文件模式（可执行位、符号链接），手动合并的内容沿用当前分支（其次是被合并一方）的模式。
+// 选定的一方删除了该文件时，从工作目录和 index 中删除它
+pub fn resolve_conflict(
+    repo: &mut git2::Repository,
+    path: impl AsRef<Path>,
+    resolution: Resolution,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let path = normalize_repo_path(repo.workdir(), path.as_ref())?;
+    let path_display = repo_path_display(&path);
+    let conflict = list_git_repo_conflicts(repo)?
+        .into_iter()
+        .find(|conflict| conflict.path == path_display)
+        .ok_or_else(|| format!("路径 {} 没有冲突", path_display))?;
+
+    let (mode, content) = match resolution {
+        Resolution::Ours | Resolution::Theirs => {
+            let side = if resolution == Resolution::Ours {
+                conflict.ours
+            } else {
+                conflict.theirs
+            };
+            match side {
+                Some(side) => (
+                    side.mode,
+                    Some(repo.find_blob(side.oid)?.content().to_vec()),
+                ),
+                None => (0, None),
+            }
+        }
+        Resolution::Content(content) => {
+            let mode = conflict
+                .ours
+                .or(conflict.theirs)
+                .map_or(u32::from(git2::FileMode::Blob), |side| side.mode);
+            (mode, Some(content))
+        }
+    };
+
+    let file_path = require_workdir(repo, "解决冲突")?.join(&path);
+    let mut index = repo.index()?;
+    let Some(content) = content else {
+        if path_exists_no_follow(&file_path) {
+            fs::remove_file(&file_path)?;
+        }
+        index.remove_path(&path)?;
+        index.write()?;
+        return Ok(());
+    };
+
+    if let Some(parent) = file_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if path_exists_no_follow(&file_path) {
+        fs::remove_file(&file_path)?;
+    }
+    fs::write(&file_path, content)?;
+    index.add_path(&path)?;
+    if let Some(mut entry) = index.get_path(&path, 0)
+        && entry.mode != mode
+    {
+        entry.mode = mode;
+        index.add(&entry)?;
+    }
+    index.write()?;
+
+    // 按 index 中的模式重新检出该路径，使工作目录中的可执行位和符号链接与暂存的版本一致
+    let mut checkout = build_checkout(CheckoutStrategy::Force, false, false);
+    checkout.path(&path).disable_pathspec_match(true);
+    repo.checkout_index(Some(&mut index), Some(&mut checkout))?;
+    Ok(())
+}
+
+// 提交变基当前步骤的结果，补丁已存在于 onto 中（没有变化）时返回 None
+fn commit_rebase_step(
+    rebase: &mut git2::Rebase,
+    committer: &git2::Signature,
+) -> Result<Option<git2::Oid>, Box<dyn std::error::Error>> {
+    match rebase.commit(None, committer, None) {
+        Ok(oid) => Ok(Some(oid)),
+        Err(e) if e.code() == git2::ErrorCode::Applied => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+// 依次应用剩余的变基步骤，遇到冲突时停下
+fn run_rebase(
+    repo: &git2::Repository,
+    mut rebase: git2::Rebase,
+    mut rewritten: Vec<(git2::Oid, git2::Oid)>,
+) -> Result<RebaseOutcome, Box<dyn std::error::Error>> {
+    let committer = resolve_signature(repo, None, SignatureRole::Committer)?;
+    while let Some(operation) = rebase.next() {
+        let original = operation?.id();
+        let index = repo.index()?;
+        if index.has_conflicts() {
+            let step = rebase.operation_current().unwrap_or_default();
+            let paths = index_conflict_paths(&index)?;
+            return Ok(RebaseOutcome::Conflicts {
+                step,
+                commit: original,
+                paths,
+            });
+        }
+        if let Some(new_oid) = commit_rebase_step(&mut rebase, &committer)? {
+            rewritten.push((original, new_oid));
+        }
+    }
+    rebase.finish(Some(&committer))?;
+
+    let head = repo.head()?.peel_to_commit()?.id();
+    Ok(RebaseOutcome::Completed { head, rewritten })
+}
+
+// 将分支变基到 onto 之上（git rebase <onto> <branch>），onto 可以是任意 revspec；
+// 完成后 HEAD 指向变基后的分支
+pub fn rebase_git_repo_branch(
+    repo: &mut git2::Repository,
+    branch_name: &str,
+    onto: &str,
+) -> Result<RebaseOutcome, Box<dyn std::error::Error>> {
+    let branch = repo.find_branch(branch_name, git2::BranchType::Local)?;
+    let branch_commit = repo.reference_to_annotated_commit(branch.get())?;
+    let (onto_oid, _) = resolve_git_revspec(repo, &format!("{}^{{commit}}", onto))?;
+    let onto_commit = repo.find_annotated_commit(onto_oid)?;
+
+    let mut rebase_options = git2::RebaseOptions::new();
+    rebase_options.checkout_options(build_checkout(CheckoutStrategy::Safe, false, false));
+    let rebase = repo.rebase(
+        Some(&branch_commit),
+        Some(&onto_commit),
+        None,
+        Some(&mut rebase_options),
+    )?;
+    run_rebase(repo, rebase, Vec::new())
+}
+
+// 解决冲突并暂存后继续进行中的变基（git rebase --continue）
+pub fn continue_rebase_git_repo(
+    repo: &mut git2::Repository,
+) -> Result<RebaseOutcome, Box<dyn std::error::Error>> {
+    let mut rebase = repo.open_rebase(None)?;
+    let index = repo.index()?;
+    if index.has_conflicts() {
+        return Err(format!("仍有未解决的冲突: {:?}", index_conflict_paths(&index)?).into());
+    }
+
+    // 先提交冲突所在的当前步骤
+    let mut rewritten = Vec::new();
+    if let Some(current) = rebase.operation_current() {
+        let original = rebase.nth(current).ok_or("找不到当前的变基步骤")?.id();
+        let committer = resolve_signature(repo, None, SignatureRole::Committer)?;
+        if let Some(new_oid) = commit_rebase_step(&mut rebase, &committer)? {
+            rewritten.push((original, new_oid));
+        }
+    }
+    run_rebase(repo, rebase, rewritten)
+}
+
+// 放弃进行中的变基，恢复到变基前的分支和工作目录（git rebase --abort）
+pub fn abort_rebase_git_repo(
+    repo: &mut git2::Repository,
+) -> Result<(), Box<dyn std::error::Error>> {
+    repo.open_rebase(None)?.abort()?;
+    Ok(())
+}
+
+// cherry_pick_git_repo_commit 的选项
+#[derive(Debug, Clone)]
+pub struct CherryPickOptions {
+    // 挑选合并提交时以第几个父节点（从 1 开始）为基准（git cherry-pick -m）
+    pub mainline: Option<u32>,
+    // 无冲突时是否自动提交，为 false 时只暂存结果（git cherry-pick --no-commit）
+    pub commit: bool,
+}
+
+impl Default for CherryPickOptions {
+    fn default() -> Self {
+        CherryPickOptions {
+            mainline: None,
+            commit: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CherryPickOutcome {
+    // 自动创建的新 commit
+    Committed(git2::Oid),
+    // 未自动提交，变更已暂存到 index
+    Staged,
+    // 变更已包含在当前分支中，没有需要提交的内容
+    Empty,
+    // 产生冲突的路径，仓库保持在 cherry-pick 中状态
+    Conflicts(Vec<String>),
+}
+
+// 将 commit 的变更应用到当前分支（git cherry-pick），无冲突时沿用原作者和提交消息自动提交
+pub fn cherry_pick_git_repo_commit(
+    repo: &mut git2::Repository,
+    commit_oid: git2::Oid,
+    options: &CherryPickOptions,
+) -> Result<CherryPickOutcome, Box<dyn std::error::Error>> {
+    let commit = repo.find_commit(commit_oid)?;
+    let head_commit = repo.head()?.peel_to_commit()?;
+
+    let mut cherrypick_options = git2::CherrypickOptions::new();
+    cherrypick_options.checkout_builder(build_checkout(CheckoutStrategy::Safe, false, false));
+    if let Some(mainline) = options.mainline {
+        cherrypick_options.mainline(mainline);
+    }
+    repo.cherrypick(&commit, Some(&mut cherrypick_options))?;
+
+    let mut index = repo.index()?;
+    if index.has_conflicts() {
+        let paths = index_conflict_paths(&index)?;
+        return Ok(CherryPickOutcome::Conflicts(paths));
+    }
+
+    let tree_oid = index.write_tree()?;
+    if tree_oid == head_commit.tree_id() {
+        repo.cleanup_state()?;
+        return Ok(CherryPickOutcome::Empty);
+    }
+    if !options.commit {
+        return Ok(CherryPickOutcome::Staged);
+    }
+
+    let tree = repo.find_tree(tree_oid)?;
+    let committer = resolve_signature(repo, None, SignatureRole::Committer)?;
+    let new_oid = repo.commit(
+        Some("HEAD"),
+        &commit.author(),
+        &committer,
+        &String::from_utf8_lossy(commit.message_bytes()),
+        &tree,
+        &[&head_commit],
+    )?;
+    repo.cleanup_state()?;
+    Ok(CherryPickOutcome::Committed(new_oid))
+}
+
+// octopus 合并（git merge a b c）：在内存中依次把每个分支的 tree 合并到当前结果上，
+// 全部无冲突时才检出并创建一个以 HEAD 和各分支为父节点的合并提交；任一分支冲突时返回错误，
+// HEAD、index 和工作目录都不会被修改。已经包含在 HEAD 中的分支会被跳过
+// message_fn 接收 default_merge_message 生成的消息，返回实际使用的提交消息
+pub fn merge_git_repo_octopus<F>(
+    repo: &mut git2::Repository,
+    branch_names: &[&str],
+    message_fn: F,
+) -> Result<git2::Oid, Box<dyn std::error::Error>>
+where
+    F: FnOnce(&str) -> String,
+{
+    let head_commit = repo.head()?.peel_to_commit()?;
+    let mut merged_oids = vec![head_commit.id()];
+    let mut merged_tree = head_commit.tree()?;
+
+    for branch_name in branch_names {
+        let their_commit = repo
+            .find_branch(branch_name, git2::BranchType::Local)?
+            .get()
+            .peel_to_commit()?;
+        if merged_oids.contains(&their_commit.id())
+            || repo.graph_descendant_of(head_commit.id(), their_commit.id())?
+        {
+            continue;
+        }
+
+        // 与已合并的所有 commit 的共同祖先作为这一步的 base
+        let mut base_input = vec![their_commit.id()];
+        base_input.extend(&merged_oids);
+        let ancestor_tree = repo
+            .find_commit(merge_base_many(repo, &base_input)?)?
+            .tree()?;
+
+        let mut index =
+            repo.merge_trees(&ancestor_tree, &merged_tree, &their_commit.tree()?, None)?;
+        if index.has_conflicts() {
+            let paths = index_conflict_paths(&index)?;
+            return Err(format!("octopus 合并 {} 时产生冲突: {:?}", branch_name, paths).into());
+        }
+        merged_tree = repo.find_tree(index.write_tree_to(repo)?)?;
+        merged_oids.push(their_commit.id());
+    }
+
+    if merged_oids.len() < 2 {
+        return Err("没有需要合并的分支".into());
+    }
+
+    repo.checkout_tree(
+        merged_tree.as_object(),
+        Some(&mut build_checkout(CheckoutStrategy::Safe, false, false)),
+    )?;
+    let parents = merged_oids
+        .iter()
+        .map(|oid| repo.find_commit(*oid))
+        .collect::<Result<Vec<_>, _>>()?;
+    let parent_refs: Vec<&git2::Commit> = parents.iter().collect();
+    let merged_branches: Vec<&str> = branch_names
+        .iter()
+        .copied()
+        .filter(|name| {
+            repo.find_branch(name, git2::BranchType::Local)
+                .ok()
+                .and_then(|branch| branch.get().target())
+                .is_some_and(|oid| merged_oids[1..].contains(&oid))
+        })
+        .collect();
+    let message = message_fn(&default_merge_message(
+        &merged_branches,
+        &head_shorthand(repo)?,
+        &[],
+    ));
+    let author = resolve_signature(repo, None, SignatureRole::Author)?;
+    let committer = resolve_signature(repo, None, SignatureRole::Committer)?;
+    let merge_oid = repo.commit(
+        Some("HEAD"),
+        &author,
+        &committer,
+        &message,
+        &merged_tree,
+        &parent_refs,
+    )?;
+    Ok(merge_oid)
+}
+
+// 计算多个 commit 的共同祖先（用于 octopus merge 的 merge base）
+pub fn merge_base_many(
+    repo: &git2::Repository,
+    oids: &[git2::Oid],
+) -> Result<git2::Oid, Box<dyn std::error::Error>> {
+    if oids.len() < 2 {
+        return Err("计算 merge base 至少需要两个 commit".into());
+    }
+    Ok(repo.merge_base_many(oids)?)
+}
+
+// 两个引用的比较结果，ahead/behind 均相对 base 而言
+#[derive(Debug)]
+pub struct RefComparison {
+    pub merge_base: git2::Oid,
+    // head 有而 base 没有的 commit 数
+    pub ahead: usize,
+    // base 有而 head 没有的 commit 数
+    pub behind: usize,
+    // 从 merge base 到 head 的文件变更（git diff base...head）
+    pub changes: Vec<FileChange>,
+}
+
+// 比较两个引用（分支、tag 或任意 revspec），用于 "base...head" 对比视图
+pub fn compare_refs(
+    repo: &git2::Repository,
+    base_ref: &str,
+    head_ref: &str,
+) -> Result<RefComparison, Box<dyn std::error::Error>> {
+    let base_oid = repo.revparse_single(base_ref)?.peel_to_commit()?.id();
+    let head_oid = repo.revparse_single(head_ref)?.peel_to_commit()?.id();
+
+    let merge_base = repo.merge_base(base_oid, head_oid)?;
+    let (ahead, behind) = repo.graph_ahead_behind(head_oid, base_oid)?;
+    let changes = name_status(repo, merge_base, head_oid, false)?;
+
+    Ok(RefComparison {
+        merge_base,
+        ahead,
+        behind,
+        changes,
+    })
+}
+
+// 作者或提交者签名，offset_minutes 为时区偏移（如东八区为 480）
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SignatureInfo {
+    pub name: String,
+    pub email: String,
+    // unix 时间戳
+    pub time: i64,
+    pub offset_minutes: i32,
+}
+
+impl SignatureInfo {
+    fn from_signature(signature: &git2::Signature) -> Self {
+        SignatureInfo {
+            name: String::from_utf8_lossy(signature.name_bytes()).to_string(),
+            email: String::from_utf8_lossy(signature.email_bytes()).to_string(),
+            time: signature.when().seconds(),
+            offset_minutes: signature.when().offset_minutes(),
+        }
+    }
+}
+
+// commit 的元数据（git cat-file -p <commit>），oid 均为 40 位十六进制字符串，便于序列化
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CommitInfo {
+    pub id: String,
+    pub author: SignatureInfo,
+    pub committer: SignatureInfo,
+    // 完整的提交消息
+    pub message: String,
+    // 提交消息的首段（git log --format=%s）
+    pub summary: String,
+    pub parent_ids: Vec<String>,
+    pub tree_id: String,
+}
+
+impl CommitInfo {
+    pub fn from_commit(commit: &git2::Commit) -> Self {
+        CommitInfo {
+            id: commit.id().to_string(),
+            author: SignatureInfo::from_signature(&commit.author()),
+            committer: SignatureInfo::from_signature(&commit.committer()),
+            message: String::from_utf8_lossy(commit.message_bytes()).to_string(),
+            summary: commit
+                .summary_bytes()
+                .map(|bytes| String::from_utf8_lossy(bytes).to_string())
+                .unwrap_or_default(),
+            parent_ids: commit.parent_ids().map(|oid| oid.to_string()).collect(),
+            tree_id: commit.tree_id().to_string(),
+        }
+    }
+
+    // git log --oneline 格式：7 位短 id 加消息首行
+    pub fn oneline(&self) -> String {
+        format!("{} {}", &self.id[..7], self.summary)
+    }
+}
+
+// 读取 commit 的元数据，返回不依赖 git2::Commit 生命周期的结构
+pub fn get_git_repo_commit_info(
+    repo: &git2::Repository,
+    commit_oid: git2::Oid,
+) -> Result<CommitInfo, Box<dyn std::error::Error>> {
+    Ok(CommitInfo::from_commit(&repo.find_commit(commit_oid)?))
+}
+
+// 以可安全打印的形式返回 commit 消息：非 UTF-8 字节替换为替换字符，去掉结尾换行
+pub fn safe_display_message(commit: &git2::Commit) -> String {
+    String::from_utf8_lossy(commit.message_bytes())
+        .trim_end()
+        .to_string()
+}
+
+// 按顺序返回 commit 的父节点 oid，根 commit 返回空列表
+pub fn commit_parents(
+    repo: &git2::Repository,
+    commit_oid: git2::Oid,
+) -> Result<Vec<git2::Oid>, Box<dyn std::error::Error>> {
+    let commit = repo.find_commit(commit_oid)?;
+    Ok(commit.parent_ids().collect())
+}
+
+// 将 refs 可达的所有对象打包，按 v2 bundle 格式写入文件（git bundle create）
+// 格式: "# v2 git bundle" 头、每个 ref 一行 "<oid> <refname>"、空行，之后是 packfile。
+// 返回打包的对象个数
+pub fn create_bundle(
+    repo: &git2::Repository,
+    refs: &[&str],
+    out_path: &Path,
+) -> Result<usize, Box<dyn std::error::Error>> {
+    use std::io::Write;
+
+    if refs.is_empty() {
+        return Err("创建 bundle 至少需要一个 ref".into());
+    }
+
+    let mut header = String::from("# v2 git bundle\n");
+    let mut revwalk = repo.revwalk()?;
+    for ref_name in refs {
+        // 优先按完整名称查找（保留 HEAD 这类符号引用本身的名字），否则按短名称解析
+        let reference = match repo.find_reference(ref_name) {
+            Ok(reference) => reference,
+            Err(_) => repo.resolve_reference_from_short_name(ref_name)?,
+        };
+        let commit = reference.peel_to_commit()?;
+        let full_name = reference.name().ok_or("引用名称不是合法的 UTF-8")?;
+        header.push_str(&format!("{} {}\n", commit.id(), full_name));
+        revwalk.push(commit.id())?;
+    }
+    header.push('\n');
+
+    // 打包所有可达的 commit 及其 tree、blob
+    let mut pack_builder = repo.packbuilder()?;
+    pack_builder.insert_walk(&mut revwalk)?;
+    let mut pack = git2::Buf::new();
+    pack_builder.write_buf(&mut pack)?;
+
+    let mut file = fs::File::create(out_path)?;
+    file.write_all(header.as_bytes())?;
+    file.write_all(&pack)?;
+
+    Ok(pack_builder.object_count())
+}
+
+// 从 start（默认 HEAD）开始按拓扑 + 时间倒序遍历提交历史，最多返回 limit 个 commit id
+// first_parent 为 true 时只沿每个 commit 的第一个父节点遍历，跳过合并进来的分支提交
+pub fn log_commits(
+    repo: &git2::Repository,
+    start: Option<git2::Oid>,
+    limit: Option<usize>,
+    first_parent: bool,
+) -> Result<Vec<git2::Oid>, Box<dyn std::error::Error>> {
+    // 同一秒内的提交仅按时间排序不稳定，先保证拓扑顺序
+    log_commits_with_sort(repo, start, limit, first_parent, LogSort::TopologicalTime)
+}
+
+// 同 log_commits，按 sort 指定的顺序遍历
+pub fn log_commits_with_sort(
+    repo: &git2::Repository,
+    start: Option<git2::Oid>,
+    limit: Option<usize>,
+    first_parent: bool,
+    sort: LogSort,
+) -> Result<Vec<git2::Oid>, Box<dyn std::error::Error>> {
+    let mut revwalk = repo.revwalk()?;
+    match start {
+        Some(oid) => revwalk.push(oid)?,
+        None => revwalk.push_head()?,
+    }
+    if first_parent {
+        revwalk.simplify_first_parent()?;
+    }
+    revwalk.set_sorting(sort.to_git2())?;
+
+    let mut commit_ids = Vec::new();
+    for oid in revwalk.take(limit.unwrap_or(usize::MAX)) {
+        commit_ids.push(oid?);
+    }
+    Ok(commit_ids)
+}
+
+// git log 的排序方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogSort {
+    // 按提交时间倒序（git log --date-order 不保证父节点在子节点之后）
+    Time,
+    // 拓扑顺序，子节点总在父节点之前（git log --topo-order）
+    Topological,
+    // 拓扑顺序，同一层级内按时间倒序
+    TopologicalTime,
+    // 拓扑顺序反转，从最早的提交开始（git log --reverse）
+    Reverse,
+}
+
+impl LogSort {
+    fn to_git2(self) -> git2::Sort {
+        match self {
+            LogSort::Time => git2::Sort::TIME,
+            LogSort::Topological => git2::Sort::TOPOLOGICAL,
+            LogSort::TopologicalTime => git2::Sort::TOPOLOGICAL | git2::Sort::TIME,
+            LogSort::Reverse => git2::Sort::TOPOLOGICAL | git2::Sort::REVERSE,
+        }
+    }
+}
+
+// git log：从 start（默认 HEAD）开始遍历提交历史，按 sort 排序，最多返回 limit 条
+pub fn log_git_repo_commits(
+    repo: &git2::Repository,
+    start: Option<git2::Oid>,
+    limit: Option<usize>,
+    sort: LogSort,
+) -> Result<Vec<CommitInfo>, Box<dyn std::error::Error>> {
+    let mut entries = Vec::new();
+    for oid in log_commits_with_sort(repo, start, limit, false, sort)? {
+        let commit = repo.find_commit(oid)?;
+        entries.push(CommitInfo::from_commit(&commit));
+    }
+    Ok(entries)
+}
+
+// blame 的范围：行号从 1 开始且包含两端；字节范围会换算成覆盖这些字节的行
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BlameRange {
+    Lines(usize, usize),
+    Bytes(std::ops::Range<usize>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlameHunkInfo {
+    // 最后修改这些行的 commit
+    pub commit_id: git2::Oid,
+    // 在 blame 的文件版本中的起始行号（从 1 开始）及行数
+    pub start_line: usize,
+    pub lines: usize,
+    // 在 commit_id 中的原始起始行号和路径（文件被重命名时与当前路径不同）
+    pub orig_start_line: usize,
+    pub orig_path: Option<String>,
+    pub author_name: String,
+    pub author_email: String,
+    pub time: i64,
+}
+
+// git blame：返回文件在 rev（默认 HEAD）版本中每段连续行最后被修改的 commit 信息
+// range 可以限定只计算部分行，大文件只关心少量行时能显著减少耗时
+pub fn blame_git_repo_file(
+    repo: &git2::Repository,
+    path: impl AsRef<Path>,
+    rev: Option<git2::Oid>,
+    range: Option<BlameRange>,
+) -> Result<Vec<BlameHunkInfo>, Box<dyn std::error::Error>> {
+    let path = normalize_repo_path(repo.workdir(), path.as_ref())?;
+    let newest_commit = match rev {
+        Some(oid) => repo.find_commit(oid)?,
+        None => repo.head()?.peel_to_commit()?,
+    };
+
+    let mut blame_options = git2::BlameOptions::new();
+    blame_options.newest_commit(newest_commit.id());
+    let line_range = match range {
+        Some(BlameRange::Lines(start, end)) => Some((start, end)),
+        Some(BlameRange::Bytes(bytes)) => {
+            if bytes.start >= bytes.end {
+                return Err(format!("字节范围 {:?} 无效，起始位置必须小于结束位置", bytes).into());
+            }
+            let blob = newest_commit
+                .tree()?
+                .get_path(&path)?
+                .to_object(repo)?
+                .peel_to_blob()?;
+            let content = blob.content();
+            if bytes.end > content.len() {
+                return Err(format!("字节范围 {:?} 超出文件大小 {}", bytes, content.len()).into());
+            }
+            let line_of =
+                |offset: usize| content[..offset].iter().filter(|&&b| b == b'\n').count() + 1;
+            Some((line_of(bytes.start), line_of(bytes.end - 1)))
+        }
+        None => None,
+    };
+    if let Some((start, end)) = line_range {
+        blame_options.min_line(start).max_line(end);
+    }
+
+    let blame = repo.blame_file(&path, Some(&mut blame_options))?;
+    let mut hunks = Vec::with_capacity(blame.len());
+    for hunk in blame.iter() {
+        let signature = hunk.final_signature();
+        hunks.push(BlameHunkInfo {
+            commit_id: hunk.final_commit_id(),
+            start_line: hunk.final_start_line(),
+            lines: hunk.lines_in_hunk(),
+            orig_start_line: hunk.orig_start_line(),
+            orig_path: hunk.path().map(repo_path_display),
+            author_name: String::from_utf8_lossy(signature.name_bytes()).to_string(),
+            author_email: String::from_utf8_lossy(signature.email_bytes()).to_string(),
+            time: signature.when().seconds(),
+        });
+    }
+    Ok(hunks)
+}
+
+// 单个作者的提交统计
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthorStats {
+    pub name: String,
+    pub email: String,
+    pub commits: usize,
+    // 相对第一个父节点新增和删除的行数，合并提交不计入
+    pub insertions: usize,
+    pub deletions: usize,
+}
+
+// commit 第一个父节点的 tree，根提交返回 None；父 commit 缺失等其他错误照常返回
+fn first_parent_tree<'r>(
+    commit: &git2::Commit<'r>,
+) -> Result<Option<git2::Tree<'r>>, Box<dyn std::error::Error>> {
+    if commit.parent_count() == 0 {
+        return Ok(None);
+    }
+    Ok(Some(commit.parent(0)?.tree()?))
+}
+
+// 按作者汇总 revision 范围内的提交数和行数变更（git shortlog -sne），按提交数倒序、姓名升序排列
+// range 可以是 "v1.0..main" 这样的区间，也可以是单个 revspec（表示其全部历史）
+pub fn shortlog(
+    repo: &git2::Repository,
+    range: &str,
+) -> Result<Vec<AuthorStats>, Box<dyn std::error::Error>> {
+    let mut revwalk = repo.revwalk()?;
+    if range.contains("..") {
+        revwalk.push_range(range)?;
+    } else {
+        let (oid, _) = resolve_git_revspec(repo, &format!("{}^{{commit}}", range))?;
+        revwalk.push(oid)?;
+    }
+
+    let mut stats: Vec<AuthorStats> = Vec::new();
+    for oid in revwalk {
+        let commit = repo.find_commit(oid?)?;
+        let author = commit.author();
+        let name = String::from_utf8_lossy(author.name_bytes()).to_string();
+        let email = String::from_utf8_lossy(author.email_bytes()).to_string();
+
+        let (insertions, deletions) = if commit.parent_count() > 1 {
+            (0, 0)
+        } else {
+            let parent_tree = first_parent_tree(&commit)?;
+            let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&commit.tree()?), None)?;
+            let diff_stats = diff.stats()?;
+            (diff_stats.insertions(), diff_stats.deletions())
+        };
+
+        let index = match stats
+            .iter()
+            .position(|s| s.name == name && s.email == email)
+        {
+            Some(index) => index,
+            None => {
+                stats.push(AuthorStats {
+                    name,
+                    email,
+                    commits: 0,
+                    insertions: 0,
+                    deletions: 0,
+                });
+                stats.len() - 1
+            }
+        };
+        stats[index].commits += 1;
+        stats[index].insertions += insertions;
+        stats[index].deletions += deletions;
+    }
+
+    stats.sort_by(|a, b| b.commits.cmp(&a.commits).then_with(|| a.name.cmp(&b.name)));
+    Ok(stats)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReflogEntry {
+    // 引用变更前后指向的 commit，新建引用时 old_oid 为零
+    pub old_oid: git2::Oid,
+    pub new_oid: git2::Oid,
+    pub committer_name: String,
+    pub committer_email: String,
+    pub time: i64,
+    pub message: Option<String>,
+}
+
+// 读取引用的 reflog（git reflog show <refname>），按从新到旧的顺序返回
+pub fn read_git_repo_reflog(
+    repo: &git2::Repository,
+    refname: &str,
+) -> Result<Vec<ReflogEntry>, Box<dyn std::error::Error>> {
+    let reflog = repo.reflog(refname)?;
+    let entries = reflog
+        .iter()
+        .map(|entry| {
+            let committer = entry.committer();
+            ReflogEntry {
+                old_oid: entry.id_old(),
+                new_oid: entry.id_new(),
+                committer_name: String::from_utf8_lossy(committer.name_bytes()).to_string(),
+                committer_email: String::from_utf8_lossy(committer.email_bytes()).to_string(),
+                time: committer.when().seconds(),
+                message: entry
+                    .message_bytes()
+                    .map(|message| String::from_utf8_lossy(message).to_string()),
+            }
+        })
+        .collect();
+    Ok(entries)
+}
+
+// git cherry 的结果，commit 均按从旧到新的顺序排列
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CherryResult {
+    // 只在本地分支上、upstream 中没有等价补丁的 commit（git cherry 中的 "+"）
+    pub local_only: Vec<git2::Oid>,
+    // 只在 upstream 上、本地分支中没有等价补丁的 commit
+    pub upstream_only: Vec<git2::Oid>,
+    // 两边内容相同但 id 不同的 commit 对 (本地, upstream)，例如被 cherry-pick 或 rebase 过（git cherry 中的 "-"）
+    pub equivalent: Vec<(git2::Oid, git2::Oid)>,
+}
+
+// commit 相对第一个父节点的 patch id，合并提交返回 None
+fn commit_patch_id(
+    repo: &git2::Repository,
+    commit: &git2::Commit,
+) -> Result<Option<git2::Oid>, Box<dyn std::error::Error>> {
+    if commit.parent_count() > 1 {
+        return Ok(None);
+    }
+    let parent_tree = first_parent_tree(commit)?;
+    let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&commit.tree()?), None)?;
+    Ok(Some(diff.patchid(None)?))
+}
+
+// 比较本地分支和 upstream 分支（git cherry upstream local），用 patch id 判断两边独有的 commit
+pub fn cherry(
+    repo: &git2::Repository,
+    local: &str,
+    upstream: &str,
+) -> Result<CherryResult, Box<dyn std::error::Error>> {
+    let (local_oid, _) = resolve_git_revspec(repo, &format!("{}^{{commit}}", local))?;
+    let (upstream_oid, _) = resolve_git_revspec(repo, &format!("{}^{{commit}}", upstream))?;
+
+    // 从 include 可达但从 exclude 不可达的 commit 及其 patch id
+    let unique_commits = |include: git2::Oid, exclude: git2::Oid| {
+        let mut revwalk = repo.revwalk()?;
+        revwalk.push(include)?;
+        revwalk.hide(exclude)?;
+        revwalk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::REVERSE)?;
+        let mut commits = Vec::new();
+        for oid in revwalk {
+            let commit = repo.find_commit(oid?)?;
+            commits.push((commit.id(), commit_patch_id(repo, &commit)?));
+        }
+        Ok::<_, Box<dyn std::error::Error>>(commits)
+    };
+    let local_commits = unique_commits(local_oid, upstream_oid)?;
+    let upstream_commits = unique_commits(upstream_oid, local_oid)?;
+
+    let find_equivalent = |patch_id: Option<git2::Oid>,
+                           others: &[(git2::Oid, Option<git2::Oid>)]| {
+        patch_id.and_then(|patch_id| {
+            others
+                .iter()
+                .find(|(_, other_patch_id)| *other_patch_id == Some(patch_id))
+                .map(|(oid, _)| *oid)
+        })
+    };
+
+    let mut result = CherryResult::default();
+    for (oid, patch_id) in &local_commits {
+        match find_equivalent(*patch_id, &upstream_commits) {
+            Some(upstream_oid) => result.equivalent.push((*oid, upstream_oid)),
+            None => result.local_only.push(*oid),
+        }
+    }
+    for (oid, patch_id) in &upstream_commits {
+        if find_equivalent(*patch_id, &local_commits).is_none() {
+            result.upstream_only.push(*oid);
+        }
+    }
+    Ok(result)
+}
+
+// (commit id, 该 commit 中的文件内容)
+pub type FileSnapshot = (git2::Oid, Vec<u8>);
+
+// 从 HEAD 开始，返回每个修改过 file_relative_path 的 commit 及该 commit 中的文件完整内容
+// 与任一父节点中内容相同的 commit（如未涉及该文件的合并）不计入，文件被删除的 commit 也跳过
+pub fn file_snapshots(
+    repo: &git2::Repository,
+    file_relative_path: impl AsRef<Path>,
+    max: Option<usize>,
+) -> Result<Vec<FileSnapshot>, Box<dyn std::error::Error>> {
+    let path = normalize_repo_path(repo.workdir(), file_relative_path.as_ref())?;
+    let path = path.as_path();
+    let blob_id_at = |commit: &git2::Commit| -> Result<Option<git2::Oid>, git2::Error> {
+        Ok(match commit.tree()?.get_path(path) {
+            Ok(entry) if entry.kind() == Some(git2::ObjectType::Blob) => Some(entry.id()),
+            _ => None,
+        })
+    };
+
+    let mut snapshots = Vec::new();
+    for commit_oid in log_commits(repo, None, None, false)? {
+        if max.is_some_and(|max| snapshots.len() >= max) {
+            break;
+        }
+
+        let commit = repo.find_commit(commit_oid)?;
+        let Some(blob_id) = blob_id_at(&commit)? else {
+            continue;
+        };
+        let mut unchanged = false;
+        for parent in commit.parents() {
+            if blob_id_at(&parent)? == Some(blob_id) {
+                unchanged = true;
+                break;
+            }
+        }
+        if unchanged {
+            continue;
+        }
+
+        let blob = repo.find_blob(blob_id)?;
+        snapshots.push((commit_oid, blob.content().to_vec()));
+    }
+    Ok(snapshots)
+}
+
+// git2::Repository 的轻量封装，以方法调用的方式使用上面的函数，原有函数保持不变。
+// 支持 repo.add(..)?.commit(..)?.tag(..)? 的链式调用
+pub struct Repo {
+    repo: git2::Repository,
+    // add 之后、commit 之前暂存的 index
+    staged_index: Option<git2::Index>,
+    // 最近一次通过 commit 创建的 commit
+    last_commit: Option<git2::Oid>,
+}
+
+impl Repo {
+    pub fn open_or_init(dir: impl AsRef<Path>) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self::from_repository(open_or_init_git_repo(dir)?))
+    }
+
+    pub fn from_repository(repo: git2::Repository) -> Self {
+        Repo {
+            repo,
+            staged_index: None,
+            last_commit: None,
+        }
+    }
+
+    pub fn config_user(
+        &mut self,
+        name: &str,
+        email: &str,
+    ) -> Result<&mut Self, Box<dyn std::error::Error>> {
+        config_git_repo_user(&mut self.repo, name, email)?;
+        Ok(self)
+    }
+
+    // 添加文件到 index，index 暂存起来供下一次 commit 使用。磁盘上不存在的文件被跳过，
+    // 暂存删除需要使用 remove_files_from_git_repo
+    pub fn add<I, P>(
+        &mut self,
+        file_relative_paths: I,
+    ) -> Result<&mut Self, Box<dyn std::error::Error>>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+    {
+        let (index, _) = add_files_to_git_repo_index(&mut self.repo, file_relative_paths)?;
+        self.staged_index = Some(index);
+        Ok(self)
+    }
+
+    // 提交暂存的 index，没有调用过 add 时提交仓库当前的 index
+    pub fn commit(&mut self, message: &str) -> Result<&mut Self, Box<dyn std::error::Error>> {
+        let index = match self.staged_index.take() {
+            Some(index) => index,
+            None => self.repo.index()?,
+        };
+        let commit_oid =
+            commit_index_to_git_repo(&mut self.repo, index, message, &CommitOptions::default())?;
+        self.last_commit = Some(commit_oid);
+        Ok(self)
+    }
+
+    // 添加文件到 index 并提交
+    pub fn commit_files<I, P>(
+        &mut self,
+        file_relative_paths: I,
+        message: &str,
+    ) -> Result<git2::Oid, Box<dyn std::error::Error>>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+    {
+        self.add(file_relative_paths)?.commit(message)?;
+        Ok(self.last_commit.unwrap())
+    }
+
+    pub fn upsert_branch(
+        &mut self,
+        branch_name: &str,
+        target_oid: Option<git2::Oid>,
+    ) -> Result<&mut Self, Box<dyn std::error::Error>> {
+        upsert_branch_to_git_repo(&mut self.repo, branch_name, target_oid)?;
+        Ok(self)
+    }
+
+    // 在最近一次提交（没有则为 HEAD）上创建或更新分支
+    pub fn branch(&mut self, branch_name: &str) -> Result<&mut Self, Box<dyn std::error::Error>> {
+        self.upsert_branch(branch_name, self.last_commit)
+    }
+
+    pub fn upsert_tag(
+        &mut self,
+        tag_name: &str,
+        message: &str,
+        target_oid: Option<git2::Oid>,
+    ) -> Result<&mut Self, Box<dyn std::error::Error>> {
+        upsert_tag_to_git_repo(&mut self.repo, tag_name, message, target_oid)?;
+        Ok(self)
+    }
+
+    // 在最近一次提交（没有则为 HEAD）上创建或更新附注 tag，tag 消息使用 tag 名
+    pub fn tag(&mut self, tag_name: &str) -> Result<&mut Self, Box<dyn std::error::Error>> {
+        self.tag_with_message(tag_name, tag_name)
+    }
+
+    // 同 tag，使用指定的 tag 消息
+    pub fn tag_with_message(
+        &mut self,
+        tag_name: &str,
+        message: &str,
+    ) -> Result<&mut Self, Box<dyn std::error::Error>> {
+        self.upsert_tag(tag_name, message, self.last_commit)
+    }
+
+    // 切换分支，之前暂存的 index 作废
+    pub fn switch_branch(
+        &mut self,
+        branch_name: &str,
+        update_workdir: bool,
+    ) -> Result<&mut Self, Box<dyn std::error::Error>> {
+        switch_git_repo_branch(&mut self.repo, branch_name, update_workdir)?;
+        self.staged_index = None;
+        Ok(self)
+    }
+
+    pub fn reset_hard(
+        &mut self,
+        target_commit_oid: git2::Oid,
+    ) -> Result<&mut Self, Box<dyn std::error::Error>> {
+        reset_git_repo_head(&mut self.repo, target_commit_oid)?;
+        self.staged_index = None;
+        Ok(self)
+    }
+
+    pub fn log(&self, limit: Option<usize>) -> Result<Vec<git2::Oid>, Box<dyn std::error::Error>> {
+        log_commits(&self.repo, None, limit, false)
+    }
+
+    pub fn last_commit(&self) -> Option<git2::Oid> {
+        self.last_commit
+    }
+
+    pub fn into_inner(self) -> git2::Repository {
+        self.repo
+    }
+}
+
+// 仓库管理器即 Repo，保留这个名称以支持 manager.add(paths)?.commit("msg")?.tag("v1")? 的写法
+pub type GitRepoManager = Repo;
+
+impl std::ops::Deref for Repo {
+    type Target = git2::Repository;
+
+    fn deref(&self) -> &Self::Target {
+        &self.repo
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{clean_test_dir, init_test_repo, write_and_commit};
+
+    // 以 HEAD 为第一个父节点、theirs 为第二个父节点创建合并提交，tree 沿用 HEAD 的 tree
+    fn commit_merge(repo: &git2::Repository, theirs: git2::Oid, message: &str) -> git2::Oid {
+        let ours_commit = repo.head().unwrap().peel_to_commit().unwrap();
+        let theirs_commit = repo.find_commit(theirs).unwrap();
+        let signature = repo.signature().unwrap();
+        repo.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            message,
+            &ours_commit.tree().unwrap(),
+            &[&ours_commit, &theirs_commit],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_commit_maybe_signed_without_gpgsign() {
+        let (mut repo, dir) = init_test_repo("maybe_signed_off");
+        repo.config()
+            .unwrap()
+            .set_bool("commit.gpgsign", false)
+            .unwrap();
+        fs::write(dir.join("a.txt"), "a").unwrap();
+        let (index, _) = add_files_to_git_repo_index(&mut repo, vec!["a.txt"]).unwrap();
+
+        let commit_id = commit_maybe_signed(&mut repo, index, "unsigned").unwrap();
+
+        assert_eq!(repo.head().unwrap().target(), Some(commit_id));
+        assert!(repo.extract_signature(&commit_id, None).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_commit_maybe_signed_with_stub_gpg_program() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let (mut repo, dir) = init_test_repo("maybe_signed_on");
+        // 用一个桩脚本代替 gpg，吞掉输入并输出固定签名
+        let stub_path = dir.join(".git").join("stub-gpg.sh");
+        fs::write(
+            &stub_path,
+            "#!/bin/sh\ncat > /dev/null\necho '-----BEGIN PGP SIGNATURE-----'\necho 'stub'\necho '-----END PGP SIGNATURE-----'\n",
+        )
+        .unwrap();
+        fs::set_permissions(&stub_path, fs::Permissions::from_mode(0o755)).unwrap();
+        {
+            let mut config = repo.config().unwrap();
+            config.set_bool("commit.gpgsign", true).unwrap();
+            config.set_str("user.signingkey", "TESTKEY").unwrap();
+            config
+                .set_str("gpg.program", stub_path.to_str().unwrap())
+                .unwrap();
+        }
+        fs::write(dir.join("a.txt"), "a").unwrap();
+        let (index, _) = add_files_to_git_repo_index(&mut repo, vec!["a.txt"]).unwrap();
+
+        let commit_id = commit_maybe_signed(&mut repo, index, "signed").unwrap();
+
+        assert_eq!(repo.head().unwrap().target(), Some(commit_id));
+        let (signature, _) = repo.extract_signature(&commit_id, None).unwrap();
+        assert!(signature.as_str().unwrap().contains("BEGIN PGP SIGNATURE"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_path_exists_in_commit() {
+        let (mut repo, dir) = init_test_repo("path_exists");
+        let commit_id = write_and_commit(&mut repo, "subdir/test3.txt", "test3", "add test3");
+
+        assert!(path_exists_in_commit(&repo, Some(commit_id), "subdir/test3.txt").unwrap());
+        assert!(path_exists_in_commit(&repo, None, "subdir").unwrap());
+        assert!(!path_exists_in_commit(&repo, Some(commit_id), "subdir/missing.txt").unwrap());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_list_refs_with_glob() {
+        let (mut repo, dir) = init_test_repo("list_refs");
+        write_and_commit(&mut repo, "a.txt", "a", "init");
+        upsert_branch_to_git_repo(&mut repo, "feature_1", None).unwrap();
+        upsert_branch_to_git_repo(&mut repo, "feature_2", None).unwrap();
+        upsert_tag_to_git_repo(&mut repo, "v1", "v1", None).unwrap();
+
+        let mut branches = list_refs(&repo, Some("refs/heads/*")).unwrap();
+        branches.sort();
+        assert_eq!(
+            branches,
+            vec![
+                "refs/heads/feature_1",
+                "refs/heads/feature_2",
+                "refs/heads/main"
+            ]
+        );
+
+        let all_refs = list_refs(&repo, None).unwrap();
+        assert!(all_refs.contains(&"refs/tags/v1".to_string()));
+        assert_eq!(all_refs.len(), 4);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_add_files_retries_while_index_locked() {
+        let (mut repo, dir) = init_test_repo("index_lock_retry");
+        fs::write(dir.join("a.txt"), "a").unwrap();
+
+        // 模拟另一个进程持有 index.lock，稍后释放
+        let lock_path = dir.join(".git").join("index.lock");
+        fs::write(&lock_path, "").unwrap();
+        let release_lock_path = lock_path.clone();
+        let releaser = std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(100));
+            fs::remove_file(release_lock_path).unwrap();
+        });
+
+        let retry = IndexWriteRetry {
+            max_attempts: 10,
+            initial_backoff: std::time::Duration::from_millis(20),
+        };
+        let index = add_files_to_git_repo_index_with_retry(&mut repo, vec!["a.txt"], &retry);
+        releaser.join().unwrap();
+
+        let (index, report) = index.unwrap();
+        assert!(index.get_path(Path::new("a.txt"), 0).is_some());
+        assert_eq!(report.changed, vec!["a.txt".to_string()]);
+        assert!(!lock_path.exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_commit_reproducible_same_oid_across_repos() {
+        let mut commit_ids = Vec::new();
+        for name in ["reproducible_1", "reproducible_2"] {
+            let (mut repo, dir) = init_test_repo(name);
+            fs::create_dir_all(dir.join("subdir")).unwrap();
+            fs::write(dir.join("subdir/test3.txt"), "Hello, Git!").unwrap();
+            let (index, _) =
+                add_files_to_git_repo_index(&mut repo, vec!["subdir/test3.txt"]).unwrap();
+
+            commit_ids
+                .push(commit_reproducible(&mut repo, index, "release", 1_700_000_000).unwrap());
+            fs::remove_dir_all(&dir).unwrap();
+        }
+
+        assert_eq!(commit_ids[0], commit_ids[1]);
+    }
+
+    #[test]
+    fn test_list_submodules_reports_path_and_url() {
+        let (mut sub_repo, sub_dir) = init_test_repo("submodule_origin");
+        write_and_commit(&mut sub_repo, "lib.txt", "lib", "init lib");
+        let (mut repo, dir) = init_test_repo("submodule_parent");
+        write_and_commit(&mut repo, "a.txt", "a", "init");
+
+        // 使用本地路径作为子模块的 url
+        let sub_url = sub_dir.to_str().unwrap();
+        let mut submodule = repo
+            .submodule(sub_url, Path::new("libs/sub"), true)
+            .unwrap();
+        submodule.clone(None).unwrap();
+        submodule.add_finalize().unwrap();
+
+        let submodules = list_submodules(&repo).unwrap();
+        assert_eq!(submodules.len(), 1);
+        assert_eq!(submodules[0].name, "libs/sub");
+        assert_eq!(submodules[0].path, "libs/sub");
+        assert_eq!(submodules[0].url.as_deref(), Some(sub_url));
+        fs::remove_dir_all(&dir).unwrap();
+        fs::remove_dir_all(&sub_dir).unwrap();
+    }
+
+    #[test]
+    fn test_update_submodule_checks_out_files() {
+        let (mut sub_repo, sub_dir) = init_test_repo("update_submodule_origin");
+        let sub_commit = write_and_commit(&mut sub_repo, "lib.txt", "lib", "init lib");
+        let (mut repo, dir) = init_test_repo("update_submodule_parent");
+        {
+            let mut submodule = repo
+                .submodule(sub_dir.to_str().unwrap(), Path::new("libs/sub"), true)
+                .unwrap();
+            submodule.clone(None).unwrap();
+            submodule.add_finalize().unwrap();
+        }
+        let index = repo.index().unwrap();
+        commit_index_to_git_repo(&mut repo, index, "add submodule", &CommitOptions::default())
+            .unwrap();
+
+        // clone 父仓库后子模块目录为空，需要 update 才会检出
+        let clone_dir = dir.with_file_name(format!(
+            "{}_clone",
+            dir.file_name().unwrap().to_str().unwrap()
+        ));
+        if clone_dir.exists() {
+            fs::remove_dir_all(&clone_dir).unwrap();
+        }
+        let cloned = git2::Repository::clone(dir.to_str().unwrap(), &clone_dir).unwrap();
+        assert!(!clone_dir.join("libs/sub/lib.txt").exists());
+
+        let head_id = update_submodule(&cloned, "libs/sub", true).unwrap();
+        assert_eq!(head_id, Some(sub_commit));
+
+        assert_eq!(
+            fs::read_to_string(clone_dir.join("libs/sub/lib.txt")).unwrap(),
+            "lib"
+        );
+        fs::remove_dir_all(&clone_dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        fs::remove_dir_all(&sub_dir).unwrap();
+    }
+
+    #[test]
+    fn test_commit_without_changes_is_skipped_unless_allow_empty() {
+        let (mut repo, dir) = init_test_repo("allow_empty");
+        let head_id = write_and_commit(&mut repo, "a.txt", "a", "init");
+
+        let (index, _) = add_files_to_git_repo_index(&mut repo, vec!["a.txt"]).unwrap();
+        let commit_id =
+            commit_index_to_git_repo(&mut repo, index, "nothing", &CommitOptions::default())
+                .unwrap();
+        assert_eq!(commit_id, head_id);
+        assert_eq!(repo.head().unwrap().target(), Some(head_id));
+
+        let index = repo.index().unwrap();
+        let empty_commit_id = commit_index_to_git_repo(
+            &mut repo,
+            index,
+            "empty",
+            &CommitOptionsBuilder::new().allow_empty(true).build(),
+        )
+        .unwrap();
+        assert_ne!(empty_commit_id, head_id);
+        let empty_commit = repo.find_commit(empty_commit_id).unwrap();
+        assert_eq!(empty_commit.parent_id(0).unwrap(), head_id);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_blob_lines_handles_crlf() {
+        let (mut repo, dir) = init_test_repo("blob_lines");
+        let commit_id =
+            write_and_commit(&mut repo, "crlf.txt", "line1\r\nline2\nline3\r\n", "crlf");
+        let entry =
+            lookup_entry_from_git_repo_commit_tree_by_path(&repo, Some(commit_id), "crlf.txt")
+                .unwrap()
+                .unwrap();
+
+        let lines = blob_lines(&repo, entry.oid).unwrap();
+
+        assert_eq!(lines, vec!["line1", "line2", "line3"]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_blob_lines_rejects_binary() {
+        let (repo, dir) = init_test_repo("blob_lines_binary");
+        let blob_oid = repo.blob(&[0u8, 159, 146, 150, 0]).unwrap();
+
+        assert!(blob_lines(&repo, blob_oid).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_repo_wrapper_fluent_methods() {
+        let (repo, dir) = init_test_repo("repo_wrapper");
+        drop(repo);
+        let mut repo = Repo::open_or_init(dir.to_str().unwrap()).unwrap();
+
+        fs::write(dir.join("a.txt"), "a").unwrap();
+        let commit_id1 = repo
+            .config_user("Other", "other@example.com")
+            .unwrap()
+            .commit_files(vec!["a.txt"], "first")
+            .unwrap();
+        fs::write(dir.join("b.txt"), "b").unwrap();
+        let commit_id2 = repo
+            .upsert_branch("feature", None)
+            .unwrap()
+            .commit_files(vec!["b.txt"], "second")
+            .unwrap();
+
+        assert_eq!(repo.log(None).unwrap(), vec![commit_id2, commit_id1]);
+        repo.switch_branch("feature", true).unwrap();
+        assert_eq!(repo.log(None).unwrap(), vec![commit_id1]);
+        assert!(!dir.join("b.txt").exists());
+        assert_eq!(repo.head().unwrap().shorthand(), Some("feature"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_per_file_line_changes() {
+        let (mut repo, dir) = init_test_repo("per_file_line_changes");
+        fs::write(dir.join("a.txt"), "1\n2\n3\n").unwrap();
+        fs::write(dir.join("b.txt"), "1\n2\n3\n4\n").unwrap();
+        let (index, _) = add_files_to_git_repo_index(&mut repo, vec!["a.txt", "b.txt"]).unwrap();
+        let old_commit_id =
+            commit_index_to_git_repo(&mut repo, index, "init", &CommitOptions::default()).unwrap();
+
+        // a.txt: 新增 2 行；b.txt: 删除 2 行并修改 1 行
+        fs::write(dir.join("a.txt"), "1\n2\n3\n4\n5\n").unwrap();
+        fs::write(dir.join("b.txt"), "1\nx\n").unwrap();
+        let (index, _) = add_files_to_git_repo_index(&mut repo, vec!["a.txt", "b.txt"]).unwrap();
+        let new_commit_id =
+            commit_index_to_git_repo(&mut repo, index, "change", &CommitOptions::default())
+                .unwrap();
+
+        let changes = per_file_line_changes(&repo, old_commit_id, new_commit_id).unwrap();
+
+        assert_eq!(
+            changes,
+            vec![("a.txt".to_string(), 2, 0), ("b.txt".to_string(), 1, 3)]
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_read_tag_round_trips_message() {
+        let (mut repo, dir) = init_test_repo("read_tag");
+        let commit_id = write_and_commit(&mut repo, "a.txt", "a", "init");
+        upsert_tag_to_git_repo(&mut repo, "v1", "release v1\n", None).unwrap();
+        let commit = repo.find_commit(commit_id).unwrap();
+        repo.tag_lightweight("light", commit.as_object(), false)
+            .unwrap();
+
+        let tag_info = read_tag(&repo, "v1").unwrap().unwrap();
+        assert_eq!(tag_info.name, "v1");
+        assert_eq!(tag_info.tag_id, repo.refname_to_id("refs/tags/v1").unwrap());
+        assert_eq!(tag_info.target, commit_id);
+        assert_eq!(tag_info.target_kind, git2::ObjectType::Commit);
+        assert_eq!(tag_info.message.as_deref(), Some("release v1\n"));
+        assert_eq!(tag_info.tagger_name.as_deref(), Some("TestUser"));
+        assert_eq!(tag_info.tagger_email.as_deref(), Some("test@example.com"));
+        assert!(tag_info.tag_time.is_some());
+
+        // 附注标签可以指向 tree
+        let tree = commit.tree().unwrap();
+        let signature = repo.signature().unwrap();
+        repo.tag("tree-tag", tree.as_object(), &signature, "tree", false)
+            .unwrap();
+        let tag_info = read_tag(&repo, "tree-tag").unwrap().unwrap();
+        assert_eq!(tag_info.target, tree.id());
+        assert_eq!(tag_info.target_kind, git2::ObjectType::Tree);
+
+        assert!(read_tag(&repo, "light").unwrap().is_none());
+        assert!(read_tag(&repo, "missing").is_err());
+        assert_eq!(
+            get_git_repo_tag_info(&repo, "tree-tag").unwrap(),
+            Some(tag_info)
+        );
+        assert!(get_git_repo_tag_info(&repo, "light").unwrap().is_none());
+        assert!(get_git_repo_tag_info(&repo, "missing").is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_log_commits_first_parent_skips_side_branch() {
+        let (mut repo, dir) = init_test_repo("log_first_parent");
+        let base_id = write_and_commit(&mut repo, "a.txt", "a", "base");
+        upsert_branch_to_git_repo(&mut repo, "feature", None).unwrap();
+        switch_git_repo_branch(&mut repo, "feature", true).unwrap();
+        let side_id = write_and_commit(&mut repo, "side.txt", "side", "side");
+        switch_git_repo_branch(&mut repo, "main", true).unwrap();
+        let main_id = write_and_commit(&mut repo, "b.txt", "b", "main");
+        let merge_id = commit_merge(&repo, side_id, "merge feature");
+
+        let all = log_commits(&repo, None, None, false).unwrap();
+        assert_eq!(all.len(), 4);
+        assert!(all.contains(&side_id));
+
+        let first_parent = log_commits(&repo, None, None, true).unwrap();
+        assert_eq!(first_parent, vec![merge_id, main_id, base_id]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_checkout_index_restores_staged_content() {
+        let (mut repo, dir) = init_test_repo("checkout_index");
+        write_and_commit(&mut repo, "a.txt", "committed", "init");
+        fs::write(dir.join("a.txt"), "staged").unwrap();
+        add_files_to_git_repo_index(&mut repo, vec!["a.txt"]).unwrap();
+        fs::write(dir.join("a.txt"), "corrupted").unwrap();
+
+        checkout_index(&mut repo).unwrap();
+
+        assert_eq!(fs::read_to_string(dir.join("a.txt")).unwrap(), "staged");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_name_status_detects_copies() {
+        let (mut repo, dir) = init_test_repo("name_status_copies");
+        let content = "line 1\nline 2\nline 3\nline 4\nline 5\n";
+        let old_commit_id = write_and_commit(&mut repo, "origin.txt", content, "init");
+        let new_commit_id = write_and_commit(&mut repo, "copy.txt", content, "copy");
+
+        let changes = name_status(&repo, old_commit_id, new_commit_id, true).unwrap();
+        assert_eq!(
+            changes,
+            vec![FileChange {
+                kind: ChangeKind::Copied,
+                old_path: Some("origin.txt".to_string()),
+                new_path: Some("copy.txt".to_string()),
+            }]
+        );
+        assert_eq!(changes[0].kind.status_char(), 'C');
+
+        let changes = name_status(&repo, old_commit_id, new_commit_id, false).unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].kind, ChangeKind::Added);
+        assert_eq!(changes[0].old_path, None);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_tree_to_nested_json() {
+        let (mut repo, dir) = init_test_repo("tree_to_nested_json");
+        fs::create_dir_all(dir.join("dir1/subdir1")).unwrap();
+        fs::write(dir.join("file1.txt"), "file1").unwrap();
+        fs::write(dir.join("dir1/subdir1/file4.txt"), "file4").unwrap();
+        let (index, _) =
+            add_files_to_git_repo_index(&mut repo, vec!["file1.txt", "dir1/subdir1/file4.txt"])
+                .unwrap();
+        let commit_id =
+            commit_index_to_git_repo(&mut repo, index, "nested", &CommitOptions::default())
+                .unwrap();
+
+        let json = tree_to_nested_json(&repo, Some(commit_id)).unwrap();
+
+        let file4 = lookup_entry_from_git_repo_commit_tree_by_path(
+            &repo,
+            Some(commit_id),
+            "dir1/subdir1/file4.txt",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(json["dir1"]["subdir1"]["file4.txt"], file4.oid.to_string());
+        assert!(json["file1.txt"].is_string());
+        assert_eq!(json.as_object().unwrap().len(), 2);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_switch_to_current_branch_is_noop() {
+        let (mut repo, dir) = init_test_repo("switch_current_branch");
+        write_and_commit(&mut repo, "a.txt", "a", "init");
+        upsert_branch_to_git_repo(&mut repo, "feature", None).unwrap();
+        switch_git_repo_branch(&mut repo, "feature", true).unwrap();
+        let mtime_before = fs::metadata(dir.join("a.txt")).unwrap().modified().unwrap();
+        // 让文件系统时间前进，若文件被重写 mtime 会变化
+        std::thread::sleep(std::time::Duration::from_millis(20));
+
+        {
+            let branch_ref = switch_git_repo_branch(&mut repo, "feature", true).unwrap();
+            assert_eq!(branch_ref.name(), Some("refs/heads/feature"));
+        }
+
+        let mtime_after = fs::metadata(dir.join("a.txt")).unwrap().modified().unwrap();
+        assert_eq!(mtime_before, mtime_after);
+        assert_eq!(repo.head().unwrap().shorthand(), Some("feature"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_merge_base_many_finds_common_root() {
+        let (mut repo, dir) = init_test_repo("merge_base_many");
+        let root_id = write_and_commit(&mut repo, "root.txt", "root", "root");
+        let mut tips = Vec::new();
+        for branch_name in ["branch_a", "branch_b", "branch_c"] {
+            upsert_branch_to_git_repo(&mut repo, branch_name, Some(root_id)).unwrap();
+            switch_git_repo_branch(&mut repo, branch_name, true).unwrap();
+            let file_name = format!("{}.txt", branch_name);
+            tips.push(write_and_commit(
+                &mut repo,
+                &file_name,
+                branch_name,
+                branch_name,
+            ));
+        }
+
+        assert_eq!(merge_base_many(&repo, &tips).unwrap(), root_id);
+        assert!(merge_base_many(&repo, &tips[..1]).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_create_bundle_for_head() {
+        let (mut repo, dir) = init_test_repo("create_bundle");
+        write_and_commit(&mut repo, "a.txt", "a", "first");
+        let head_id = write_and_commit(&mut repo, "dir/b.txt", "b", "second");
+        let bundle_path = dir.join(".git").join("repo.bundle");
+
+        // 2 个 commit、3 个 tree（两个根 tree 和 dir）、2 个 blob
+        let object_count = create_bundle(&repo, &["HEAD"], &bundle_path).unwrap();
+        assert_eq!(object_count, 7);
+
+        let bundle = fs::read(&bundle_path).unwrap();
+        let header_end = bundle.windows(2).position(|w| w == b"\n\n").unwrap() + 2;
+        let header = String::from_utf8(bundle[..header_end].to_vec()).unwrap();
+        let mut lines = header.lines();
+        assert_eq!(lines.next(), Some("# v2 git bundle"));
+        assert_eq!(lines.next(), Some(format!("{} HEAD", head_id).as_str()));
+
+        // 把 packfile 部分导入另一个仓库，验证其中包含完整的历史
+        let (other_repo, other_dir) = init_test_repo("create_bundle_import");
+        {
+            let odb = other_repo.odb().unwrap();
+            let mut pack_writer = odb.packwriter().unwrap();
+            std::io::Write::write_all(&mut pack_writer, &bundle[header_end..]).unwrap();
+            pack_writer.commit().unwrap();
+        }
+        let imported = other_repo.find_commit(head_id).unwrap();
+        assert_eq!(imported.parent_count(), 1);
+        assert!(
+            imported
+                .tree()
+                .unwrap()
+                .get_path(Path::new("dir/b.txt"))
+                .is_ok()
+        );
+        fs::remove_dir_all(&other_dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_count_lines_by_extension() {
+        let (mut repo, dir) = init_test_repo("count_lines");
+        write_and_commit(&mut repo, "a.txt", "1\n2\n3\n", "add a");
+        write_and_commit(&mut repo, "sub/b.txt", "1\n2", "add b");
+        write_and_commit(&mut repo, "c.md", "1\n2\n3\n4\n", "add c");
+        write_and_commit(&mut repo, "d.bin", "\0\0\n\0", "add d");
+
+        assert_eq!(count_lines(&repo, None, &["txt"]).unwrap(), 5);
+        assert_eq!(count_lines(&repo, None, &[".txt", "md"]).unwrap(), 9);
+        assert_eq!(count_lines(&repo, None, &[]).unwrap(), 9);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_branch_commit_time_orders_branches() {
+        let (mut repo, dir) = init_test_repo("branch_commit_time");
+        let first = write_and_commit(&mut repo, "a.txt", "a", "first");
+
+        // 用指定时间的签名在 first 之上创建两个分支的提交
+        let base_commit = repo.find_commit(first).unwrap();
+        let tree = base_commit.tree().unwrap();
+        for (branch_name, seconds) in [("old", 1_000_000), ("new", 2_000_000)] {
+            let signature =
+                git2::Signature::new("TestUser", "test@example.com", &git2::Time::new(seconds, 0))
+                    .unwrap();
+            repo.commit(
+                Some(&format!("refs/heads/{}", branch_name)),
+                &signature,
+                &signature,
+                branch_name,
+                &tree,
+                &[&base_commit],
+            )
+            .unwrap();
+        }
+
+        let old_time = branch_commit_time(&repo, "old").unwrap();
+        let new_time = branch_commit_time(&repo, "new").unwrap();
+        assert_eq!(old_time, 1_000_000);
+        assert_eq!(new_time, 2_000_000);
+
+        let mut branches = vec!["old", "new"];
+        branches.sort_by_key(|name| std::cmp::Reverse(branch_commit_time(&repo, name).unwrap()));
+        assert_eq!(branches, vec!["new", "old"]);
+        assert!(branch_commit_time(&repo, "missing").is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_stage_removal_records_deletion() {
+        let (mut repo, dir) = init_test_repo("stage_removal");
+        write_and_commit(&mut repo, "keep.txt", "keep", "add keep");
+        write_and_commit(&mut repo, "gone.txt", "gone", "add gone");
+
+        // 文件仍在磁盘上，也应当被记录为删除
+        stage_removal(&mut repo, "gone.txt").unwrap();
+        assert!(dir.join("gone.txt").exists());
+        let index = repo.index().unwrap();
+        let commit_oid =
+            commit_index_to_git_repo(&mut repo, index, "remove gone", &CommitOptions::default())
+                .unwrap();
+
+        assert!(!path_exists_in_commit(&repo, Some(commit_oid), "gone.txt").unwrap());
+        assert!(path_exists_in_commit(&repo, Some(commit_oid), "keep.txt").unwrap());
+
+        let error = stage_removal(&mut repo, "untracked.txt").unwrap_err();
+        assert!(error.to_string().contains("untracked.txt"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_commit_parents_of_merge_and_root() {
+        let (mut repo, dir) = init_test_repo("commit_parents");
+        let root = write_and_commit(&mut repo, "a.txt", "a", "root");
+        let ours = write_and_commit(&mut repo, "b.txt", "b", "ours");
+        let theirs = {
+            let root_commit = repo.find_commit(root).unwrap();
+            let signature = repo.signature().unwrap();
+            repo.commit(
+                None,
+                &signature,
+                &signature,
+                "theirs",
+                &root_commit.tree().unwrap(),
+                &[&root_commit],
+            )
+            .unwrap()
+        };
+        let merge = commit_merge(&repo, theirs, "merge");
+
+        assert_eq!(commit_parents(&repo, merge).unwrap(), vec![ours, theirs]);
+        assert_eq!(
+            commit_parents(&repo, root).unwrap(),
+            Vec::<git2::Oid>::new()
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_build_checkout_strategies_on_dirty_worktree() {
+        let (mut repo, dir) = init_test_repo("build_checkout");
+        write_and_commit(&mut repo, "modified.txt", "original", "add modified");
+        write_and_commit(&mut repo, "deleted.txt", "original", "add deleted");
+        let head_tree = repo.head().unwrap().peel_to_tree().unwrap();
+
+        let make_dirty = || {
+            fs::write(dir.join("modified.txt"), "local change").unwrap();
+            let _ = fs::remove_file(dir.join("deleted.txt"));
+            fs::write(dir.join("untracked.txt"), "untracked").unwrap();
+        };
+
+        // Safe: 不覆盖本地修改，也不恢复已删除的文件
+        make_dirty();
+        repo.checkout_tree(
+            head_tree.as_object(),
+            Some(&mut build_checkout(CheckoutStrategy::Safe, false, false)),
+        )
+        .unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.join("modified.txt")).unwrap(),
+            "local change"
+        );
+        assert!(!dir.join("deleted.txt").exists());
+        assert!(dir.join("untracked.txt").exists());
+
+        // SafeCreate: 不覆盖本地修改，但会恢复已删除的文件
+        make_dirty();
+        repo.checkout_tree(
+            head_tree.as_object(),
+            Some(&mut build_checkout(
+                CheckoutStrategy::SafeCreate,
+                false,
+                false,
+            )),
+        )
+        .unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.join("modified.txt")).unwrap(),
+            "local change"
+        );
+        assert_eq!(
+            fs::read_to_string(dir.join("deleted.txt")).unwrap(),
+            "original"
+        );
+        assert!(dir.join("untracked.txt").exists());
+
+        // Force 且不移除未跟踪文件：覆盖修改并恢复删除，保留未跟踪文件
+        make_dirty();
+        repo.checkout_tree(
+            head_tree.as_object(),
+            Some(&mut build_checkout(CheckoutStrategy::Force, false, false)),
+        )
+        .unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.join("modified.txt")).unwrap(),
+            "original"
+        );
+        assert_eq!(
+            fs::read_to_string(dir.join("deleted.txt")).unwrap(),
+            "original"
+        );
+        assert!(dir.join("untracked.txt").exists());
+
+        // Force 且移除未跟踪文件
+        make_dirty();
+        repo.checkout_tree(
+            head_tree.as_object(),
+            Some(&mut build_checkout(CheckoutStrategy::Force, true, false)),
+        )
+        .unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.join("modified.txt")).unwrap(),
+            "original"
+        );
+        assert!(!dir.join("untracked.txt").exists());
+
+        // restore 只恢复已跟踪文件，不删除未跟踪文件
+        make_dirty();
+        restore_git_repo_head_to_workdir(&repo).unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.join("modified.txt")).unwrap(),
+            "original"
+        );
+        assert!(dir.join("untracked.txt").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_merge_commit_diffs_against_each_parent() {
+        let (mut repo, dir) = init_test_repo("merge_commit_diffs");
+        let root = write_and_commit(&mut repo, "a.txt", "a", "root");
+        let theirs = write_and_commit(&mut repo, "c.txt", "c", "theirs");
+        reset_git_repo_head(&mut repo, root).unwrap();
+        let ours = write_and_commit(&mut repo, "b.txt", "b", "ours");
+
+        // 合并结果同时包含 b.txt 和 c.txt
+        fs::write(dir.join("c.txt"), "c").unwrap();
+        let (mut index, _) = add_files_to_git_repo_index(&mut repo, vec!["c.txt"]).unwrap();
+        let merge = {
+            let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+            let signature = repo.signature().unwrap();
+            let ours_commit = repo.find_commit(ours).unwrap();
+            let theirs_commit = repo.find_commit(theirs).unwrap();
+            repo.commit(
+                Some("HEAD"),
+                &signature,
+                &signature,
+                "merge",
+                &tree,
+                &[&ours_commit, &theirs_commit],
+            )
+            .unwrap()
+        };
+
+        let diffs = merge_commit_diffs(&repo, merge).unwrap();
+        assert_eq!(diffs.len(), 2);
+        assert_eq!(
+            diffs[0],
+            vec![FileChange {
+                kind: ChangeKind::Added,
+                old_path: None,
+                new_path: Some("c.txt".to_string()),
+            }]
+        );
+        assert_eq!(
+            diffs[1],
+            vec![FileChange {
+                kind: ChangeKind::Added,
+                old_path: None,
+                new_path: Some("b.txt".to_string()),
+            }]
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_reset_git_repo_head_with_mode() {
+        let (mut repo, dir) = init_test_repo("reset_modes");
+        let first = write_and_commit(&mut repo, "a.txt", "v1", "first");
+
+        for mode in [ResetMode::Soft, ResetMode::Mixed, ResetMode::Hard] {
+            write_and_commit(&mut repo, "a.txt", "v2", "second");
+            reset_git_repo_head_with_mode(&mut repo, first, mode).unwrap();
+
+            // HEAD 仍然指向分支，分支被移动到目标 commit
+            {
+                let head = repo.head().unwrap();
+                assert!(head.is_branch());
+                assert_eq!(head.target(), Some(first));
+            }
+
+            let index_entry = repo
+                .index()
+                .unwrap()
+                .get_path(Path::new("a.txt"), 0)
+                .unwrap();
+            let index_content = repo.find_blob(index_entry.id).unwrap().content().to_vec();
+            let workdir_content = fs::read_to_string(dir.join("a.txt")).unwrap();
+            match mode {
+                ResetMode::Soft => {
+                    assert_eq!(index_content, b"v2");
+                    assert_eq!(workdir_content, "v2");
+                }
+                ResetMode::Mixed => {
+                    assert_eq!(index_content, b"v1");
+                    assert_eq!(workdir_content, "v2");
+                }
+                ResetMode::Hard => {
+                    assert_eq!(index_content, b"v1");
+                    assert_eq!(workdir_content, "v1");
+                }
+            }
+            reset_git_repo_head(&mut repo, first).unwrap();
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_reset_git_repo_head_keeps_detached_head_detached() {
+        let (mut repo, dir) = init_test_repo("reset_detached");
+        let first = write_and_commit(&mut repo, "a.txt", "v1", "first");
+        let second = write_and_commit(&mut repo, "a.txt", "v2", "second");
+        repo.set_head_detached(second).unwrap();
+
+        reset_git_repo_head(&mut repo, first).unwrap();
+
+        // 分离状态下只移动 HEAD，原分支不受影响
+        assert!(repo.head_detached().unwrap());
+        assert_eq!(repo.head().unwrap().target(), Some(first));
+        let main = repo.find_branch("main", git2::BranchType::Local).unwrap();
+        assert_eq!(main.get().target(), Some(second));
+        assert_eq!(fs::read_to_string(dir.join("a.txt")).unwrap(), "v1");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_file_snapshots_per_change() {
+        let (mut repo, dir) = init_test_repo("file_snapshots");
+        let first = write_and_commit(&mut repo, "a.txt", "v1", "first");
+        let second = write_and_commit(&mut repo, "a.txt", "v2", "second");
+        write_and_commit(&mut repo, "other.txt", "other", "unrelated");
+        let third = write_and_commit(&mut repo, "a.txt", "v3", "third");
+
+        let snapshots = file_snapshots(&repo, "a.txt", None).unwrap();
+        assert_eq!(
+            snapshots,
+            vec![
+                (third, b"v3".to_vec()),
+                (second, b"v2".to_vec()),
+                (first, b"v1".to_vec()),
+            ]
+        );
+        assert_ne!(snapshots[0].1, snapshots[1].1);
+
+        let limited = file_snapshots(&repo, "a.txt", Some(2)).unwrap();
+        assert_eq!(limited.len(), 2);
+        assert!(
+            file_snapshots(&repo, "missing.txt", None)
+                .unwrap()
+                .is_empty()
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_tree_path_oid_map_nested_files() {
+        let (mut repo, dir) = init_test_repo("tree_path_oid_map");
+        // 与性能测试中的嵌套文件结构相同
+        let nested_files = [
+            "file1.txt",
+            "dir1/file2.txt",
+            "dir1/file3.txt",
+            "dir1/subdir1/file4.txt",
+            "dir2/file5.txt",
+            "dir2/subdir2/file6.txt",
+            "dir2/subdir2/subdir3/file7.txt",
+            "dir3/subdir4/subdir5/subdir6/file8.txt",
+            "dir3/subdir4/subdir5/subdir6/subdir7/file9.txt",
+            "dir4/subdir8/subdir9/subdir10/subdir11/subdir12/file10.txt",
+        ];
+        for file_path in nested_files {
+            let full_path = dir.join(file_path);
+            fs::create_dir_all(full_path.parent().unwrap()).unwrap();
+            fs::write(full_path, file_path).unwrap();
+        }
+        let (index, _) = add_files_to_git_repo_index(&mut repo, nested_files.to_vec()).unwrap();
+        let commit_id = commit_index_to_git_repo(
+            &mut repo,
+            index,
+            "Add nested files",
+            &CommitOptions::default(),
+        )
+        .unwrap();
+        let tree_id = repo.find_commit(commit_id).unwrap().tree_id();
+
+        let path_oids = tree_path_oid_map(&repo, tree_id).unwrap();
+        assert_eq!(path_oids.len(), nested_files.len());
+        for file_path in nested_files {
+            let entry = lookup_entry_from_git_repo_commit_tree_by_path(&repo, None, file_path)
+                .unwrap()
+                .unwrap();
+            assert_eq!(path_oids[file_path], entry.oid);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_safe_display_message_non_utf8() {
+        let (mut repo, dir) = init_test_repo("safe_display_message");
+        let first = write_and_commit(&mut repo, "a.txt", "a", "first");
+        assert_eq!(
+            safe_display_message(&repo.find_commit(first).unwrap()),
+            "first"
+        );
+
+        // 直接写入原始 commit 对象，消息中包含非法的 UTF-8 字节
+        let tree_id = repo.find_commit(first).unwrap().tree_id();
+        let mut raw_commit = format!(
+            "tree {}\nauthor TestUser <test@example.com> 0 +0000\ncommitter TestUser <test@example.com> 0 +0000\n\n",
+            tree_id
+        )
+        .into_bytes();
+        raw_commit.extend_from_slice(b"bad \xff\xfe message\n");
+        let commit_id = repo
+            .odb()
+            .unwrap()
+            .write(git2::ObjectType::Commit, &raw_commit)
+            .unwrap();
+
+        let commit = repo.find_commit(commit_id).unwrap();
+        assert!(commit.message().is_none());
+        let message = safe_display_message(&commit);
+        assert!(message.starts_with("bad "));
+        assert!(message.ends_with(" message"));
+        assert!(message.contains('\u{FFFD}'));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_compare_refs_main_and_feature() {
+        let (mut repo, dir) = init_test_repo("compare_refs");
+        let base = write_and_commit(&mut repo, "a.txt", "a", "base");
+        upsert_branch_to_git_repo(&mut repo, "feature", None).unwrap();
+        write_and_commit(&mut repo, "main.txt", "main", "main only");
+
+        switch_git_repo_branch(&mut repo, "feature", true).unwrap();
+        write_and_commit(&mut repo, "a.txt", "a changed", "feature 1");
+        write_and_commit(&mut repo, "feature.txt", "feature", "feature 2");
+
+        let comparison = compare_refs(&repo, "main", "feature").unwrap();
+        assert_eq!(comparison.merge_base, base);
+        assert_eq!(comparison.ahead, 2);
+        assert_eq!(comparison.behind, 1);
+        let changes: Vec<_> = comparison
+            .changes
+            .iter()
+            .map(|change| (change.kind, change.new_path.as_deref().unwrap()))
+            .collect();
+        assert_eq!(
+            changes,
+            vec![
+                (ChangeKind::Modified, "a.txt"),
+                (ChangeKind::Added, "feature.txt"),
+            ]
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_repo_chained_add_commit_tag() {
+        let (repo, dir) = init_test_repo("repo_chained");
+        let mut wrapper: GitRepoManager = Repo::from_repository(repo);
+
+        fs::write(dir.join("a.txt"), "a").unwrap();
+        wrapper
+            .add(vec!["a.txt"])
+            .unwrap()
+            .commit("first")
+            .unwrap()
+            .tag("v1")
+            .unwrap()
+            .branch("release")
+            .unwrap();
+        let first = wrapper.last_commit().unwrap();
+
+        fs::write(dir.join("b.txt"), "b").unwrap();
+        wrapper
+            .add(vec!["b.txt"])
+            .unwrap()
+            .commit("second")
+            .unwrap()
+            .tag_with_message("v2", "release v2")
+            .unwrap();
+        let second = wrapper.last_commit().unwrap();
+        assert_ne!(first, second);
+
+        {
+            let repo = &*wrapper;
+            assert_eq!(repo.head().unwrap().target(), Some(second));
+            let tag_target = repo.revparse_single("v1^{commit}").unwrap().id();
+            assert_eq!(tag_target, first);
+            let tag = repo.revparse_single("v1").unwrap().peel_to_tag().unwrap();
+            assert_eq!(tag.message(), Some("v1"));
+            let tag = repo.revparse_single("v2").unwrap().peel_to_tag().unwrap();
+            assert_eq!(tag.message(), Some("release v2"));
+            assert_eq!(tag.target_id(), second);
+            let release = repo
+                .find_branch("release", git2::BranchType::Local)
+                .unwrap();
+            assert_eq!(release.get().target(), Some(first));
+        }
+
+        wrapper.switch_branch("release", true).unwrap();
+        assert!(!dir.join("b.txt").exists());
+        let repo = wrapper.into_inner();
+        assert_eq!(repo.head().unwrap().shorthand(), Some("release"));
+        drop(repo);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_commit_options_builder_identity_time_and_ref() {
+        let (mut repo, dir) = init_test_repo("commit_options");
+        let first = write_and_commit(&mut repo, "a.txt", "a", "first");
+
+        fs::write(dir.join("b.txt"), "b").unwrap();
+        let (index, _) = add_files_to_git_repo_index(&mut repo, vec!["b.txt"]).unwrap();
+        let options = CommitOptionsBuilder::new()
+            .author("Author", "author@example.com")
+            .time(1_700_000_000, 480)
+            .build();
+        let commit_id = commit_index_to_git_repo(&mut repo, index, "second", &options).unwrap();
+
+        {
+            let commit = repo.find_commit(commit_id).unwrap();
+            assert_eq!(commit.author().name(), Some("Author"));
+            assert_eq!(commit.author().email(), Some("author@example.com"));
+            // 未指定提交者时使用仓库配置的身份
+            assert_eq!(commit.committer().name(), Some("TestUser"));
+            assert_eq!(commit.author().when().seconds(), 1_700_000_000);
+            assert_eq!(commit.committer().when().offset_minutes(), 480);
+            assert_eq!(commit.parent_id(0).unwrap(), first);
+        }
+
+        // 提交到其他分支，HEAD 保持不变，新分支的第一个提交没有父节点
+        let index = repo.index().unwrap();
+        let options = CommitOptionsBuilder::new()
+            .committer("Bot", "bot@example.com")
+            .update_ref(Some("refs/heads/other"))
+            .build();
+        let other_id = commit_index_to_git_repo(&mut repo, index, "other", &options).unwrap();
+        {
+            let other = repo.find_commit(other_id).unwrap();
+            assert_eq!(other.parent_count(), 0);
+            assert_eq!(other.committer().name(), Some("Bot"));
+        }
+        assert_eq!(repo.head().unwrap().target(), Some(commit_id));
+        assert_eq!(
+            repo.find_reference("refs/heads/other").unwrap().target(),
+            Some(other_id)
+        );
+
+        // 不更新任何引用时以当前 HEAD 为父节点，HEAD 保持不变
+        fs::write(dir.join("c.txt"), "c").unwrap();
+        let (index, _) = add_files_to_git_repo_index(&mut repo, vec!["c.txt"]).unwrap();
+        let options = CommitOptionsBuilder::new().update_ref(None).build();
+        let dangling_id = commit_index_to_git_repo(&mut repo, index, "dangling", &options).unwrap();
+        let parent_ids: Vec<git2::Oid> = repo
+            .find_commit(dangling_id)
+            .unwrap()
+            .parent_ids()
+            .collect();
+        assert_eq!(parent_ids, vec![commit_id]);
+        assert_eq!(repo.head().unwrap().target(), Some(commit_id));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_operation_reports_for_config_and_add() {
+        let (mut repo, dir) = init_test_repo("operation_report");
+
+        // init_test_repo 已经配置过 TestUser，这里只有 user.email 变更
+        let report = config_git_repo_user(&mut repo, "TestUser", "other@example.com").unwrap();
+        assert_eq!(
+            report.changed,
+            vec!["user.email = other@example.com".to_string()]
+        );
+        assert_eq!(report.skipped, vec!["user.name = TestUser".to_string()]);
+        let report = config_git_repo_user(&mut repo, "TestUser", "other@example.com").unwrap();
+        assert!(report.is_noop());
+        assert_eq!(report.skipped.len(), 2);
+
+        write_and_commit(&mut repo, "gone.txt", "gone", "add gone");
+        fs::remove_file(dir.join("gone.txt")).unwrap();
+        fs::write(dir.join("new.txt"), "new").unwrap();
+        let (_, report) = add_files_to_git_repo_index_with_retry(
+            &mut repo,
+            vec!["new.txt", "gone.txt", "never.txt"],
+            &IndexWriteRetry::default(),
+        )
+        .unwrap();
+        assert_eq!(report.changed, vec!["new.txt".to_string()]);
+        assert!(report.removed.is_empty());
+        assert_eq!(
+            report.skipped,
+            vec!["gone.txt".to_string(), "never.txt".to_string()]
+        );
+        let new_blob = repo.blob(b"new").unwrap();
+        assert_eq!(report.created_oids, vec![new_blob]);
+        // 磁盘上已删除的文件不会被 add 从 index 中移除
+        assert!(
+            repo.index()
+                .unwrap()
+                .get_path(Path::new("gone.txt"), 0)
+                .is_some()
+        );
+
+        // 再次添加未修改的文件不产生变更
+        let (_, report) = add_files_to_git_repo_index(&mut repo, vec!["new.txt"]).unwrap();
+        assert!(report.is_noop());
+        assert_eq!(report.skipped, vec!["new.txt".to_string()]);
+        assert!(report.created_oids.is_empty());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_config_typed_upsert_skips_unchanged() {
+        let (repo, dir) = init_test_repo("config_typed");
+        let mut config = repo.config().unwrap();
+
+        assert!(config_git_repo_upsert(&mut config, "core.autocrlf", false).unwrap());
+        assert!(!config_git_repo_upsert(&mut config, "core.autocrlf", false).unwrap());
+        assert!(config_git_repo_upsert(&mut config, "core.autocrlf", true).unwrap());
+        assert!(config.get_bool("core.autocrlf").unwrap());
+
+        assert!(config_git_repo_upsert(&mut config, "core.bigFileThreshold", 1_i32).unwrap());
+        assert!(!config_git_repo_kv_i32(&mut config, "core.bigFileThreshold", 1).unwrap());
+        assert!(config_git_repo_upsert(&mut config, "pack.windowMemory", 1_i64 << 40).unwrap());
+        assert!(!config_git_repo_kv_i64(&mut config, "pack.windowMemory", 1 << 40).unwrap());
+        assert_eq!(config.get_i64("pack.windowMemory").unwrap(), 1 << 40);
+
+        let refspecs: &[&str] = &[
+            "+refs/heads/*:refs/remotes/origin/*",
+            "+refs/tags/*:refs/tags/*",
+        ];
+        assert!(config_git_repo_upsert(&mut config, "remote.origin.fetch", refspecs).unwrap());
+        assert!(!config_git_repo_upsert(&mut config, "remote.origin.fetch", refspecs).unwrap());
+        assert!(
+            config_git_repo_kv_multivar(&mut config, "remote.origin.fetch", &refspecs[..1])
+                .unwrap()
+        );
+        let mut values = Vec::new();
+        let mut entries = config.multivar("remote.origin.fetch", None).unwrap();
+        while let Some(entry) = entries.next() {
+            values.push(entry.unwrap().value().unwrap().to_string());
+        }
+        assert_eq!(values, vec![refspecs[0].to_string()]);
+        drop(entries);
+
+        // 其他级别中的同名值不参与比较，local 级别没有值时仍然写入
+        let global_path = dir.join("global.gitconfig");
+        fs::write(
+            &global_path,
+            "[url \"git@example.com:\"]\n\tinsteadOf = example:\n",
+        )
+        .unwrap();
+        let mut config = git2::Config::new().unwrap();
+        config
+            .add_file(&global_path, git2::ConfigLevel::Global, false)
+            .unwrap();
+        config
+            .add_file(&dir.join(".git/config"), git2::ConfigLevel::Local, false)
+            .unwrap();
+        let instead_of: &[&str] = &["example:"];
+        assert!(
+            config_git_repo_kv_multivar(&mut config, "url.git@example.com:.insteadOf", instead_of)
+                .unwrap()
+        );
+        assert!(
+            !config_git_repo_kv_multivar(&mut config, "url.git@example.com:.insteadOf", instead_of)
+                .unwrap()
+        );
+        let local_config = config.open_level(git2::ConfigLevel::Local).unwrap();
+        assert_eq!(
+            local_config
+                .get_string("url.git@example.com:.insteadOf")
+                .unwrap(),
+            "example:"
+        );
+
+        assert!(!config_git_repo_upsert(&mut config, "user.name", "TestUser").unwrap());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_resolve_signature_fallback_chain() {
+        let (mut repo, dir) = init_test_repo("resolve_signature");
+        let env = |key: &str| match key {
+            "GIT_COMMITTER_NAME" => Some("EnvCommitter".to_string()),
+            "GIT_COMMITTER_EMAIL" => Some("env@example.com".to_string()),
+            _ => None,
+        };
+
+        // 显式签名优先
+        let explicit = git2::Signature::now("Explicit", "explicit@example.com").unwrap();
+        let signature =
+            resolve_signature_with_env(&repo, Some(&explicit), SignatureRole::Committer, env)
+                .unwrap();
+        assert_eq!(signature.name(), Some("Explicit"));
+
+        // 其次是对应角色的环境变量
+        let signature =
+            resolve_signature_with_env(&repo, None, SignatureRole::Committer, env).unwrap();
+        assert_eq!(signature.name(), Some("EnvCommitter"));
+        assert_eq!(signature.email(), Some("env@example.com"));
+
+        // 环境变量不完整时使用仓库配置
+        let signature =
+            resolve_signature_with_env(&repo, None, SignatureRole::Author, env).unwrap();
+        assert_eq!(signature.name(), Some("TestUser"));
+
+        // 显式指定 tagger
+        write_and_commit(&mut repo, "a.txt", "a", "first");
+        upsert_tag_to_git_repo_with_signature(&mut repo, "v1", "v1", None, Some(&explicit))
+            .unwrap();
+        let tag_info = read_tag(&repo, "v1").unwrap().unwrap();
+        assert_eq!(tag_info.tagger_name.as_deref(), Some("Explicit"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_resolve_signature_default_identity_without_user_config() {
+        let (repo, dir) = init_test_repo("resolve_signature_default");
+        // local 级别的空值会覆盖 global 中可能存在的 user.name / user.email，
+        // 使 repo.signature() 失败，模拟没有配置身份的 CI 环境
+        {
+            let mut config = repo.config().unwrap();
+            config.set_str("user.name", "").unwrap();
+            config.set_str("user.email", "").unwrap();
+        }
+        assert!(repo.signature().is_err());
+
+        let no_env = |_: &str| None;
+        for role in [SignatureRole::Author, SignatureRole::Committer] {
+            let signature = resolve_signature_with_env(&repo, None, role, no_env).unwrap();
+            assert_eq!(signature.name(), Some(DEFAULT_SIGNATURE_NAME));
+            assert_eq!(signature.email(), Some(DEFAULT_SIGNATURE_EMAIL));
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_add_files_accepts_paths_and_normalizes() {
+        let (mut repo, dir) = init_test_repo("as_ref_path");
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("sub/a.txt"), "a").unwrap();
+        fs::write(dir.join("b.txt"), "b").unwrap();
+
+        let (index, report) = add_files_to_git_repo_index_with_retry(
+            &mut repo,
+            [dir.join("sub").join("a.txt"), PathBuf::from("./b.txt")],
+            &IndexWriteRetry::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            report.changed,
+            vec!["sub/a.txt".to_string(), "b.txt".to_string()]
+        );
+        assert!(index.get_path(Path::new("sub/a.txt"), 0).is_some());
+        assert!(index.get_path(Path::new("b.txt"), 0).is_some());
+
+        assert!(add_files_to_git_repo_index(&mut repo, ["../outside.txt"]).is_err());
+        assert!(add_files_to_git_repo_index(&mut repo, [std::env::temp_dir()]).is_err());
+
+        commit_index_to_git_repo(&mut repo, index, "paths", &CommitOptions::default()).unwrap();
+        let entry =
+            lookup_entry_from_git_repo_commit_tree_by_path(&repo, None, Path::new("./sub/a.txt"))
+                .unwrap()
+                .unwrap();
+        assert_eq!(entry.relative_path, "sub/a.txt");
+        assert_eq!(entry.path, "sub/a.txt");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_add_files_non_utf8_path() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let (mut repo, dir) = init_test_repo("non_utf8_path");
+        let file_name = std::ffi::OsStr::from_bytes(b"caf\xe9.txt");
+        fs::write(dir.join(file_name), "non utf8").unwrap();
+
+        let (index, _) = add_files_to_git_repo_index(&mut repo, [Path::new(file_name)]).unwrap();
+        assert!(index.get_path(Path::new(file_name), 0).is_some());
+        commit_index_to_git_repo(&mut repo, index, "non utf8", &CommitOptions::default()).unwrap();
+        assert!(path_exists_in_commit(&repo, None, file_name).unwrap());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_get_git_repo_status_entries() {
+        let (mut repo, dir) = init_test_repo("status");
+        fs::write(dir.join(".gitignore"), "*.log\n").unwrap();
+        fs::write(dir.join("modified.txt"), "original").unwrap();
+        fs::write(dir.join("deleted.txt"), "deleted").unwrap();
+        fs::write(dir.join("old_name.txt"), "renamed content\n".repeat(10)).unwrap();
+        let (index, _) = add_files_to_git_repo_index(
+            &mut repo,
+            [".gitignore", "modified.txt", "deleted.txt", "old_name.txt"],
+        )
+        .unwrap();
+        commit_index_to_git_repo(&mut repo, index, "base", &CommitOptions::default()).unwrap();
+
+        fs::write(dir.join("modified.txt"), "changed").unwrap();
+        fs::remove_file(dir.join("deleted.txt")).unwrap();
+        fs::rename(dir.join("old_name.txt"), dir.join("new_name.txt")).unwrap();
+        fs::write(dir.join("staged.txt"), "staged").unwrap();
+        fs::write(dir.join("untracked.txt"), "untracked").unwrap();
+        fs::write(dir.join("debug.log"), "ignored").unwrap();
+        remove_files_from_git_repo(&mut repo, ["old_name.txt"], true).unwrap();
+        add_files_to_git_repo_index(&mut repo, ["staged.txt", "new_name.txt"]).unwrap();
+
+        let short_codes = |entries: &[StatusEntry]| -> Vec<(String, String)> {
+            let mut codes: Vec<_> = entries
+                .iter()
+                .map(|entry| (entry.path.clone(), entry.short_code()))
+                .collect();
+            codes.sort();
+            codes
+        };
+
+        let entries = get_git_repo_status(&repo, &GitStatusOptions::default()).unwrap();
+        assert_eq!(
+            short_codes(&entries),
+            vec![
+                ("deleted.txt".to_string(), " D".to_string()),
+                ("modified.txt".to_string(), " M".to_string()),
+                ("new_name.txt".to_string(), "R ".to_string()),
+                ("staged.txt".to_string(), "A ".to_string()),
+                ("untracked.txt".to_string(), "??".to_string()),
+            ]
+        );
+        let renamed = entries
+            .iter()
+            .find(|entry| entry.path == "new_name.txt")
+            .unwrap();
+        assert_eq!(
+            renamed.index_rename,
+            Some(("old_name.txt".to_string(), "new_name.txt".to_string()))
+        );
+
+        let options = GitStatusOptions {
+            include_untracked: false,
+            include_ignored: true,
+            detect_renames: false,
+        };
+        let codes = short_codes(&get_git_repo_status(&repo, &options).unwrap());
+        assert!(codes.contains(&("debug.log".to_string(), "!!".to_string())));
+        assert!(codes.contains(&("old_name.txt".to_string(), "D ".to_string())));
+        assert!(codes.contains(&("new_name.txt".to_string(), "A ".to_string())));
+        assert!(!codes.iter().any(|(path, _)| path == "untracked.txt"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_diff_git_repo_commits_hunks() {
+        let (mut repo, dir) = init_test_repo("diff_commits");
+        fs::write(dir.join("renamed.txt"), "same content\n".repeat(10)).unwrap();
+        fs::write(dir.join("deleted.txt"), "gone\n").unwrap();
+        fs::write(dir.join("modified.txt"), "line1\nline2\nline3\n").unwrap();
+        let paths = ["renamed.txt", "deleted.txt", "modified.txt"];
+        let (index, _) = add_files_to_git_repo_index(&mut repo, paths).unwrap();
+        let old_oid =
+            commit_index_to_git_repo(&mut repo, index, "base", &CommitOptions::default()).unwrap();
+
+        fs::rename(dir.join("renamed.txt"), dir.join("moved.txt")).unwrap();
+        fs::remove_file(dir.join("deleted.txt")).unwrap();
+        fs::write(dir.join("modified.txt"), "line1\nchanged\nline3\n").unwrap();
+        remove_files_from_git_repo(&mut repo, ["renamed.txt", "deleted.txt"], true).unwrap();
+        let (index, _) =
+            add_files_to_git_repo_index(&mut repo, ["moved.txt", "modified.txt"]).unwrap();
+        let new_oid =
+            commit_index_to_git_repo(&mut repo, index, "change", &CommitOptions::default())
+                .unwrap();
+
+        let diffs = diff_git_repo_commits(&repo, old_oid, new_oid).unwrap();
+        let kinds: Vec<_> = diffs
+            .iter()
+            .map(|file_diff| (file_diff.change.kind, file_diff.change.new_path.clone()))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (ChangeKind::Deleted, None),
+                (ChangeKind::Modified, Some("modified.txt".to_string())),
+                (ChangeKind::Renamed, Some("moved.txt".to_string())),
+            ]
+        );
+
+        let deleted = &diffs[0];
+        assert!(deleted.new_oid.is_zero());
+        assert!(!deleted.old_oid.is_zero());
+
+        let modified = &diffs[1];
+        assert!(!modified.is_binary);
+        assert_eq!(modified.hunks.len(), 1);
+        let hunk = &modified.hunks[0];
+        assert_eq!(hunk.header, "@@ -1,3 +1,3 @@");
+        let lines: Vec<_> = hunk
+            .lines
+            .iter()
+            .map(|line| format!("{}{}", line.origin, line.content))
+            .collect();
+        assert_eq!(
+            lines,
+            vec![" line1\n", "-line2\n", "+changed\n", " line3\n"]
+        );
+        assert_eq!(hunk.lines[1].old_lineno, Some(2));
+        assert_eq!(hunk.lines[2].new_lineno, Some(2));
+
+        // 纯重命名没有内容变化，oid 相同且没有 hunk
+        let renamed = &diffs[2];
+        assert_eq!(renamed.old_oid, renamed.new_oid);
+        assert!(renamed.hunks.is_empty());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_diff_git_repo_workdir_to_index() {
+        let (mut repo, dir) = init_test_repo("diff_workdir");
+        write_and_commit(&mut repo, ".gitignore", "*.log\n", "ignore logs");
+        write_and_commit(&mut repo, "tracked.txt", "old\n", "base");
+
+        fs::write(dir.join("tracked.txt"), "new\n").unwrap();
+        fs::write(dir.join("staged.txt"), "staged\n").unwrap();
+        add_files_to_git_repo_index(&mut repo, ["staged.txt"]).unwrap();
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("sub/untracked.txt"), "untracked\n").unwrap();
+        fs::write(dir.join("debug.log"), "ignored\n").unwrap();
+
+        let paths_of = |diffs: &[FileDiff]| -> Vec<(ChangeKind, String)> {
+            diffs
+                .iter()
+                .map(|file_diff| {
+                    (
+                        file_diff.change.kind,
+                        file_diff.change.new_path.clone().unwrap(),
+                    )
+                })
+                .collect()
+        };
+
+        let diffs = diff_git_repo_workdir_to_index(&repo, false).unwrap();
+        assert_eq!(
+            paths_of(&diffs),
+            vec![(ChangeKind::Modified, "tracked.txt".to_string())]
+        );
+        let lines: Vec<_> = diffs[0].hunks[0]
+            .lines
+            .iter()
+            .map(|line| format!("{}{}", line.origin, line.content))
+            .collect();
+        assert_eq!(lines, vec!["-old\n", "+new\n"]);
+
+        let diffs = diff_git_repo_workdir_to_index(&repo, true).unwrap();
+        assert_eq!(
+            paths_of(&diffs),
+            vec![
+                (ChangeKind::Added, "sub/untracked.txt".to_string()),
+                (ChangeKind::Modified, "tracked.txt".to_string()),
+            ]
+        );
+        assert_eq!(diffs[0].hunks[0].lines[0].content, "untracked\n");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_diff_git_repo_index_to_head() {
+        let (mut repo, dir) = init_test_repo("diff_index");
+        fs::write(dir.join("first.txt"), "a\nb\n").unwrap();
+        add_files_to_git_repo_index(&mut repo, ["first.txt"]).unwrap();
+        // HEAD 尚未创建时，暂存的文件都是新增
+        let diffs = diff_git_repo_index_to_head(&repo).unwrap();
+        assert_eq!(diffs.len(), 1);
+        assert_eq!(diffs[0].change.kind, ChangeKind::Added);
+        assert_eq!(diffs[0].line_stats(), (2, 0));
+
+        let index = repo.index().unwrap();
+        commit_index_to_git_repo(&mut repo, index, "base", &CommitOptions::default()).unwrap();
+        assert!(diff_git_repo_index_to_head(&repo).unwrap().is_empty());
+
+        fs::write(dir.join("first.txt"), "a\nc\nd\n").unwrap();
+        fs::write(dir.join("second.txt"), "new\n").unwrap();
+        add_files_to_git_repo_index(&mut repo, ["first.txt"]).unwrap();
+        // 未暂存的 second.txt 不在预览中
+        let summary: Vec<_> = diff_git_repo_index_to_head(&repo)
+            .unwrap()
+            .iter()
+            .map(|file_diff| (file_diff.path().to_string(), file_diff.line_stats()))
+            .collect();
+        assert_eq!(summary, vec![("first.txt".to_string(), (2, 1))]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_apply_patch_to_git_repo() {
+        let (mut repo, dir) = init_test_repo("apply_patch");
+        write_and_commit(&mut repo, "a.txt", "one\ntwo\nthree\n", "base");
+        let patch = "diff --git a/a.txt b/a.txt\n\
+                     --- a/a.txt\n\
+                     +++ b/a.txt\n\
+                     @@ -1,3 +1,3 @@\n\
+                     \x20one\n\
+                     -two\n\
+                     +TWO\n\
+                     \x20three\n";
+
+        // 只应用到 index，工作目录保持不变
+        let result = apply_patch_to_git_repo(&repo, patch, PatchTarget::Index).unwrap();
+        assert_eq!(result.applied, vec!["a.txt".to_string()]);
+        assert!(result.is_clean());
+        assert_eq!(
+            fs::read_to_string(dir.join("a.txt")).unwrap(),
+            "one\ntwo\nthree\n"
+        );
+        let staged = diff_git_repo_index_to_head(&repo).unwrap();
+        assert_eq!(staged[0].line_stats(), (1, 1));
+
+        // 应用到工作目录
+        let result = apply_patch_to_git_repo(&repo, patch, PatchTarget::Workdir).unwrap();
+        assert!(result.is_clean());
+        assert_eq!(
+            fs::read_to_string(dir.join("a.txt")).unwrap(),
+            "one\nTWO\nthree\n"
+        );
+
+        // 再次应用时上下文已不匹配，报告冲突且不修改文件
+        let result = apply_patch_to_git_repo(&repo, patch, PatchTarget::Workdir).unwrap();
+        assert!(!result.is_clean());
+        assert_eq!(result.conflicts, vec!["a.txt".to_string()]);
+        assert!(result.applied.is_empty());
+        assert_eq!(
+            fs::read_to_string(dir.join("a.txt")).unwrap(),
+            "one\nTWO\nthree\n"
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_render_diff_as_unified_text() {
+        let (mut repo, dir) = init_test_repo("unified_diff");
+        let content: String = (1..=10).map(|n| format!("line{}\n", n)).collect();
+        let old_oid = write_and_commit(&mut repo, "a.txt", &content, "base");
+        let new_oid = write_and_commit(
+            &mut repo,
+            "a.txt",
+            &content.replace("line5\n", "line five\n"),
+            "change",
+        );
+
+        let default_diff =
+            build_commit_diff(&repo, old_oid, new_oid, &GitDiffOptions::default()).unwrap();
+        let text = render_diff_as_unified_text(&default_diff).unwrap();
+        drop(default_diff);
+        let lines: Vec<_> = text.lines().collect();
+        assert!(lines[0].starts_with("diff --git a/a.txt b/a.txt"));
+        assert!(lines[1].starts_with("index "));
+        assert_eq!(
+            lines[2..].to_vec(),
+            vec![
+                "--- a/a.txt",
+                "+++ b/a.txt",
+                "@@ -2,7 +2,7 @@ line1",
+                " line2",
+                " line3",
+                " line4",
+                "-line5",
+                "+line five",
+                " line6",
+                " line7",
+                " line8",
+            ]
+        );
+
+        let options = GitDiffOptions {
+            context_lines: 0,
+            ..Default::default()
+        };
+        let diff = build_commit_diff(&repo, old_oid, new_oid, &options).unwrap();
+        let text = render_diff_as_unified_text(&diff).unwrap();
+        assert!(text.ends_with("@@ -5 +5 @@ line4\n-line5\n+line five\n"));
+
+        // 渲染结果可以作为补丁重新应用
+        drop(diff);
+        reset_git_repo_head(&mut repo, old_oid).unwrap();
+        let result = apply_patch_to_git_repo(&repo, &text, PatchTarget::Both).unwrap();
+        assert!(result.is_clean());
+        assert!(
+            diff_git_repo_workdir_to_index(&repo, false)
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(
+            diff_git_repo_index_to_head(&repo).unwrap()[0].line_stats(),
+            (1, 1)
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_diff_stats() {
+        let (mut repo, dir) = init_test_repo("diff_stats");
+        let old_oid = write_and_commit(&mut repo, "a.txt", "one\ntwo\n", "base");
+        fs::write(dir.join("a.txt"), "one\nTWO\nthree\n").unwrap();
+        fs::write(dir.join("long_name.txt"), "x\n".repeat(60)).unwrap();
+        fs::write(dir.join("image.bin"), [0u8, 159, 146, 150]).unwrap();
+        let paths = ["a.txt", "long_name.txt", "image.bin"];
+        let (index, _) = add_files_to_git_repo_index(&mut repo, paths).unwrap();
+        let new_oid =
+            commit_index_to_git_repo(&mut repo, index, "change", &CommitOptions::default())
+                .unwrap();
+
+        let stats = diff_stats(&repo, old_oid, new_oid).unwrap();
+        assert_eq!(stats.files_changed(), 3);
+        assert_eq!(stats.insertions, 62);
+        assert_eq!(stats.deletions, 1);
+        assert_eq!(stats.binary_files, vec!["image.bin".to_string()]);
+        assert!(stats.files.contains(&("a.txt".to_string(), 2, 1)));
+
+        let expected = format!(
+            " a.txt         |  3 +-\n image.bin     | Bin\n long_name.txt | 60 {}\n \
+             3 files changed, 62 insertions(+), 1 deletion(-)\n",
+            "+".repeat(50)
+        );
+        assert_eq!(stats.to_stat_string(), expected);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_word_diff_hunks() {
+        let (mut repo, dir) = init_test_repo("word_diff");
+        let old_oid = write_and_commit(
+            &mut repo,
+            "prose.txt",
+            "The quick brown fox jumps.\n今天天气很好。\n",
+            "base",
+        );
+        let new_oid = write_and_commit(
+            &mut repo,
+            "prose.txt",
+            "The quick red fox leaps high.\n今天天气不好。\n",
+            "edit",
+        );
+
+        let diffs = diff_git_repo_commits(&repo, old_oid, new_oid).unwrap();
+        assert!(diffs[0].hunks[0].word_changes.is_empty());
+
+        let options = GitDiffOptions {
+            word_diff: true,
+            ..Default::default()
+        };
+        let diffs = diff_git_repo_commits_with_options(&repo, old_oid, new_oid, &options).unwrap();
+        let hunk = &diffs[0].hunks[0];
+        let changes: Vec<_> = hunk
+            .word_changes
+            .iter()
+            .map(|change| (change.old_text.as_str(), change.new_text.as_str()))
+            .collect();
+        assert_eq!(
+            changes,
+            vec![("brown", "red"), ("jumps", "leaps high"), ("很", "不")]
+        );
+
+        let old_text = hunk.old_text();
+        let new_text = hunk.new_text();
+        for change in &hunk.word_changes {
+            assert_eq!(old_text[change.old_range.clone()], change.old_text);
+            assert_eq!(new_text[change.new_range.clone()], change.new_text);
+        }
+        assert_eq!(
+            hunk.word_diff_text(),
+            "The quick [-brown-]{+red+} fox [-jumps-]{+leaps high+}.\n今天天气[-很-]{+不+}好。\n"
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_word_diff_hunk_falls_back_to_lines_for_large_hunks() {
+        let line = |origin: char, content: String| DiffLine {
+            origin,
+            old_lineno: None,
+            new_lineno: None,
+            content,
+        };
+        // 1500 行、每行 9 个单词，单词级 LCS 表远超上限，按行比较仍在上限内
+        let mut lines: Vec<DiffLine> = (0..1500)
+            .map(|i| line(' ', format!("line {} a b c\n", i)))
+            .collect();
+        lines.insert(700, line('-', "old words here\n".to_string()));
+        lines.insert(701, line('+', "new words here\n".to_string()));
+        let hunk = DiffHunk {
+            header: String::new(),
+            old_start: 1,
+            old_lines: 1501,
+            new_start: 1,
+            new_lines: 1501,
+            lines,
+            word_changes: Vec::new(),
+        };
+
+        let changes = word_diff_hunk(&hunk);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].old_text, "old words here\n");
+        assert_eq!(changes[0].new_text, "new words here\n");
+    }
+
+    #[test]
+    fn test_diff_binary_and_size_limited_files() {
+        let (mut repo, dir) = init_test_repo("diff_large");
+        let old_oid = write_and_commit(&mut repo, "small.txt", "a\n", "base");
+        fs::write(dir.join("small.txt"), "b\n").unwrap();
+        fs::write(dir.join("large.txt"), "line\n".repeat(200)).unwrap();
+        fs::write(dir.join("image.bin"), [0u8, 1, 2, 3, 0, 255]).unwrap();
+        let paths = ["small.txt", "large.txt", "image.bin"];
+        let (index, _) = add_files_to_git_repo_index(&mut repo, paths).unwrap();
+        let new_oid =
+            commit_index_to_git_repo(&mut repo, index, "change", &CommitOptions::default())
+                .unwrap();
+
+        let options = GitDiffOptions {
+            max_file_size: Some(100),
+            ..Default::default()
+        };
+        let diffs = diff_git_repo_commits_with_options(&repo, old_oid, new_oid, &options).unwrap();
+        let summary: Vec<_> = diffs
+            .iter()
+            .map(|file_diff| {
+                (
+                    file_diff.path(),
+                    file_diff.is_binary,
+                    file_diff.is_too_large,
+                    file_diff.old_size,
+                    file_diff.new_size,
+                    file_diff.hunks.len(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("image.bin", true, false, 0, 6, 0),
+                ("large.txt", true, true, 0, 1000, 0),
+                ("small.txt", false, false, 2, 2, 1),
+            ]
+        );
+
+        // 不限制大小时 large.txt 正常比较
+        let diffs = diff_git_repo_commits(&repo, old_oid, new_oid).unwrap();
+        assert!(!diffs[1].is_binary);
+        assert_eq!(diffs[1].line_stats(), (200, 0));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_log_git_repo_commits_sorting() {
+        let (mut repo, dir) = init_test_repo("log_sorting");
+        // 子提交的时间早于父提交，用于区分时间排序和拓扑排序
+        let mut commit_at = |path: &str, message: &str, seconds: i64| {
+            fs::write(dir.join(path), message).unwrap();
+            let (index, _) = add_files_to_git_repo_index(&mut repo, [path]).unwrap();
+            let options = CommitOptionsBuilder::new()
+                .author("Alice", "alice@example.com")
+                .time(seconds, 480)
+                .build();
+            commit_index_to_git_repo(&mut repo, index, message, &options).unwrap()
+        };
+        let first = commit_at("a.txt", "first\n\nbody", 300);
+        let second = commit_at("b.txt", "second", 100);
+        let third = commit_at("c.txt", "third", 200);
+
+        let ids = |entries: Vec<CommitInfo>| {
+            entries
+                .iter()
+                .map(|entry| entry.id.parse::<git2::Oid>().unwrap())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            ids(log_git_repo_commits(&repo, None, None, LogSort::Topological).unwrap()),
+            vec![third, second, first]
+        );
+        assert_eq!(
+            ids(log_git_repo_commits(&repo, None, None, LogSort::Time).unwrap()),
+            vec![first, third, second]
+        );
+        assert_eq!(
+            ids(log_git_repo_commits(&repo, None, None, LogSort::Reverse).unwrap()),
+            vec![first, second, third]
+        );
+        assert_eq!(
+            ids(
+                log_git_repo_commits(&repo, Some(second), Some(1), LogSort::TopologicalTime)
+                    .unwrap()
+            ),
+            vec![second]
+        );
+
+        let entries = log_git_repo_commits(&repo, None, None, LogSort::Reverse).unwrap();
+        let first_entry = &entries[0];
+        assert_eq!(first_entry.author.name, "Alice");
+        assert_eq!(first_entry.author.email, "alice@example.com");
+        assert_eq!(first_entry.committer.name, "TestUser");
+        assert_eq!(first_entry.committer.time, 300);
+        assert_eq!(first_entry.message, "first\n\nbody");
+        assert!(first_entry.parent_ids.is_empty());
+        assert_eq!(entries[1].parent_ids, vec![first.to_string()]);
+        assert!(first_entry.oneline().ends_with(" first"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_blame_git_repo_file() {
+        let (mut repo, dir) = init_test_repo("blame");
+        let mut commit_as = |content: &str, author: &str, seconds: i64| {
+            fs::write(dir.join("poem.txt"), content).unwrap();
+            let (index, _) = add_files_to_git_repo_index(&mut repo, ["poem.txt"]).unwrap();
+            let options = CommitOptionsBuilder::new()
+                .author(author, &format!("{}@example.com", author.to_lowercase()))
+                .time(seconds, 0)
+                .build();
+            commit_index_to_git_repo(&mut repo, index, author, &options).unwrap()
+        };
+        let first = commit_as("one\ntwo\nthree\nfour\n", "Alice", 100);
+        let second = commit_as("one\nTWO\nthree\nfour\nfive\n", "Bob", 200);
+
+        let summary = |hunks: Vec<BlameHunkInfo>| -> Vec<(git2::Oid, usize, usize, String)> {
+            hunks
+                .into_iter()
+                .map(|hunk| {
+                    (
+                        hunk.commit_id,
+                        hunk.start_line,
+                        hunk.lines,
+                        hunk.author_name,
+                    )
+                })
+                .collect()
+        };
+        let hunks = blame_git_repo_file(&repo, "poem.txt", None, None).unwrap();
+        assert_eq!(hunks[0].author_email, "alice@example.com");
+        assert_eq!(hunks[0].time, 100);
+        assert_eq!(hunks[0].orig_path.as_deref(), Some("poem.txt"));
+        assert_eq!(
+            summary(hunks),
+            vec![
+                (first, 1, 1, "Alice".to_string()),
+                (second, 2, 1, "Bob".to_string()),
+                (first, 3, 2, "Alice".to_string()),
+                (second, 5, 1, "Bob".to_string()),
+            ]
+        );
+
+        // 在第一个 commit 的版本上 blame，所有行都属于 Alice
+        let hunks = blame_git_repo_file(&repo, "poem.txt", Some(first), None).unwrap();
+        assert_eq!(summary(hunks), vec![(first, 1, 4, "Alice".to_string())]);
+
+        // 只 blame 第 2 行，"one\n" 占 4 个字节，字节 4..7 为 "TWO"
+        let by_lines = blame_git_repo_file(&repo, "poem.txt", None, Some(BlameRange::Lines(2, 2)));
+        let by_bytes = blame_git_repo_file(&repo, "poem.txt", None, Some(BlameRange::Bytes(4..7)));
+        let expected = vec![(second, 2, 1, "Bob".to_string())];
+        assert_eq!(summary(by_lines.unwrap()), expected);
+        assert_eq!(summary(by_bytes.unwrap()), expected);
+        let error = blame_git_repo_file(&repo, "poem.txt", None, Some(BlameRange::Bytes(0..999)))
+            .unwrap_err();
+        assert!(error.to_string().contains("超出文件大小"));
+        let error = blame_git_repo_file(&repo, "poem.txt", None, Some(BlameRange::Bytes(5..5)))
+            .unwrap_err();
+        assert!(error.to_string().contains("无效"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_merge_base_and_is_descendant_of() {
+        let (mut repo, dir) = init_test_repo("ancestry");
+        let base_id = write_and_commit(&mut repo, "a.txt", "a", "base");
+        upsert_branch_to_git_repo(&mut repo, "feature", None).unwrap();
+        switch_git_repo_branch(&mut repo, "feature", true).unwrap();
+        let feature_id = write_and_commit(&mut repo, "feature.txt", "feature", "feature");
+        switch_git_repo_branch(&mut repo, "main", true).unwrap();
+        let main_id = write_and_commit(&mut repo, "b.txt", "b", "main");
+
+        assert_eq!(
+            merge_base(&repo, feature_id, main_id).unwrap(),
+            Some(base_id)
+        );
+        assert_eq!(merge_base(&repo, base_id, main_id).unwrap(), Some(base_id));
+        assert!(is_descendant_of(&repo, main_id, base_id).unwrap());
+        assert!(!is_descendant_of(&repo, base_id, main_id).unwrap());
+        assert!(!is_descendant_of(&repo, main_id, feature_id).unwrap());
+        assert!(!is_descendant_of(&repo, main_id, main_id).unwrap());
+
+        // 没有共同历史的孤立提交
+        let tree = repo.find_commit(base_id).unwrap().tree().unwrap();
+        let signature = repo.signature().unwrap();
+        let orphan_id = repo
+            .commit(None, &signature, &signature, "orphan", &tree, &[])
+            .unwrap();
+        assert_eq!(merge_base(&repo, orphan_id, main_id).unwrap(), None);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_resolve_git_revspec() {
+        let (mut repo, dir) = init_test_repo("revspec");
+        let first = write_and_commit(&mut repo, "a.txt", "1", "first");
+        upsert_tag_to_git_repo(&mut repo, "v1.0", "release", None).unwrap();
+        let second = write_and_commit(&mut repo, "a.txt", "2", "second");
+        let third = write_and_commit(&mut repo, "a.txt", "3", "third");
+
+        let commit = git2::ObjectType::Commit;
+        assert_eq!(resolve_git_revspec(&repo, "HEAD").unwrap(), (third, commit));
+        assert_eq!(
+            resolve_git_revspec(&repo, "HEAD~2").unwrap(),
+            (first, commit)
+        );
+        assert_eq!(
+            resolve_git_revspec(&repo, "main@{1}").unwrap(),
+            (second, commit)
+        );
+        assert_eq!(
+            resolve_git_revspec(&repo, "v1.0^{commit}").unwrap(),
+            (first, commit)
+        );
+
+        let (tag_oid, tag_kind) = resolve_git_revspec(&repo, "v1.0").unwrap();
+        assert_eq!(tag_kind, git2::ObjectType::Tag);
+        assert_ne!(tag_oid, first);
+        let (_, blob_kind) = resolve_git_revspec(&repo, "HEAD:a.txt").unwrap();
+        assert_eq!(blob_kind, git2::ObjectType::Blob);
+        let (tree_oid, _) = resolve_git_revspec(&repo, "HEAD^{tree}").unwrap();
+        assert_eq!(tree_oid, repo.find_commit(third).unwrap().tree_id());
+
+        assert!(resolve_git_revspec(&repo, "HEAD~10").is_err());
+
+        // 解析结果可直接用于 reset
+        let (target, _) = resolve_git_revspec(&repo, "HEAD^").unwrap();
+        reset_git_repo_head(&mut repo, target).unwrap();
+        assert_eq!(repo.head().unwrap().target(), Some(second));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_shortlog_groups_by_author() {
+        let (mut repo, dir) = init_test_repo("shortlog");
+        let mut commit_as = |path: &str, content: &str, author: &str| {
+            fs::write(dir.join(path), content).unwrap();
+            let (index, _) = add_files_to_git_repo_index(&mut repo, [path]).unwrap();
+            let options = CommitOptionsBuilder::new()
+                .author(author, &format!("{}@example.com", author.to_lowercase()))
+                .build();
+            commit_index_to_git_repo(&mut repo, index, author, &options).unwrap()
+        };
+        commit_as("a.txt", "1\n2\n3\n", "Bob");
+        let second = commit_as("a.txt", "1\ntwo\n3\n", "Alice");
+        commit_as("b.txt", "x\n", "Alice");
+        commit_as("a.txt", "1\n", "Carol");
+
+        let summary = |stats: Vec<AuthorStats>| -> Vec<(String, usize, usize, usize)> {
+            stats
+                .into_iter()
+                .map(|s| (s.name, s.commits, s.insertions, s.deletions))
+                .collect()
+        };
+        let all = shortlog(&repo, "HEAD").unwrap();
+        assert_eq!(all[0].email, "alice@example.com");
+        assert_eq!(
+            summary(all),
+            vec![
+                ("Alice".to_string(), 2, 2, 1),
+                ("Bob".to_string(), 1, 3, 0),
+                ("Carol".to_string(), 1, 0, 2),
+            ]
+        );
+
+        let range = format!("{}..main", second);
+        assert_eq!(
+            summary(shortlog(&repo, &range).unwrap()),
+            vec![
+                ("Alice".to_string(), 1, 1, 0),
+                ("Carol".to_string(), 1, 0, 2)
+            ]
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_read_git_repo_reflog() {
+        let (mut repo, dir) = init_test_repo("reflog");
+        let first = write_and_commit(&mut repo, "a.txt", "1", "first");
+        let second = write_and_commit(&mut repo, "a.txt", "2", "second");
+        upsert_branch_to_git_repo(&mut repo, "feature", None).unwrap();
+        switch_git_repo_branch(&mut repo, "feature", true).unwrap();
+        reset_git_repo_head(&mut repo, first).unwrap();
+
+        let entries = read_git_repo_reflog(&repo, "HEAD").unwrap();
+        let moves: Vec<_> = entries
+            .iter()
+            .map(|entry| (entry.old_oid, entry.new_oid))
+            .collect();
+        // 从新到旧：reset、checkout、第二次提交、第一次提交
+        assert_eq!(
+            moves,
+            vec![
+                (second, first),
+                (second, second),
+                (first, second),
+                (git2::Oid::zero(), first),
+            ]
+        );
+        assert_eq!(
+            entries[0].message.as_deref(),
+            Some(format!("reset HEAD to {}", first).as_str())
+        );
+        assert_eq!(entries[0].committer_name, "TestUser");
+        assert_eq!(entries[0].committer_email, "test@example.com");
+
+        // 分支自己的 reflog 只记录该分支的变化
+        let feature_entries = read_git_repo_reflog(&repo, "refs/heads/feature").unwrap();
+        assert_eq!(feature_entries.len(), 2);
+        assert_eq!(feature_entries[0].new_oid, first);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_cherry_compares_patch_ids() {
+        let (mut repo, dir) = init_test_repo("cherry");
+        write_and_commit(&mut repo, "base.txt", "base", "base");
+        upsert_branch_to_git_repo(&mut repo, "upstream", None).unwrap();
+
+        // 本地分支：一个会被 upstream cherry-pick 的提交和一个独有的提交
+        let picked_local = write_and_commit(&mut repo, "fix.txt", "fix", "fix");
+        let local_only = write_and_commit(&mut repo, "local.txt", "local", "local");
+
+        switch_git_repo_branch(&mut repo, "upstream", true).unwrap();
+        let upstream_only = write_and_commit(&mut repo, "upstream.txt", "upstream", "upstream");
+        // 同样的补丁在不同的父节点上提交，commit id 不同但 patch id 相同
+        let picked_upstream = write_and_commit(&mut repo, "fix.txt", "fix", "fix (picked)");
+
+        let result = cherry(&repo, "main", "upstream").unwrap();
+        assert_eq!(result.local_only, vec![local_only]);
+        assert_eq!(result.upstream_only, vec![upstream_only]);
+        assert_eq!(result.equivalent, vec![(picked_local, picked_upstream)]);
+
+        let reversed = cherry(&repo, "upstream", "main").unwrap();
+        assert_eq!(reversed.local_only, vec![upstream_only]);
+        assert_eq!(reversed.upstream_only, vec![local_only]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_rename_git_repo_branch_keeps_tracking() {
+        let (mut repo, dir) = init_test_repo("rename_branch");
+        let first = write_and_commit(&mut repo, "a.txt", "1", "first");
+        upsert_branch_to_git_repo(&mut repo, "old", None).unwrap();
+        upsert_branch_to_git_repo(&mut repo, "taken", None).unwrap();
+        {
+            let mut config = repo.config().unwrap();
+            config_git_repo_kv_str(&mut config, "branch.old.remote", "origin").unwrap();
+            config_git_repo_kv_str(&mut config, "branch.old.merge", "refs/heads/old").unwrap();
+        }
+        switch_git_repo_branch(&mut repo, "old", true).unwrap();
+
+        let Err(error) = rename_git_repo_branch(&mut repo, "old", "taken", false) else {
+            panic!("新分支已存在时应当返回错误");
+        };
+        assert_eq!(error.to_string(), "分支 taken 已存在");
+        {
+            let renamed = rename_git_repo_branch(&mut repo, "old", "new", false).unwrap();
+            assert_eq!(renamed.name(), Some("refs/heads/new"));
+            assert_eq!(renamed.target(), Some(first));
+        }
+
+        assert!(repo.find_branch("old", git2::BranchType::Local).is_err());
+        assert_eq!(repo.head().unwrap().name(), Some("refs/heads/new"));
+        let config = repo.config().unwrap().snapshot().unwrap();
+        assert_eq!(config.get_str("branch.new.remote").unwrap(), "origin");
+        assert_eq!(
+            config.get_str("branch.new.merge").unwrap(),
+            "refs/heads/old"
+        );
+        assert!(config.get_str("branch.old.remote").is_err());
+        assert!(
+            !read_git_repo_reflog(&repo, "refs/heads/new")
+                .unwrap()
+                .is_empty()
+        );
+
+        // force 时覆盖已存在的分支
+        rename_git_repo_branch(&mut repo, "new", "taken", true).unwrap();
+        assert_eq!(repo.head().unwrap().name(), Some("refs/heads/taken"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_list_git_repo_branches() {
+        let (mut repo, dir) = init_test_repo("list_branches");
+        let first = write_and_commit(&mut repo, "a.txt", "1", "first\n\nbody");
+        upsert_branch_to_git_repo(&mut repo, "feature", None).unwrap();
+        let second = write_and_commit(&mut repo, "a.txt", "2", "second");
+
+        // 模拟一个已 fetch 的远程分支，并让 main 跟踪它
+        repo.remote("origin", "https://example.com/repo.git")
+            .unwrap();
+        repo.reference("refs/remotes/origin/main", first, false, "fetch")
+            .unwrap();
+        repo.find_branch("main", git2::BranchType::Local)
+            .unwrap()
+            .set_upstream(Some("origin/main"))
+            .unwrap();
+
+        let summary = |branches: Vec<BranchInfo>| -> Vec<(String, bool, git2::Oid, String, bool, Option<String>)> {
+            branches
+                .into_iter()
+                .map(|b| (b.name, b.is_remote, b.tip, b.summary, b.is_head, b.upstream))
+                .collect()
+        };
+        assert_eq!(
+            summary(list_git_repo_branches(&repo, None).unwrap()),
+            vec![
+                (
+                    "feature".to_string(),
+                    false,
+                    first,
+                    "first".to_string(),
+                    false,
+                    None
+                ),
+                (
+                    "main".to_string(),
+                    false,
+                    second,
+                    "second".to_string(),
+                    true,
+                    Some("origin/main".to_string())
+                ),
+                (
+                    "origin/main".to_string(),
+                    true,
+                    first,
+                    "first".to_string(),
+                    false,
+                    None
+                ),
+            ]
+        );
+
+        let remote = list_git_repo_branches(&repo, Some(git2::BranchType::Remote)).unwrap();
+        assert_eq!(remote.len(), 1);
+        let local = list_git_repo_branches(&repo, Some(git2::BranchType::Local)).unwrap();
+        assert_eq!(local.len(), 2);
+
+        // 配置了 upstream 但远程分支还没有 fetch 下来时 upstream 为 None
+        {
+            let mut config = repo.config().unwrap();
+            config.set_str("branch.feature.remote", "origin").unwrap();
+            config
+                .set_str("branch.feature.merge", "refs/heads/feature")
+                .unwrap();
+        }
+        let local = list_git_repo_branches(&repo, Some(git2::BranchType::Local)).unwrap();
+        assert_eq!(local[0].name, "feature");
+        assert_eq!(local[0].upstream, None);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_list_git_repo_tags_with_glob() {
+        let (mut repo, dir) = init_test_repo("list_tags");
+        let first = write_and_commit(&mut repo, "a.txt", "1", "first");
+        upsert_tag_to_git_repo(&mut repo, "release-1.0", "first release", None).unwrap();
+        let second = write_and_commit(&mut repo, "a.txt", "2", "second");
+        upsert_tag_to_git_repo(&mut repo, "release-2.0", "second release", None).unwrap();
+        // 轻量标签
+        repo.tag_lightweight(
+            "nightly",
+            repo.find_commit(first).unwrap().as_object(),
+            false,
+        )
+        .unwrap();
+
+        let tags = list_git_repo_tags(&repo, None).unwrap();
+        let names: Vec<_> = tags.iter().map(|tag| tag.name.as_str()).collect();
+        assert_eq!(names, vec!["nightly", "release-1.0", "release-2.0"]);
+        assert!(!tags[0].is_annotated());
+        assert_eq!(tags[0].target, first);
+
+        let releases = list_git_repo_tags(&repo, Some("release-*")).unwrap();
+        assert_eq!(releases.len(), 2);
+        assert_eq!(releases[1].target, second);
+        let annotation = releases[1].annotation.as_ref().unwrap();
+        assert_eq!(annotation.tagger_name.as_deref(), Some("TestUser"));
+        assert_eq!(annotation.message.as_deref(), Some("second release"));
+        assert!(list_git_repo_tags(&repo, Some("v*")).unwrap().is_empty());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_delete_git_repo_tag_reports_reachability() {
+        let (mut repo, dir) = init_test_repo("delete_tag");
+        let commit_id = write_and_commit(&mut repo, "a.txt", "1", "first");
+        let tag_oid = upsert_tag_to_git_repo(&mut repo, "v1", "release", None)
+            .unwrap()
+            .target()
+            .unwrap();
+        // 另一个引用指向同一个 tag 对象
+        repo.reference("refs/tags/v1-alias", tag_oid, false, "alias")
+            .unwrap();
+        repo.tag_lightweight(
+            "light",
+            repo.find_commit(commit_id).unwrap().as_object(),
+            false,
+        )
+        .unwrap();
+
+        let deleted = delete_git_repo_tag(&mut repo, "v1").unwrap();
+        assert_eq!(deleted.target, tag_oid);
+        assert!(deleted.is_annotated);
+        assert!(!deleted.tag_object_unreachable);
+        assert!(repo.find_reference("refs/tags/v1").is_err());
+
+        let deleted = delete_git_repo_tag(&mut repo, "v1-alias").unwrap();
+        assert!(deleted.tag_object_unreachable);
+
+        let deleted = delete_git_repo_tag(&mut repo, "light").unwrap();
+        assert_eq!(deleted.target, commit_id);
+        assert!(!deleted.is_annotated);
+        assert!(!deleted.tag_object_unreachable);
+
+        assert!(delete_git_repo_tag(&mut repo, "missing").is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_set_and_get_branch_upstream() {
+        let (mut repo, dir) = init_test_repo("upstream");
+        let commit_id = write_and_commit(&mut repo, "a.txt", "1", "first");
+        upsert_branch_to_git_repo(&mut repo, "feature", None).unwrap();
+        repo.remote("origin", "https://example.com/repo.git")
+            .unwrap();
+        repo.remote("team/fork", "https://example.com/fork.git")
+            .unwrap();
+
+        assert_eq!(get_upstream(&repo, "feature").unwrap(), None);
+        // 远程分支尚未 fetch 也可以设置
+        set_git_branch_upstream(&mut repo, "feature", Some("origin/feature/x")).unwrap();
+        assert_eq!(
+            get_upstream(&repo, "feature").unwrap().as_deref(),
+            Some("origin/feature/x")
+        );
+        let config = repo.config().unwrap().snapshot().unwrap();
+        assert_eq!(config.get_str("branch.feature.remote").unwrap(), "origin");
+        assert_eq!(
+            config.get_str("branch.feature.merge").unwrap(),
+            "refs/heads/feature/x"
+        );
+
+        set_git_branch_upstream(&mut repo, "main", Some("team/fork/main")).unwrap();
+        repo.reference("refs/remotes/team/fork/main", commit_id, false, "fetch")
+            .unwrap();
+        let branches = list_git_repo_branches(&repo, Some(git2::BranchType::Local)).unwrap();
+        let main = branches
+            .iter()
+            .find(|branch| branch.name == "main")
+            .unwrap();
+        assert_eq!(main.upstream.as_deref(), Some("team/fork/main"));
+
+        assert!(set_git_branch_upstream(&mut repo, "main", Some("unknown/main")).is_err());
+        set_git_branch_upstream(&mut repo, "feature", None).unwrap();
+        assert_eq!(get_upstream(&repo, "feature").unwrap(), None);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_checkout_git_repo_commit_detaches_head() {
+        let (mut repo, dir) = init_test_repo("detached");
+        let first = write_and_commit(&mut repo, "a.txt", "1", "first");
+        write_and_commit(&mut repo, "a.txt", "2", "second");
+
+        checkout_git_repo_commit(&mut repo, first, false).unwrap();
+        assert!(repo.head_detached().unwrap());
+        assert_eq!(repo.head().unwrap().target(), Some(first));
+        assert_eq!(fs::read_to_string(dir.join("a.txt")).unwrap(), "2");
+        // 只更新 HEAD 和索引时，工作目录的内容表现为未暂存的修改
+        let unstaged = diff_git_repo_workdir_to_index(&repo, false).unwrap();
+        assert_eq!(unstaged.len(), 1);
+
+        fs::write(dir.join("untracked.txt"), "untracked").unwrap();
+        checkout_git_repo_commit(&mut repo, first, true).unwrap();
+        assert_eq!(fs::read_to_string(dir.join("a.txt")).unwrap(), "1");
+        assert!(!dir.join("untracked.txt").exists());
+        // main 分支不受影响
+        let main = repo.find_branch("main", git2::BranchType::Local).unwrap();
+        assert_ne!(main.get().target(), Some(first));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_iterate_git_repo_references() {
+        let (mut repo, dir) = init_test_repo("for_each_ref");
+        let commit_id = write_and_commit(&mut repo, "a.txt", "1", "first");
+        let tag_oid = upsert_tag_to_git_repo(&mut repo, "v1", "release", None)
+            .unwrap()
+            .target()
+            .unwrap();
+        repo.tag_lightweight(
+            "light",
+            repo.find_commit(commit_id).unwrap().as_object(),
+            false,
+        )
+        .unwrap();
+        repo.reference_symbolic(
+            "refs/remotes/origin/HEAD",
+            "refs/heads/main",
+            false,
+            "clone",
+        )
+        .unwrap();
+
+        let references = iterate_git_repo_references(&repo, None).unwrap();
+        let names: Vec<_> = references.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "refs/heads/main",
+                "refs/remotes/origin/HEAD",
+                "refs/tags/light",
+                "refs/tags/v1"
+            ]
+        );
+
+        let symbolic = &references[1];
+        assert_eq!(symbolic.ref_type, git2::ReferenceType::Symbolic);
+        assert_eq!(symbolic.symbolic_target.as_deref(), Some("refs/heads/main"));
+        assert_eq!(symbolic.target, Some(commit_id));
+
+        let tags = iterate_git_repo_references(&repo, Some("refs/tags/*")).unwrap();
+        assert_eq!(tags.len(), 2);
+        assert_eq!(tags[0].target, Some(commit_id));
+        assert_eq!(tags[0].peeled, None);
+        assert_eq!(tags[1].ref_type, git2::ReferenceType::Direct);
+        assert_eq!(tags[1].target, Some(tag_oid));
+        assert_eq!(tags[1].peeled, Some(commit_id));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_merge_git_repo_branch_ff_only() {
+        let (mut repo, dir) = init_test_repo("merge_ff_only");
+        write_and_commit(&mut repo, "a.txt", "a", "base");
+        upsert_branch_to_git_repo(&mut repo, "feature", None).unwrap();
+        switch_git_repo_branch(&mut repo, "feature", true).unwrap();
+        let feature_id = write_and_commit(&mut repo, "feature.txt", "feature", "feature");
+        switch_git_repo_branch(&mut repo, "main", true).unwrap();
+
+        let ff_only = MergeOptions { ff_only: true };
+        let outcome = merge_git_repo_branch(&mut repo, "feature", &ff_only).unwrap();
+        assert_eq!(outcome, MergeOutcome::FastForward(feature_id));
+        assert_eq!(repo.head().unwrap().target(), Some(feature_id));
+        assert_eq!(repo.head().unwrap().name(), Some("refs/heads/main"));
+        assert_eq!(
+            fs::read_to_string(dir.join("feature.txt")).unwrap(),
+            "feature"
+        );
+        assert_eq!(
+            merge_git_repo_branch(&mut repo, "feature", &ff_only).unwrap(),
+            MergeOutcome::UpToDate
+        );
+
+        // 历史分叉后 ff_only 返回类型化的错误，且不修改 HEAD
+        let main_id = write_and_commit(&mut repo, "main.txt", "main", "main");
+        switch_git_repo_branch(&mut repo, "feature", true).unwrap();
+        let diverged_id = write_and_commit(&mut repo, "feature2.txt", "feature2", "feature2");
+        switch_git_repo_branch(&mut repo, "main", true).unwrap();
+        let err = merge_git_repo_branch(&mut repo, "feature", &ff_only).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<MergeError>(),
+            Some(&MergeError::NotFastForward {
+                head: main_id,
+                target: diverged_id,
+            })
+        );
+        assert_eq!(repo.head().unwrap().target(), Some(main_id));
+
+        // 不限制快进时创建合并提交
+        let outcome =
+            merge_git_repo_branch(&mut repo, "feature", &MergeOptions::default()).unwrap();
+        let MergeOutcome::Merged(merge_id) = outcome else {
+            panic!("expected merge commit, got {:?}", outcome);
+        };
+        assert_eq!(
+            commit_parents(&repo, merge_id).unwrap(),
+            vec![main_id, diverged_id]
+        );
+        assert!(dir.join("feature2.txt").exists());
+        assert_eq!(repo.state(), git2::RepositoryState::Clean);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_rebase_git_repo_branch_with_conflict() {
+        let (mut repo, dir) = init_test_repo("rebase");
+        write_and_commit(&mut repo, "a.txt", "base\n", "base");
+        upsert_branch_to_git_repo(&mut repo, "feature", None).unwrap();
+        let main_id = write_and_commit(&mut repo, "a.txt", "main\n", "main change");
+
+        switch_git_repo_branch(&mut repo, "feature", true).unwrap();
+        let clean_id = write_and_commit(&mut repo, "b.txt", "feature\n", "feature file");
+        let conflict_id = write_and_commit(&mut repo, "a.txt", "feature\n", "feature change");
+        let last_id = write_and_commit(&mut repo, "c.txt", "last\n", "last");
+
+        // 先放弃一次，分支和工作目录恢复原状
+        let outcome = rebase_git_repo_branch(&mut repo, "feature", "main").unwrap();
+        assert!(matches!(outcome, RebaseOutcome::Conflicts { step: 1, .. }));
+        abort_rebase_git_repo(&mut repo).unwrap();
+        assert_eq!(repo.head().unwrap().target(), Some(last_id));
+        assert_eq!(fs::read_to_string(dir.join("a.txt")).unwrap(), "feature\n");
+
+        let outcome = rebase_git_repo_branch(&mut repo, "feature", "main").unwrap();
+        assert_eq!(
+            outcome,
+            RebaseOutcome::Conflicts {
+                step: 1,
+                commit: conflict_id,
+                paths: vec!["a.txt".to_string()],
+            }
+        );
+        assert!(continue_rebase_git_repo(&mut repo).is_err());
+
+        fs::write(dir.join("a.txt"), "resolved\n").unwrap();
+        add_files_to_git_repo_index(&mut repo, ["a.txt"]).unwrap();
+        let RebaseOutcome::Completed { head, rewritten } =
+            continue_rebase_git_repo(&mut repo).unwrap()
+        else {
+            panic!("rebase should complete after resolving the conflict");
+        };
+        let originals: Vec<_> = rewritten.iter().map(|(original, _)| *original).collect();
+        assert_eq!(originals, vec![conflict_id, last_id]);
+
+        // 变基后的历史是线性的：last -> feature change -> feature file -> main change
+        assert_eq!(repo.head().unwrap().name(), Some("refs/heads/feature"));
+        let history = log_commits(&repo, Some(head), None, false).unwrap();
+        assert_eq!(history.len(), 5);
+        assert_eq!(history[3], main_id);
+        assert!(!history.contains(&clean_id));
+        assert_eq!(fs::read_to_string(dir.join("a.txt")).unwrap(), "resolved\n");
+        assert_eq!(repo.state(), git2::RepositoryState::Clean);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_cherry_pick_git_repo_commit() {
+        let (mut repo, dir) = init_test_repo("cherry_pick");
+        write_and_commit(&mut repo, "a.txt", "base\n", "base");
+        upsert_branch_to_git_repo(&mut repo, "feature", None).unwrap();
+        switch_git_repo_branch(&mut repo, "feature", true).unwrap();
+        let fix_id = write_and_commit(&mut repo, "fix.txt", "fix\n", "fix bug");
+        let other_id = write_and_commit(&mut repo, "other.txt", "other\n", "other");
+        let conflict_id = write_and_commit(&mut repo, "a.txt", "feature\n", "feature change");
+        switch_git_repo_branch(&mut repo, "main", true).unwrap();
+        let main_id = write_and_commit(&mut repo, "a.txt", "main\n", "main change");
+
+        let outcome =
+            cherry_pick_git_repo_commit(&mut repo, fix_id, &CherryPickOptions::default()).unwrap();
+        let CherryPickOutcome::Committed(picked_id) = outcome else {
+            panic!("expected commit, got {:?}", outcome);
+        };
+        {
+            let picked = repo.find_commit(picked_id).unwrap();
+            assert_eq!(picked.message(), Some("fix bug"));
+            assert_eq!(picked.parent_ids().collect::<Vec<_>>(), vec![main_id]);
+        }
+        assert!(dir.join("fix.txt").exists());
+        assert!(!dir.join("other.txt").exists());
+
+        // 再次挑选同一个 commit 没有变更
+        let outcome =
+            cherry_pick_git_repo_commit(&mut repo, fix_id, &CherryPickOptions::default()).unwrap();
+        assert_eq!(outcome, CherryPickOutcome::Empty);
+
+        let no_commit = CherryPickOptions {
+            commit: false,
+            ..Default::default()
+        };
+        let outcome = cherry_pick_git_repo_commit(&mut repo, other_id, &no_commit).unwrap();
+        assert_eq!(outcome, CherryPickOutcome::Staged);
+        assert_eq!(repo.head().unwrap().target(), Some(picked_id));
+        assert_eq!(diff_git_repo_index_to_head(&repo).unwrap().len(), 1);
+        let index = repo.index().unwrap();
+        commit_index_to_git_repo(&mut repo, index, "other", &CommitOptions::default()).unwrap();
+        repo.cleanup_state().unwrap();
+
+        let outcome =
+            cherry_pick_git_repo_commit(&mut repo, conflict_id, &CherryPickOptions::default())
+                .unwrap();
+        assert_eq!(
+            outcome,
+            CherryPickOutcome::Conflicts(vec!["a.txt".to_string()])
+        );
+        assert_eq!(repo.state(), git2::RepositoryState::CherryPick);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_resolve_conflict_keeps_side_mode() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let (mut repo, dir) = init_test_repo("resolve_conflict_mode");
+        write_and_commit(&mut repo, "run.sh", "base\n", "base");
+        upsert_branch_to_git_repo(&mut repo, "feature", None).unwrap();
+        write_and_commit(&mut repo, "run.sh", "main\n", "main");
+
+        // feature 分支修改内容并加上可执行位
+        switch_git_repo_branch(&mut repo, "feature", true).unwrap();
+        fs::write(dir.join("run.sh"), "feature\n").unwrap();
+        fs::set_permissions(dir.join("run.sh"), fs::Permissions::from_mode(0o755)).unwrap();
+        let (index, _) = add_files_to_git_repo_index(&mut repo, ["run.sh"]).unwrap();
+        commit_index_to_git_repo(&mut repo, index, "feature", &CommitOptions::default()).unwrap();
+        switch_git_repo_branch(&mut repo, "main", true).unwrap();
+
+        let outcome =
+            merge_git_repo_branch(&mut repo, "feature", &MergeOptions::default()).unwrap();
+        assert!(matches!(outcome, MergeOutcome::Conflicts(_)));
+        resolve_conflict(&mut repo, "run.sh", Resolution::Theirs).unwrap();
+
+        let entry = repo
+            .index()
+            .unwrap()
+            .get_path(Path::new("run.sh"), 0)
+            .unwrap();
+        assert_eq!(entry.mode, u32::from(git2::FileMode::BlobExecutable));
+        let mode = fs::metadata(dir.join("run.sh"))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o111, 0o111);
+        assert_eq!(fs::read_to_string(dir.join("run.sh")).unwrap(), "feature\n");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_list_and_resolve_conflicts() {
+        let (mut repo, dir) = init_test_repo("conflicts");
+        fs::write(dir.join("a.txt"), "base\n").unwrap();
+        fs::write(dir.join("b.txt"), "base\n").unwrap();
+        fs::write(dir.join("c.txt"), "base\n").unwrap();
+        let (index, _) =
+            add_files_to_git_repo_index(&mut repo, ["a.txt", "b.txt", "c.txt"]).unwrap();
+        commit_index_to_git_repo(&mut repo, index, "base", &CommitOptions::default()).unwrap();
+        upsert_branch_to_git_repo(&mut repo, "feature", None).unwrap();
+
+        fs::write(dir.join("a.txt"), "main\n").unwrap();
+        fs::write(dir.join("b.txt"), "main\n").unwrap();
+        fs::write(dir.join("c.txt"), "main\n").unwrap();
+        let (index, _) =
+            add_files_to_git_repo_index(&mut repo, ["a.txt", "b.txt", "c.txt"]).unwrap();
+        commit_index_to_git_repo(&mut repo, index, "main", &CommitOptions::default()).unwrap();
+
+        switch_git_repo_branch(&mut repo, "feature", true).unwrap();
+        fs::write(dir.join("a.txt"), "feature\n").unwrap();
+        fs::write(dir.join("b.txt"), "feature\n").unwrap();
+        remove_files_from_git_repo(&mut repo, ["c.txt"], false).unwrap();
+        let (index, _) = add_files_to_git_repo_index(&mut repo, ["a.txt", "b.txt"]).unwrap();
+        commit_index_to_git_repo(&mut repo, index, "feature", &CommitOptions::default()).unwrap();
+        switch_git_repo_branch(&mut repo, "main", true).unwrap();
+
+        let outcome =
+            merge_git_repo_branch(&mut repo, "feature", &MergeOptions::default()).unwrap();
+        assert!(matches!(outcome, MergeOutcome::Conflicts(_)));
+
+        let conflicts = list_git_repo_conflicts(&repo).unwrap();
+        let paths: Vec<_> = conflicts.iter().map(|c| c.path.as_str()).collect();
+        assert_eq!(paths, vec!["a.txt", "b.txt", "c.txt"]);
+        let a = &conflicts[0];
+        let blob_text = |side: &Option<ConflictSide>| {
+            let blob = repo.find_blob(side.as_ref().unwrap().oid).unwrap();
+            String::from_utf8(blob.content().to_vec()).unwrap()
+        };
+        assert_eq!(blob_text(&a.ancestor), "base\n");
+        assert_eq!(blob_text(&a.ours), "main\n");
+        assert_eq!(blob_text(&a.theirs), "feature\n");
+        assert_eq!(a.ours.as_ref().unwrap().mode, 0o100644);
+        // feature 分支删除了 c.txt
+        assert!(conflicts[2].theirs.is_none());
+
+        resolve_conflict(&mut repo, "a.txt", Resolution::Theirs).unwrap();
+        resolve_conflict(
+            &mut repo,
+            "b.txt",
+            Resolution::Content(b"merged\n".to_vec()),
+        )
+        .unwrap();
+        assert!(resolve_conflict(&mut repo, "a.txt", Resolution::Ours).is_err());
+        assert_eq!(list_git_repo_conflicts(&repo).unwrap().len(), 1);
+        resolve_conflict(&mut repo, "c.txt", Resolution::Theirs).unwrap();
+
+        assert!(!repo.index().unwrap().has_conflicts());
+        assert_eq!(fs::read_to_string(dir.join("a.txt")).unwrap(), "feature\n");
+        assert_eq!(fs::read_to_string(dir.join("b.txt")).unwrap(), "merged\n");
+        assert!(!dir.join("c.txt").exists());
+        let staged: Vec<_> = diff_git_repo_index_to_head(&repo)
+            .unwrap()
+            .iter()
+            .map(|file_diff| (file_diff.path().to_string(), file_diff.change.kind))
+            .collect();
+        assert_eq!(
+            staged,
+            vec![
+                ("a.txt".to_string(), ChangeKind::Modified),
+                ("b.txt".to_string(), ChangeKind::Modified),
+                ("c.txt".to_string(), ChangeKind::Deleted),
+            ]
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_merge_git_repo_octopus() {
+        let (mut repo, dir) = init_test_repo("octopus");
+        write_and_commit(&mut repo, "shared.txt", "base\n", "base");
+        let mut tips = Vec::new();
+        for name in ["a", "b", "c"] {
+            upsert_branch_to_git_repo(&mut repo, name, None).unwrap();
+            switch_git_repo_branch(&mut repo, name, true).unwrap();
+            tips.push(write_and_commit(
+                &mut repo,
+                &format!("{}.txt", name),
+                name,
+                name,
+            ));
+            switch_git_repo_branch(&mut repo, "main", true).unwrap();
+        }
+        upsert_branch_to_git_repo(&mut repo, "conflict", None).unwrap();
+        switch_git_repo_branch(&mut repo, "conflict", true).unwrap();
+        write_and_commit(&mut repo, "a.txt", "conflict", "conflict");
+        switch_git_repo_branch(&mut repo, "main", true).unwrap();
+        let main_id = write_and_commit(&mut repo, "main.txt", "main", "main");
+
+        // 冲突时不修改任何内容
+        let err =
+            merge_git_repo_octopus(&mut repo, &["a", "conflict"], |m| m.to_string()).unwrap_err();
+        assert!(err.to_string().contains("a.txt"));
+        assert_eq!(repo.head().unwrap().target(), Some(main_id));
+        assert!(!dir.join("a.txt").exists());
+        assert!(
+            get_git_repo_status(&repo, &GitStatusOptions::default())
+                .unwrap()
+                .is_empty()
+        );
+
+        let merge_id =
+            merge_git_repo_octopus(&mut repo, &["a", "b", "c"], |m| m.to_string()).unwrap();
+        let mut expected_parents = vec![main_id];
+        expected_parents.extend(&tips);
+        assert_eq!(commit_parents(&repo, merge_id).unwrap(), expected_parents);
+        assert_eq!(
+            repo.find_commit(merge_id).unwrap().message(),
+            Some("Merge branches 'a', 'b' and 'c' into main\n")
+        );
+        for name in ["a", "b", "c", "main"] {
+            assert!(dir.join(format!("{}.txt", name)).exists());
+        }
+        assert!(
+            get_git_repo_status(&repo, &GitStatusOptions::default())
+                .unwrap()
+                .is_empty()
+        );
+
+        // 所有分支都已合并时返回错误
+        assert!(merge_git_repo_octopus(&mut repo, &["a", "b"], |m| m.to_string()).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_merge_messages_and_conclude_merge() {
+        assert_eq!(
+            default_merge_message(&["test_branch_1"], "main", &[]),
+            "Merge branch 'test_branch_1' into main\n"
+        );
+        assert_eq!(
+            default_merge_message(
+                &["a", "b"],
+                "dev",
+                &["x.txt".to_string(), "y.txt".to_string()]
+            ),
+            "Merge branches 'a' and 'b' into dev\n\nConflicts:\n\tx.txt\n\ty.txt\n"
+        );
+
+        let (mut repo, dir) = init_test_repo("merge_messages");
+        write_and_commit(&mut repo, "a.txt", "base\n", "base");
+        upsert_branch_to_git_repo(&mut repo, "feature", None).unwrap();
+        upsert_branch_to_git_repo(&mut repo, "clean", None).unwrap();
+        write_and_commit(&mut repo, "a.txt", "main\n", "main");
+        switch_git_repo_branch(&mut repo, "feature", true).unwrap();
+        let feature_id = write_and_commit(&mut repo, "a.txt", "feature\n", "feature");
+        switch_git_repo_branch(&mut repo, "clean", true).unwrap();
+        write_and_commit(&mut repo, "clean.txt", "clean\n", "clean");
+        switch_git_repo_branch(&mut repo, "main", true).unwrap();
+
+        // 通过闭包改写生成的消息
+        let outcome = merge_git_repo_branch_with_message(
+            &mut repo,
+            "clean",
+            &MergeOptions::default(),
+            |message| format!("{}\nReviewed-by: TestUser\n", message),
+        )
+        .unwrap();
+        let MergeOutcome::Merged(merge_id) = outcome else {
+            panic!("expected merge commit, got {:?}", outcome);
+        };
+        assert_eq!(
+            repo.find_commit(merge_id).unwrap().message(),
+            Some("Merge branch 'clean' into main\n\nReviewed-by: TestUser\n")
+        );
+
+        let outcome =
+            merge_git_repo_branch(&mut repo, "feature", &MergeOptions::default()).unwrap();
+        assert_eq!(outcome, MergeOutcome::Conflicts(vec!["a.txt".to_string()]));
+        assert!(conclude_merge_git_repo(&mut repo).is_err());
+
+        resolve_conflict(&mut repo, "a.txt", Resolution::Ours).unwrap();
+        let concluded_id = conclude_merge_git_repo(&mut repo).unwrap();
+        let concluded = repo.find_commit(concluded_id).unwrap();
+        assert_eq!(
+            concluded.message(),
+            Some("Merge branch 'feature' into main\n\nConflicts:\n\ta.txt\n")
+        );
+        assert_eq!(
+            concluded.parent_ids().collect::<Vec<_>>(),
+            vec![merge_id, feature_id]
+        );
+        assert_eq!(repo.state(), git2::RepositoryState::Clean);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_merge_trees_in_memory() {
+        let (mut repo, dir) = init_test_repo("merge_trees_in_memory");
+        let base_id = write_and_commit(&mut repo, "a.txt", "base\n", "base");
+        upsert_branch_to_git_repo(&mut repo, "feature", None).unwrap();
+        let main_id = write_and_commit(&mut repo, "a.txt", "main\n", "main");
+        switch_git_repo_branch(&mut repo, "feature", true).unwrap();
+        let feature_id = write_and_commit(&mut repo, "b.txt", "feature\n", "feature");
+        let conflict_id = write_and_commit(&mut repo, "a.txt", "feature\n", "conflict");
+        switch_git_repo_branch(&mut repo, "main", true).unwrap();
+
+        let outcome = merge_trees_in_memory(&repo, base_id, main_id, feature_id).unwrap();
+        let TreeMergeOutcome::Merged(tree_id) = outcome else {
+            panic!("expected merged tree, got {:?}", outcome);
+        };
+        let tree = repo.find_tree(tree_id).unwrap();
+        let blob_text = |name: &str| {
+            let entry = tree.get_name(name).unwrap();
+            let blob = repo.find_blob(entry.id()).unwrap();
+            String::from_utf8(blob.content().to_vec()).unwrap()
+        };
+        assert_eq!(blob_text("a.txt"), "main\n");
+        assert_eq!(blob_text("b.txt"), "feature\n");
+
+        let outcome = merge_trees_in_memory(&repo, base_id, main_id, conflict_id).unwrap();
+        let TreeMergeOutcome::Conflicts(conflicts) = outcome else {
+            panic!("expected conflicts, got {:?}", outcome);
+        };
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].path, "a.txt");
+        assert!(conflicts[0].ancestor.is_some() && conflicts[0].ours.is_some());
+
+        // index、工作目录和 HEAD 均未被修改
+        assert_eq!(repo.head().unwrap().target(), Some(main_id));
+        assert!(!repo.index().unwrap().has_conflicts());
+        assert!(!repo.workdir().unwrap().join("b.txt").exists());
+        assert!(
+            get_git_repo_status(&repo, &GitStatusOptions::default())
+                .unwrap()
+                .is_empty()
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_bare_repo_support() {
+        let dir = clean_test_dir("bare");
+        let mut repo = open_or_init_bare_git_repo(&dir).unwrap();
+        assert!(repo.is_bare());
+        config_git_repo_user(&mut repo, "TestUser", "test@example.com").unwrap();
+
+        // 需要工作目录的操作返回可区分的错误而不是 panic
+        let Err(error) = add_files_to_git_repo_index(&mut repo, ["a.txt"]) else {
+            panic!("git add should fail on a bare repository");
+        };
+        assert_eq!(
+            error.downcast_ref::<NotSupportedOnBare>(),
+            Some(&NotSupportedOnBare {
+                operation: "git add"
+            })
+        );
+        let error = get_git_repo_status(&repo, &GitStatusOptions::default()).unwrap_err();
+        assert!(error.downcast_ref::<NotSupportedOnBare>().is_some());
+        assert!(
+            restore_git_repo_head_to_workdir(&repo)
+                .unwrap_err()
+                .downcast_ref::<NotSupportedOnBare>()
+                .is_some()
+        );
+
+        // 通过内存中的 index 提交
+        let mut index = git2::Index::new().unwrap();
+        let entry = git2::IndexEntry {
+            ctime: git2::IndexTime::new(0, 0),
+            mtime: git2::IndexTime::new(0, 0),
+            dev: 0,
+            ino: 0,
+            mode: 0o100644,
+            uid: 0,
+            gid: 0,
+            file_size: 5,
+            id: repo.blob(b"bare\n").unwrap(),
+            flags: 0,
+            flags_extended: 0,
+            path: b"a.txt".to_vec(),
+        };
+        index.add(&entry).unwrap();
+        let commit_id =
+            commit_index_to_git_repo(&mut repo, index, "bare commit", &CommitOptions::default())
+                .unwrap();
+        assert_eq!(repo.head().unwrap().target(), Some(commit_id));
+        assert!(path_exists_in_commit(&repo, Some(commit_id), "a.txt").unwrap());
+        upsert_branch_to_git_repo(&mut repo, "feature", None).unwrap();
+        let error = reset_git_repo_head(&mut repo, commit_id).unwrap_err();
+        assert!(error.downcast_ref::<NotSupportedOnBare>().is_some());
+        reset_git_repo_head_with_mode(&mut repo, commit_id, ResetMode::Soft).unwrap();
+        drop(repo);
+
+        // 再次打开时不会把裸仓库当作普通目录删除
+        let repo = open_or_init_git_repo(&dir).unwrap();
+        assert!(repo.is_bare());
+        assert_eq!(repo.head().unwrap().target(), Some(commit_id));
+        let repo = open_or_init_bare_git_repo(&dir).unwrap();
+        assert!(repo.find_branch("feature", git2::BranchType::Local).is_ok());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_add_all_changes_to_index() {
+        let (mut repo, dir) = init_test_repo("add_all_changes");
+        write_and_commit(&mut repo, "keep.txt", "keep\n", "init");
+        write_and_commit(&mut repo, "modify.txt", "old\n", "modify");
+        write_and_commit(&mut repo, "delete.txt", "delete\n", "delete");
+
+        fs::write(dir.join("modify.txt"), "new\n").unwrap();
+        fs::remove_file(dir.join("delete.txt")).unwrap();
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("sub/new.txt"), "new\n").unwrap();
+        fs::write(dir.join(".gitignore"), "*.log\n").unwrap();
+        fs::write(dir.join("debug.log"), "ignored\n").unwrap();
+
+        let (index, report) = add_all_changes_to_index(&mut repo).unwrap();
+        let mut changed = report.changed.clone();
+        changed.sort();
+        assert_eq!(changed, vec![".gitignore", "modify.txt", "sub/new.txt"]);
+        assert_eq!(report.removed, vec!["delete.txt"]);
+        assert_eq!(report.created_oids.len(), 3);
+        assert!(index.get_path(Path::new("delete.txt"), 0).is_none());
+        assert!(index.get_path(Path::new("debug.log"), 0).is_none());
+        assert!(index.get_path(Path::new("keep.txt"), 0).is_some());
+
+        // 全部暂存后工作目录相对 index 没有未暂存的变更
+        assert!(
+            get_git_repo_status(&repo, &GitStatusOptions::default())
+                .unwrap()
+                .iter()
+                .all(|entry| entry.workdir_status == FileStatus::Unmodified)
+        );
+        let (_, report) = add_all_changes_to_index(&mut repo).unwrap();
+        assert!(report.is_noop());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_add_files_skips_ignored_paths() {
+        let (mut repo, dir) = init_test_repo("add_skips_ignored");
+        fs::write(dir.join(".gitignore"), "*.log\nbuild/\n").unwrap();
+        fs::write(dir.join("tracked.log"), "tracked\n").unwrap();
+        // 先强制跟踪一个匹配 .gitignore 的文件
+        add_files_to_git_repo_index(&mut repo, ["tracked.log"]).unwrap();
+
+        fs::create_dir_all(dir.join("build")).unwrap();
+        fs::write(dir.join("build/out.bin"), "out").unwrap();
+        fs::write(dir.join("debug.log"), "debug\n").unwrap();
+        fs::write(dir.join("tracked.log"), "changed\n").unwrap();
+        fs::write(dir.join("main.rs"), "fn main() {}\n").unwrap();
+
+        let paths = ["main.rs", "debug.log", "build/out.bin", "tracked.log"];
+        let (index, report) =
+            add_files_to_git_repo_index_with_options(&mut repo, paths, &AddOptions::default())
+                .unwrap();
+        assert_eq!(report.changed, vec!["main.rs", "tracked.log"]);
+        assert_eq!(report.ignored, vec!["debug.log", "build/out.bin"]);
+        assert!(report.to_string().contains("忽略: debug.log"));
+        assert!(index.get_path(Path::new("debug.log"), 0).is_none());
+        assert!(index.get_path(Path::new("build/out.bin"), 0).is_none());
+
+        // 关闭 skip_ignored 时保持原有行为
+        let (index, report) =
+            add_files_to_git_repo_index_with_retry(&mut repo, paths, &IndexWriteRetry::default())
+                .unwrap();
+        assert!(report.ignored.is_empty());
+        assert!(index.get_path(Path::new("debug.log"), 0).is_some());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_stage_selected_hunks() {
+        let (mut repo, dir) = init_test_repo("stage_hunks");
+        let original: String = (1..=20).map(|i| format!("line {}\n", i)).collect();
+        write_and_commit(&mut repo, "a.txt", &original, "init");
+
+        let modified = original
+            .replace("line 2\n", "line 2 changed\n")
+            .replace("line 18\n", "line 18 changed\n");
+        fs::write(dir.join("a.txt"), &modified).unwrap();
+        let hunks = list_git_repo_unstaged_hunks(&repo, "a.txt").unwrap();
+        assert_eq!(hunks.len(), 2);
+        assert!(hunks[1].new_text().contains("line 18 changed"));
+
+        // 只暂存第二个 hunk
+        let blob_id = stage_git_repo_hunks(&mut repo, "a.txt", &[1]).unwrap();
+        let staged =
+            String::from_utf8(repo.find_blob(blob_id).unwrap().content().to_vec()).unwrap();
+        assert_eq!(staged, original.replace("line 18\n", "line 18 changed\n"));
+        assert_eq!(fs::read_to_string(dir.join("a.txt")).unwrap(), modified);
+        let index = repo.index().unwrap();
+        assert_eq!(index.get_path(Path::new("a.txt"), 0).unwrap().id, blob_id);
+
+        let hunks = list_git_repo_unstaged_hunks(&repo, "a.txt").unwrap();
+        assert_eq!(hunks.len(), 1);
+        assert!(hunks[0].new_text().contains("line 2 changed"));
+        assert!(stage_git_repo_hunks(&mut repo, "a.txt", &[1]).is_err());
+        stage_git_repo_hunks(&mut repo, "a.txt", &[0]).unwrap();
+        assert!(
+            list_git_repo_unstaged_hunks(&repo, "a.txt")
+                .unwrap()
+                .is_empty()
+        );
+
+        fs::write(dir.join("new.txt"), "new\n").unwrap();
+        assert!(list_git_repo_unstaged_hunks(&repo, "new.txt").is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_list_git_repo_index_entries() {
+        let (mut repo, dir) = init_test_repo("index_entries");
+        write_and_commit(&mut repo, "b.txt", "base\n", "base");
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("sub/a.txt"), "hello\n").unwrap();
+        add_files_to_git_repo_index(&mut repo, ["sub/a.txt"]).unwrap();
+
+        let entries = list_git_repo_index_entries(&repo).unwrap();
+        let paths: Vec<_> = entries.iter().map(|entry| entry.path.as_str()).collect();
+        assert_eq!(paths, vec!["b.txt", "sub/a.txt"]);
+        let entry = &entries[1];
+        assert_eq!(entry.oid, repo.blob(b"hello\n").unwrap());
+        assert_eq!(entry.mode, 0o100644);
+        assert_eq!(entry.stage, 0);
+        assert_eq!(entry.size, 6);
+        let modified = fs::metadata(dir.join("sub/a.txt"))
+            .unwrap()
+            .modified()
+            .unwrap();
+        let seconds = modified
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        assert_eq!(entry.mtime_seconds as u64, seconds);
+
+        // 冲突中的文件以 stage 1/2/3 出现
+        upsert_branch_to_git_repo(&mut repo, "feature", None).unwrap();
+        write_and_commit(&mut repo, "b.txt", "main\n", "main");
+        switch_git_repo_branch(&mut repo, "feature", true).unwrap();
+        write_and_commit(&mut repo, "b.txt", "feature\n", "feature");
+        switch_git_repo_branch(&mut repo, "main", true).unwrap();
+        merge_git_repo_branch(&mut repo, "feature", &MergeOptions::default()).unwrap();
+        let stages: Vec<_> = list_git_repo_index_entries(&repo)
+            .unwrap()
+            .into_iter()
+            .filter(|entry| entry.path == "b.txt")
+            .map(|entry| entry.stage)
+            .collect();
+        assert_eq!(stages, vec![1, 2, 3]);
+
+        clean_git_repo_index(&mut repo).unwrap();
+        assert!(list_git_repo_index_entries(&repo).unwrap().is_empty());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_restore_git_repo_path_from_commit() {
+        let (mut repo, dir) = init_test_repo("restore_path");
+        write_and_commit(&mut repo, "other.txt", "other v1\n", "init");
+        let first = write_and_commit(&mut repo, "a.txt", "a v1\n", "a v1");
+        write_and_commit(&mut repo, "a.txt", "a v2\n", "a v2");
+        fs::write(dir.join("other.txt"), "local edit\n").unwrap();
+        fs::write(dir.join("untracked.txt"), "untracked\n").unwrap();
+
+        // 只更新工作目录
+        restore_git_repo_path_from_commit(&mut repo, first, "a.txt", false).unwrap();
+        assert_eq!(fs::read_to_string(dir.join("a.txt")).unwrap(), "a v1\n");
+        let index_id = repo
+            .index()
+            .unwrap()
+            .get_path(Path::new("a.txt"), 0)
+            .unwrap()
+            .id;
+        assert_eq!(index_id, repo.blob(b"a v2\n").unwrap());
+
+        fs::write(dir.join("a.txt"), "scratch\n").unwrap();
+        restore_git_repo_path_from_commit(&mut repo, first, "./a.txt", true).unwrap();
+        assert_eq!(fs::read_to_string(dir.join("a.txt")).unwrap(), "a v1\n");
+        let index_id = repo
+            .index()
+            .unwrap()
+            .get_path(Path::new("a.txt"), 0)
+            .unwrap()
+            .id;
+        assert_eq!(index_id, repo.blob(b"a v1\n").unwrap());
+
+        // 其他文件的本地修改和未跟踪文件保持不变
+        assert_eq!(
+            fs::read_to_string(dir.join("other.txt")).unwrap(),
+            "local edit\n"
+        );
+        assert!(dir.join("untracked.txt").exists());
+        assert!(restore_git_repo_path_from_commit(&mut repo, first, "missing.txt", true).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_file_mode_round_trip() {
+        let (mut repo, dir) = init_test_repo("file_mode");
+        fs::write(dir.join("run.sh"), "#!/bin/sh\necho hi\n").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(dir.join("run.sh"), fs::Permissions::from_mode(0o755)).unwrap();
+        }
+        add_files_to_git_repo_index(&mut repo, ["run.sh"]).unwrap();
+        // 不依赖平台的权限位，显式标记为可执行
+        set_index_entry_mode(&mut repo, "run.sh", git2::FileMode::BlobExecutable).unwrap();
+        let index = repo.index().unwrap();
+        let commit_id =
+            commit_index_to_git_repo(&mut repo, index, "script", &CommitOptions::default())
+                .unwrap();
+        let entry =
+            lookup_entry_from_git_repo_commit_tree_by_path(&repo, Some(commit_id), "run.sh")
+                .unwrap()
+                .unwrap();
+        assert_eq!(entry.filemode, 0o100755);
+        let entries = traverse_git_repo_commit_tree_recorder(&repo, Some(commit_id)).unwrap();
+        assert_eq!(entries[0].filemode, 0o100755);
+
+        // core.filemode 为 false 时再次暂存修改后的文件仍保留可执行位
+        let mut config = repo.config().unwrap();
+        config_git_repo_kv_str(&mut config, "core.filemode", "false").unwrap();
+        fs::write(dir.join("run.sh"), "#!/bin/sh\necho changed\n").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(dir.join("run.sh"), fs::Permissions::from_mode(0o644)).unwrap();
+        }
+        let (index, _) = add_files_to_git_repo_index(&mut repo, ["run.sh"]).unwrap();
+        assert_eq!(
+            index.get_path(Path::new("run.sh"), 0).unwrap().mode,
+            0o100755
+        );
+
+        set_index_entry_mode(&mut repo, "run.sh", git2::FileMode::Blob).unwrap();
+        let index = repo.index().unwrap();
+        assert_eq!(
+            index.get_path(Path::new("run.sh"), 0).unwrap().mode,
+            0o100644
+        );
+        assert!(set_index_entry_mode(&mut repo, "run.sh", git2::FileMode::Link).is_err());
+        assert!(set_index_entry_mode(&mut repo, "missing.sh", git2::FileMode::Blob).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlink_staging_and_checkout() {
+        let (mut repo, dir) = init_test_repo("symlinks");
+        fs::write(dir.join("target.txt"), "target content\n").unwrap();
+        std::os::unix::fs::symlink("target.txt", dir.join("link")).unwrap();
+        std::os::unix::fs::symlink("missing.txt", dir.join("dangling")).unwrap();
+
+        let (index, report) = add_files_to_git_repo_index_with_options(
+            &mut repo,
+            ["target.txt", "link", "dangling"],
+            &AddOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(report.changed, vec!["target.txt", "link", "dangling"]);
+        for path in ["link", "dangling"] {
+            let entry = index.get_path(Path::new(path), 0).unwrap();
+            assert_eq!(entry.mode, 0o120000);
+        }
+        let commit_id =
+            commit_index_to_git_repo(&mut repo, index, "links", &CommitOptions::default()).unwrap();
+        let link = lookup_entry_from_git_repo_commit_tree_by_path(&repo, Some(commit_id), "link")
+            .unwrap()
+            .unwrap();
+        assert!(link.is_symlink());
+        assert_eq!(
+            read_git_repo_blob_content(&repo, link.oid).unwrap(),
+            b"target.txt"
+        );
+        let symlinks: Vec<_> = traverse_git_repo_commit_tree_recorder(&repo, Some(commit_id))
+            .unwrap()
+            .into_iter()
+            .filter(TreeEntry::is_symlink)
+            .map(|entry| entry.relative_path)
+            .collect();
+        assert_eq!(symlinks, vec!["dangling", "link"]);
+
+        // checkout 时重新创建符号链接
+        fs::remove_file(dir.join("link")).unwrap();
+        checkout_git_repo_commit(&mut repo, commit_id, true).unwrap();
+        assert_eq!(
+            fs::read_link(dir.join("link")).unwrap(),
+            Path::new("target.txt")
+        );
+        assert!(materialize_git_repo_symlinks(&repo).unwrap().is_empty());
+
+        // 不支持符号链接时替换为目标文件的副本，status 保持干净
+        let mut config = repo.config().unwrap();
+        config_git_repo_kv_str(&mut config, "core.symlinks", "false").unwrap();
+        let mut repo = git2::Repository::open(&dir).unwrap();
+        fs::remove_file(dir.join("link")).unwrap();
+        fs::remove_file(dir.join("dangling")).unwrap();
+        checkout_git_repo_commit(&mut repo, commit_id, true).unwrap();
+        let link_metadata = fs::symlink_metadata(dir.join("link")).unwrap();
+        assert!(link_metadata.file_type().is_file());
+        assert_eq!(
+            fs::read_to_string(dir.join("link")).unwrap(),
+            "target content\n"
+        );
+        assert_eq!(
+            fs::read_to_string(dir.join("dangling")).unwrap(),
+            "missing.txt"
+        );
+        assert!(
+            get_git_repo_status(&repo, &GitStatusOptions::default())
+                .unwrap()
+                .is_empty()
+        );
+        let (_, report) =
+            add_files_to_git_repo_index_with_options(&mut repo, ["link"], &AddOptions::default())
+                .unwrap();
+        assert_eq!(report.skipped, vec!["link"]);
+        // 修改过的副本不能被静默跳过
+        fs::write(dir.join("link"), "edited copy\n").unwrap();
+        assert!(
+            add_files_to_git_repo_index_with_options(&mut repo, ["link"], &AddOptions::default())
+                .is_err()
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_remove_files_from_git_repo() {
+        let (mut repo, dir) = init_test_repo("git_rm");
+        write_and_commit(&mut repo, "a.txt", "a\n", "a");
+        write_and_commit(&mut repo, "dir/sub/b.txt", "b\n", "b");
+        write_and_commit(&mut repo, "dir/c.txt", "c\n", "c");
+        write_and_commit(&mut repo, "keep/d.txt", "d\n", "d");
+        fs::write(dir.join("dir/untracked.txt"), "untracked\n").unwrap();
+
+        // 有未跟踪的路径时不做任何修改
+        assert!(remove_files_from_git_repo(&mut repo, ["a.txt", "missing.txt"], false).is_err());
+        assert!(dir.join("a.txt").exists());
+        assert_eq!(list_git_repo_index_entries(&repo).unwrap().len(), 4);
+
+        let (index, report) = remove_files_from_git_repo(&mut repo, ["a.txt"], true).unwrap();
+        assert_eq!(report.removed, vec!["a.txt"]);
+        assert!(index.get_path(Path::new("a.txt"), 0).is_none());
+        assert!(dir.join("a.txt").exists());
+
+        let (_, report) =
+            remove_files_from_git_repo(&mut repo, ["dir", "dir/c.txt"], false).unwrap();
+        assert_eq!(report.removed, vec!["dir/c.txt", "dir/sub/b.txt"]);
+        assert!(!dir.join("dir/c.txt").exists());
+        assert!(!dir.join("dir/sub").exists());
+        // 目录中还有未跟踪的文件时保留目录
+        assert!(dir.join("dir/untracked.txt").exists());
+
+        let paths: Vec<_> = list_git_repo_index_entries(&repo)
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.path)
+            .collect();
+        assert_eq!(paths, vec!["keep/d.txt"]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_write_blobs_from_memory() {
+        let (repo, dir) = init_test_repo("write_blobs");
+        let oid = write_blob_to_git_repo(&repo, b"hello\n").unwrap();
+        assert_eq!(oid.to_string(), "ce013625030ba8dba906f756967f9e9ca394464a");
+        assert_eq!(read_git_repo_blob_content(&repo, oid).unwrap(), b"hello\n");
+
+        let contents = [b"a".to_vec(), b"".to_vec(), b"hello\n".to_vec()];
+        let oids = write_blobs_to_git_repo(&repo, &contents).unwrap();
+        assert_eq!(oids.len(), 3);
+        assert_eq!(oids[2], oid);
+        for (oid, content) in oids.iter().zip(&contents) {
+            assert_eq!(&read_git_repo_blob_content(&repo, *oid).unwrap(), content);
+        }
+        // 工作目录中没有生成任何文件
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_commit_tree_snapshot_nested() {
+        let (mut repo, dir) = init_test_repo("tree_snapshot");
+        let head_id = write_and_commit(&mut repo, "a.txt", "a\n", "init");
+
+        let files = vec![
+            ("a.txt", b"a\n".to_vec(), git2::FileMode::Blob),
+            ("dir/b.txt", b"b\n".to_vec(), git2::FileMode::Blob),
+            (
+                "dir/sub/run.sh",
+                b"#!/bin/sh\n".to_vec(),
+                git2::FileMode::BlobExecutable,
+            ),
+        ];
+        let commit_id = commit_tree_snapshot(&repo, files, "snapshot", Some(head_id)).unwrap();
+        let commit = repo.find_commit(commit_id).unwrap();
+        assert_eq!(commit.parent_ids().collect::<Vec<_>>(), vec![head_id]);
+        assert_eq!(commit.message(), Some("snapshot"));
+        // 不更新任何引用
+        assert_eq!(repo.head().unwrap().target(), Some(head_id));
+
+        let entry = lookup_entry_from_git_repo_commit_tree_by_path(
+            &repo,
+            Some(commit_id),
+            "dir/sub/run.sh",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(entry.filemode, i32::from(git2::FileMode::BlobExecutable));
+        assert_eq!(
+            read_git_repo_blob_content(&repo, entry.oid).unwrap(),
+            b"#!/bin/sh\n"
+        );
+
+        // 与经过 index 提交得到的 tree 一致
+        fs::create_dir_all(dir.join("dir")).unwrap();
+        fs::write(dir.join("dir/b.txt"), "b\n").unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("dir/b.txt")).unwrap();
+        let index_tree = index.write_tree().unwrap();
+        let snapshot_id = commit_tree_snapshot(
+            &repo,
+            vec![
+                ("dir/b.txt", "b\n", git2::FileMode::Blob),
+                ("a.txt", "a\n", git2::FileMode::Blob),
+            ],
+            "snapshot",
+            None,
+        )
+        .unwrap();
+        assert_eq!(repo.find_commit(snapshot_id).unwrap().tree_id(), index_tree);
+
+        // 文件与目录同名、非法路径都会报错
+        assert!(
+            commit_tree_snapshot(
+                &repo,
+                vec![
+                    ("x", "1", git2::FileMode::Blob),
+                    ("x/y", "2", git2::FileMode::Blob)
+                ],
+                "conflict",
+                None,
+            )
+            .is_err()
+        );
+        assert!(
+            commit_tree_snapshot(
+                &repo,
+                vec![("../x", "1", git2::FileMode::Blob)],
+                "bad",
+                None
+            )
+            .is_err()
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_get_git_repo_commit_info() {
+        let (mut repo, dir) = init_test_repo("commit_info");
+        let first = write_and_commit(&mut repo, "a.txt", "a", "first");
+        fs::write(dir.join("a.txt"), "b").unwrap();
+        let (index, _) = add_files_to_git_repo_index(&mut repo, vec!["a.txt"]).unwrap();
+        let options = CommitOptionsBuilder::new()
+            .author("Alice", "alice@example.com")
+            .committer("Bob", "bob@example.com")
+            .time(1_700_000_000, 480)
+            .build();
+        let second =
+            commit_index_to_git_repo(&mut repo, index, "second\n\nbody line\n", &options).unwrap();
+
+        let info = get_git_repo_commit_info(&repo, second).unwrap();
+        assert_eq!(info.id, second.to_string());
+        assert_eq!(info.author.name, "Alice");
+        assert_eq!(info.committer.email, "bob@example.com");
+        assert_eq!(info.author.time, 1_700_000_000);
+        assert_eq!(info.committer.offset_minutes, 480);
+        assert_eq!(info.message, "second\n\nbody line\n");
+        assert_eq!(info.summary, "second");
+        assert_eq!(info.parent_ids, vec![first.to_string()]);
+        let tree_id = repo.find_commit(second).unwrap().tree_id();
+        assert_eq!(info.tree_id, tree_id.to_string());
+
+        #[cfg(feature = "serde")]
+        {
+            let json = serde_json::to_value(&info).unwrap();
+            assert_eq!(json["author"]["offset_minutes"], 480);
+            let decoded: CommitInfo = serde_json::from_value(json).unwrap();
+            assert_eq!(decoded, info);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_object_exists_and_type() {
+        let (mut repo, dir) = init_test_repo("object_type");
+        let commit_id = write_and_commit(&mut repo, "a.txt", "a", "init");
+        let commit = repo.find_commit(commit_id).unwrap();
+        let blob_id = commit.tree().unwrap().get_name("a.txt").unwrap().id();
+
+        assert!(object_exists(&repo, commit_id).unwrap());
+        assert_eq!(
+            object_type(&repo, commit_id).unwrap(),
+            Some(git2::ObjectType::Commit)
+        );
+        assert_eq!(
+            object_type(&repo, commit.tree_id()).unwrap(),
+            Some(git2::ObjectType::Tree)
+        );
+        assert_eq!(
+            object_type(&repo, blob_id).unwrap(),
+            Some(git2::ObjectType::Blob)
+        );
+
+        let missing = git2::Oid::from_str("1234567890123456789012345678901234567890").unwrap();
+        assert!(!object_exists(&repo, missing).unwrap());
+        assert_eq!(object_type(&repo, missing).unwrap(), None);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_hash_git_object_without_writing() {
+        let (repo, dir) = init_test_repo("hash_object");
+        let oid = hash_git_object(b"hello\n", git2::ObjectType::Blob).unwrap();
+        assert_eq!(oid.to_string(), "ce013625030ba8dba906f756967f9e9ca394464a");
+        assert!(!object_exists(&repo, oid).unwrap());
+
+        assert_eq!(write_blob_to_git_repo(&repo, b"hello\n").unwrap(), oid);
+        assert!(object_exists(&repo, oid).unwrap());
+
+        // 相同内容按不同类型计算得到不同的 oid
+        let tree_oid = hash_git_object(b"hello\n", git2::ObjectType::Tree).unwrap();
+        assert_ne!(tree_oid, oid);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_read_git_repo_object_raw() {
+        let (mut repo, dir) = init_test_repo("object_raw");
+        let commit_id = write_and_commit(&mut repo, "a.txt", "hello\n", "init");
+        let commit = repo.find_commit(commit_id).unwrap();
+
+        let blob_id = commit.tree().unwrap().get_name("a.txt").unwrap().id();
+        let blob = read_git_repo_object_raw(&repo, blob_id).unwrap();
+        assert_eq!(blob.kind, git2::ObjectType::Blob);
+        assert_eq!(blob.size, 6);
+        assert_eq!(blob.data, b"hello\n");
+
+        let raw_commit = read_git_repo_object_raw(&repo, commit_id).unwrap();
+        assert_eq!(raw_commit.kind, git2::ObjectType::Commit);
+        assert_eq!(raw_commit.size, raw_commit.data.len());
+        let text = String::from_utf8(raw_commit.data).unwrap();
+        assert!(text.starts_with(&format!("tree {}\n", commit.tree_id())));
+        assert!(text.ends_with("\ninit"));
+
+        // tree 负载为 "<mode> <name>\0<20 字节 oid>"
+        let tree = read_git_repo_object_raw(&repo, commit.tree_id()).unwrap();
+        assert_eq!(tree.kind, git2::ObjectType::Tree);
+        let mut expected = b"100644 a.txt\0".to_vec();
+        expected.extend_from_slice(blob_id.as_bytes());
+        assert_eq!(tree.data, expected);
+        drop(commit);
+
+        upsert_tag_to_git_repo(&mut repo, "v1", "release", None).unwrap();
+        let tag_id = repo.refname_to_id("refs/tags/v1").unwrap();
+        let tag = read_git_repo_object_raw(&repo, tag_id).unwrap();
+        assert_eq!(tag.kind, git2::ObjectType::Tag);
+        assert!(String::from_utf8_lossy(&tag.data).contains("tag v1\n"));
+
+        let missing = git2::Oid::from_str("1234567890123456789012345678901234567890").unwrap();
+        assert!(read_git_repo_object_raw(&repo, missing).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_blob_stream_and_probe() {
+        use std::io::Read;
+
+        let (repo, dir) = init_test_repo("blob_stream");
+        let content: Vec<u8> = (0..200_000u32).map(|i| b'a' + (i % 26) as u8).collect();
+        let blob_id = write_blob_to_git_repo(&repo, &content).unwrap();
+
+        let odb = repo.odb().unwrap();
+        let mut stream = open_git_repo_blob_stream(&odb, blob_id).unwrap();
+        assert_eq!(stream.size(), content.len());
+        assert!(matches!(stream, BlobStream::Odb { .. }));
+        let mut chunk = [0u8; 4096];
+        let mut read = Vec::new();
+        loop {
+            let n = stream.read(&mut chunk).unwrap();
+            if n == 0 {
+                break;
+            }
+            assert!(n <= chunk.len());
+            read.extend_from_slice(&chunk[..n]);
+        }
+        assert_eq!(read, content);
+
+        let probe = probe_git_repo_blob(&repo, blob_id).unwrap();
+        assert_eq!(
+            probe,
+            BlobProbe {
+                size: content.len(),
+                is_binary: false
+            }
+        );
+        let binary_id = write_blob_to_git_repo(&repo, b"PNG\0\x01\x02").unwrap();
+        assert!(probe_git_repo_blob(&repo, binary_id).unwrap().is_binary);
+
+        // 非 blob 对象报错
+        let tree_id = repo.treebuilder(None).unwrap().write().unwrap();
+        assert!(open_git_repo_blob_stream(&odb, tree_id).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_build_tree_from_entries_round_trip() {
+        let (repo, dir) = init_test_repo("build_tree");
+        let commit_id = commit_tree_snapshot(
+            &repo,
+            vec![
+                ("a.txt", "a", git2::FileMode::Blob),
+                ("dir/b.txt", "b", git2::FileMode::Blob),
+                ("dir/sub/run.sh", "run", git2::FileMode::BlobExecutable),
+                ("other/c.txt", "c", git2::FileMode::Blob),
+            ],
+            "snapshot",
+            None,
+        )
+        .unwrap();
+        let tree_id = repo.find_commit(commit_id).unwrap().tree_id();
+
+        let entries = traverse_git_repo_commit_tree_recorder(&repo, Some(commit_id)).unwrap();
+        assert!(
+            entries
+                .iter()
+                .any(|entry| entry.path == "dir/sub/run.sh" && entry.relative_path == "run.sh")
+        );
+        assert_eq!(build_tree_from_entries(&repo, &entries).unwrap(), tree_id);
+
+        // 只有目录条目时直接引用已有的子 tree
+        let subtree_entries: Vec<TreeEntry> = entries
+            .iter()
+            .filter(|entry| !entry.path.contains('/'))
+            .cloned()
+            .collect();
+        assert_eq!(subtree_entries.len(), 3);
+        assert_eq!(
+            build_tree_from_entries(&repo, &subtree_entries).unwrap(),
+            tree_id
+        );
+
+        // 目录条目可以放到新的路径下
+        let other = entries.iter().find(|entry| entry.path == "other").unwrap();
+        let moved = TreeEntry {
+            path: "nested/other".to_string(),
+            ..other.clone()
+        };
+        let moved_tree = build_tree_from_entries(&repo, &[moved]).unwrap();
+        let tree = repo.find_tree(moved_tree).unwrap();
+        let entry = tree.get_path(Path::new("nested/other/c.txt")).unwrap();
+        assert_eq!(read_git_repo_blob_content(&repo, entry.id()).unwrap(), b"c");
+
+        // 同一路径同时作为文件和目录时报错
+        let mut conflicting = entries.clone();
+        conflicting.push(TreeEntry {
+            path: "a.txt/x".to_string(),
+            ..other.clone()
+        });
+        assert!(build_tree_from_entries(&repo, &conflicting).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,440 +1,6 @@
-use git2;
 use std::{fs, path::Path};
 
-mod bench;
-
-fn open_or_init_git_repo(dir: &str) -> Result<git2::Repository, Box<dyn std::error::Error>> {
-    let git_dir = Path::new(dir).join(".git");
-    if git_dir.exists() {
-        println!("Git 仓库: {} 已存在，将打开它", dir);
-        let result = git2::Repository::open(dir)?;
-        return Ok(result);
-    }
-    if Path::new(dir).exists() {
-        println!("目录: {} 已存在，但是 .git ，将删除它", dir);
-        std::fs::remove_dir_all(dir)?;
-    }
-    std::fs::create_dir_all(dir)?;
-    let result =
-        git2::Repository::init_opts(dir, git2::RepositoryInitOptions::new().initial_head("main"))?;
-    println!("初始化并打开了 Git 仓库: {}", dir);
-    return Ok(result);
-}
-
-fn config_git_repo_kv_str(
-    config: &mut git2::Config,
-    name: &str,
-    value: &str,
-) -> Result<bool, Box<dyn std::error::Error>> {
-    let mut need_update = false;
-
-    // 先尝试获取现有值，如果不存在则认为需要更新
-    match config.get_string(name) {
-        Ok(old_value) => {
-            if old_value != value {
-                need_update = true;
-            }
-        }
-        Err(_) => {
-            // 配置项不存在，需要设置
-            need_update = true;
-        }
-    }
-
-    if need_update {
-        println!("配置了 {} = {}", name, value);
-        config.set_str(name, value)?;
-    } else {
-        println!("无需配置 {} = {} ，跳过", name, value);
-    }
-    return Ok(need_update);
-}
-
-fn config_git_repo_user(
-    repo: &mut git2::Repository,
-    name: &str,
-    email: &str,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let mut config = repo.config()?;
-    if config_git_repo_kv_str(&mut config, "user.name", name)?
-        || config_git_repo_kv_str(&mut config, "user.email", email)?
-    {
-        println!("用户信息被更新了，写入 .git/config");
-    } else {
-        println!("用户信息无需更新");
-    }
-    Ok(())
-}
-
-fn add_files_to_git_repo_index(
-    repo: &mut git2::Repository,
-    file_relative_paths: Vec<&str>,
-) -> Result<git2::Index, Box<dyn std::error::Error>> {
-    let mut index = repo.index()?;
-    let workdir = repo.workdir().ok_or("仓库没有工作目录")?;
-
-    for file_relative_path in file_relative_paths {
-        let file_path = workdir.join(file_relative_path);
-
-        if file_path.exists() {
-            println!("添加文件到 index: {}", file_relative_path);
-            index.add_path(std::path::Path::new(file_relative_path))?;
-        } else {
-            println!("文件不存在，从 index 中移除: {}", file_relative_path);
-            // 尝试从索引中移除文件，如果文件不在索引中则忽略错误
-            if let Err(_) = index.remove_path(std::path::Path::new(file_relative_path)) {
-                println!("文件 {} 不在索引中，跳过移除操作", file_relative_path);
-            }
-        }
-    }
-    index.write()?;
-    Ok(index)
-}
-
-fn commit_index_to_git_repo(
-    repo: &mut git2::Repository,
-    mut index: git2::Index,
-    message: &str,
-) -> Result<git2::Oid, Box<dyn std::error::Error>> {
-    let tree_id = index.write_tree()?;
-    let tree = repo.find_tree(tree_id)?;
-
-    let signature = repo.signature()?;
-
-    // 获取 HEAD 引用，如果是第一次提交则为 None
-    let parent_commit = match repo.head() {
-        Ok(head) => {
-            let oid = head.target().unwrap();
-            Some(repo.find_commit(oid)?)
-        }
-        Err(_) => None,
-    };
-
-    let parents: Vec<&git2::Commit> = match &parent_commit {
-        Some(commit) => vec![commit],
-        None => vec![],
-    };
-
-    let commit_id = repo.commit(
-        Some("HEAD"),
-        &signature,
-        &signature,
-        message,
-        &tree,
-        &parents,
-    )?;
-
-    Ok(commit_id)
-}
-
-fn upsert_tag_to_git_repo<'a>(
-    repo: &'a mut git2::Repository,
-    tag_name: &str,
-    message: &str,
-    target_oid: Option<git2::Oid>,
-) -> Result<git2::Reference<'a>, Box<dyn std::error::Error>> {
-    let signature = repo.signature()?;
-
-    // 确定标签指向的目标对象，如果没有指定则使用 HEAD
-    let target_commit = match target_oid {
-        Some(oid) => repo.find_commit(oid)?,
-        None => {
-            let head = repo.head()?;
-            let oid = head.target().unwrap();
-            repo.find_commit(oid)?
-        }
-    };
-
-    let target = target_commit.as_object();
-
-    // 检查标签是否已存在
-    let tag_ref_name = format!("refs/tags/{}", tag_name);
-
-    // 如果标签已存在，先删除它
-    if let Ok(_) = repo.find_reference(&tag_ref_name) {
-        println!("标签 {} 已存在，将更新它", tag_name);
-    } else {
-        println!("标签 {} 不存在，将创建它", tag_name);
-    }
-
-    // 创建新的标签
-    let tag_oid = repo.tag(tag_name, &target, &signature, message, true)?;
-
-    println!("upsert 标签: {} -> {}", tag_name, tag_oid);
-
-    // 返回标签引用
-    let tag_ref = repo.find_reference(&tag_ref_name)?;
-
-    Ok(tag_ref)
-}
-
-
-fn upsert_branch_to_git_repo<'a>(
-    repo: &'a mut git2::Repository,
-    branch_name: &str,
-    target_oid: Option<git2::Oid>,
-) -> Result<git2::Reference<'a>, Box<dyn std::error::Error>> {
-    // 确定分支指向的目标 commit，如果没有指定则使用 HEAD
-    let target_commit = match target_oid {
-        Some(oid) => repo.find_commit(oid)?,
-        None => {
-            let head = repo.head()?;
-            let oid = head.target().unwrap();
-            repo.find_commit(oid)?
-        }
-    };
-
-    // 检查分支是否已存在
-    let branch_ref_name = format!("refs/heads/{}", branch_name);
-
-    // 如果分支已存在，先删除它
-    if let Ok(_) = repo.find_branch(branch_name, git2::BranchType::Local) {
-        println!("分支 {} 已存在，将更新它", branch_name);
-    } else {
-        println!("分支 {} 不存在，将创建它", branch_name);
-    }
-
-    // 创建新的分支
-    repo.branch(branch_name, &target_commit, true)?;
-
-    println!("upsert 分支: {} -> {}", branch_name, target_commit.id());
-
-    // 返回分支引用
-    let branch_ref = repo.find_reference(&branch_ref_name)?;
-
-    Ok(branch_ref)
-}
-
-fn switch_git_repo_branch<'a>(
-    repo: &'a mut git2::Repository,
-    branch_name: &str,
-    update_workdir: bool,
-) -> Result<git2::Reference<'a>, Box<dyn std::error::Error>> {
-    // 查找分支引用
-    let branch_ref_name = format!("refs/heads/{}", branch_name);
-    // 检查分支是否存在
-    let branch_ref = repo.find_reference(&branch_ref_name)?;
-
-    // 设置 HEAD 指向目标分支
-    repo.set_head(&branch_ref_name)?;
-
-    // 重置索引到目标 tree
-    let mut index = repo.index()?;
-    let commit_id = branch_ref.resolve()?.target().unwrap();
-    let target_tree = repo.find_commit(commit_id)?.tree()?;
-    index.read_tree(&target_tree)?;
-    index.write()?;
-
-    if update_workdir {
-        // 如果需要更新工作目录，则进行 checkout 操作
-        let head = repo.head()?;
-        let oid = head.target().unwrap();
-        let commit = repo.find_commit(oid)?;
-        let tree = commit.tree()?;
-
-        // 执行 checkout 操作，更新工作目录文件
-        repo.checkout_tree(
-            tree.as_object(),
-            Some(
-                git2::build::CheckoutBuilder::new()
-                    .force() // 强制覆盖工作目录中的文件
-                    .remove_untracked(true), // 移除未跟踪的文件
-            ),
-        )?;
-
-        println!("已切换到分支 {} 并更新工作目录", branch_name);
-    } else {
-        println!("已切换到分支 {} (仅更新 HEAD)", branch_name);
-    }
-
-    // 返回分支引用
-    let updated_branch_ref = repo.find_reference(&branch_ref_name)?;
-    Ok(updated_branch_ref)
-}
-
-fn reset_git_repo_head(
-    repo: &mut git2::Repository,
-    target_commit_oid: git2::Oid,
-) -> Result<(), Box<dyn std::error::Error>> {
-    // 查找目标 commit
-    let target_commit = repo.find_commit(target_commit_oid)?;
-    
-    // 获取目标 commit 的 tree
-    let target_tree = target_commit.tree()?;
-
-    // 获取当前分支引用
-    let head_ref = repo.head()?;
-
-    
-    // 1. 重置 HEAD 到目标 commit
-    match head_ref.kind() {
-        Some(git2::ReferenceType::Symbolic) => {
-            let branch_name = head_ref.name().unwrap();
-            repo
-            .reference(&branch_name, target_commit_oid, true, format!("reset HEAD to {}", target_commit_oid).as_str())?;
-        },
-        Some(git2::ReferenceType::Direct) | None => {
-            repo
-            .set_head_detached(target_commit_oid)?;
-        },
-    }
-
-    // 2. 重置索引到目标 tree
-    let mut index = repo.index()?;
-    index.read_tree(&target_tree)?;
-    index.write()?;
-    
-    // 3. 重置工作目录到目标 tree (hard reset)
-    repo.checkout_tree(
-        target_tree.as_object(),
-        Some(
-            git2::build::CheckoutBuilder::new()
-                .force() // 强制覆盖工作目录中的文件
-                .remove_untracked(true) // 移除未跟踪的文件
-                .remove_ignored(false) // 移除被忽略的文件
-        ),
-    )?;
-    
-    println!("已重置 HEAD、索引和工作目录到 commit: {}", target_commit_oid);
-    
-    Ok(())
-}
-
-fn clean_git_repo_index(
-    repo: &mut git2::Repository,
-) -> Result<git2::Index, Box<dyn std::error::Error>> {
-    // 获取仓库的索引
-    let mut index = repo.index()?;
-    
-    // 清空索引中的所有条目
-    index.clear()?;
-    
-    // 写入索引更改
-    index.write()?;
-    
-    println!("已清空索引中的所有文件");
-    
-    Ok(index)
-}
-
-#[derive(Debug)]
-#[allow(dead_code)]
-struct TreeEntry {
-    relative_path: String,
-    oid: git2::Oid,
-    kind: git2::ObjectType,
-}
-
-fn traverse_git_repo_commit_tree_recorder(
-    repo: &git2::Repository,
-    commit_oid: Option<git2::Oid>,
-) -> Result<Vec<TreeEntry>, Box<dyn std::error::Error>> {
-    let mut recorder = Vec::new();
-
-    // 确定要遍历的 commit，如果没有指定则使用 HEAD
-    let target_commit = match commit_oid {
-        Some(oid) => repo.find_commit(oid)?,
-        None => {
-            let head = repo.head()?;
-            let oid = head.target().unwrap();
-            repo.find_commit(oid)?
-        }
-    };
-
-    // 获取 commit 对应的 tree
-    let tree = target_commit.tree()?;
-
-    // 遍历 tree 中的所有条目
-    tree.walk(git2::TreeWalkMode::PreOrder, |_, entry| {
-        let entry_kind = match entry.kind() {
-            Some(git2::ObjectType::Tree) | Some(git2::ObjectType::Blob) => entry.kind().unwrap(),
-            _ => return git2::TreeWalkResult::Ok,
-        };
-
-        recorder.push(TreeEntry {
-            relative_path: entry.name().unwrap().to_string(),
-            kind: entry_kind,
-            oid: entry.id(),
-        });
-
-        git2::TreeWalkResult::Ok
-    })?;
-
-    Ok(recorder)
-}
-
-fn lookup_entry_from_git_repo_commit_tree_by_path(
-    repo: &git2::Repository,
-    commit_oid: Option<git2::Oid>,
-    target_path: &str,
-) -> Result<Option<TreeEntry>, Box<dyn std::error::Error>> {
-    // 确定要查找的 commit，如果没有指定则使用 HEAD
-    let target_commit = match commit_oid {
-        Some(oid) => repo.find_commit(oid)?,
-        None => {
-            let head = repo.head()?;
-            let oid = head.target().unwrap();
-            repo.find_commit(oid)?
-        }
-    };
-
-    // 获取 commit 对应的 tree
-    let tree = target_commit.tree()?;
-
-    // 使用 get_path 方法查找指定路径的条目
-    match tree.get_path(std::path::Path::new(target_path)) {
-        Ok(tree_entry) => {
-            let entry = TreeEntry {
-                relative_path: target_path.to_string(),
-                oid: tree_entry.id(),
-                kind: tree_entry.kind().unwrap_or(git2::ObjectType::Any),
-            };
-            Ok(Some(entry))
-        }
-        Err(_) => Ok(None), // 路径不存在
-    }
-}
-
-fn read_git_repo_blob_content(
-    repo: &git2::Repository,
-    blob_oid: git2::Oid,
-) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
-    // 根据 OID 查找 blob 对象
-    let blob = repo.find_blob(blob_oid)?;
-
-    // 获取 blob 的内容
-    let content = blob.content().to_vec();
-
-    Ok(content)
-}
-
-fn restore_git_repo_head_to_workdir(
-    repo: &git2::Repository,
-) -> Result<(), Box<dyn std::error::Error>> {
-    // 获取 HEAD 引用
-    let head_ref = repo.head()?;
-    
-    // 获取 HEAD 指向的 commit
-    let head_commit = head_ref.peel_to_commit()?;
-    
-    // 获取 commit 的 tree
-    let head_tree = head_commit.tree()?;
-    
-    // 使用 checkout 将工作目录恢复到 HEAD 状态
-    repo.checkout_tree(
-        head_tree.as_object(),
-        Some(
-            git2::build::CheckoutBuilder::new()
-                .force() // 强制覆盖工作目录中的文件
-                .remove_untracked(true) // 不移除未跟踪的文件
-                .remove_ignored(false) // 不移除被忽略的文件
-        ),
-    )?;
-    
-    println!("已将工作目录恢复到 HEAD 状态");
-    
-    Ok(())
-}
+use git2_rs_demo::*;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     // let test_dir = "/Users/bytedance/Workspace/ide/agent-e2e-cli";
@@ -451,8 +17,11 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // git config user.name "TestUser"
     // git config user.email "test@example.com"
-    config_git_repo_user(&mut repo, "TestUser", "test@example.com")?;
-    config_git_repo_user(&mut repo, "TestUser", "test@example.com")?;
+    let config_report = config_git_repo_user(&mut repo, "TestUser", "test@example.com")?;
+    print!("{}", config_report);
+    // 再次配置相同的用户信息，全部跳过
+    let config_report = config_git_repo_user(&mut repo, "TestUser", "test@example.com")?;
+    print!("{}", config_report);
     println!("✓ 配置了用户信息\n");
 
     // touch test.txt
@@ -467,12 +36,26 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("创建了测试文件: {:?}", file_path);
 
     // git add test.txt
-    let index = add_files_to_git_repo_index(&mut repo, vec![file_relative_path])?;
+    let (index, add_report) = add_files_to_git_repo_index_with_retry(
+        &mut repo,
+        vec![file_relative_path],
+        &IndexWriteRetry::default(),
+    )?;
+    print!("{}", add_report);
     println!("✓ 添加文件到 index\n");
 
     // git commit -m "测试提交的消息"
-    let commit_id1 = commit_index_to_git_repo(&mut repo, index, "测试提交的消息")?;
-    println!("✓ 创建了 commit: {}\n", commit_id1);
+    let commit_id1 = commit_index_to_git_repo(
+        &mut repo,
+        index,
+        "测试提交的消息",
+        &CommitOptions::default(),
+    )?;
+    println!(
+        "✓ 创建了 commit: {} {}\n",
+        commit_id1,
+        safe_display_message(&repo.find_commit(commit_id1)?)
+    );
 
     // git tag test_tag_1 -m "测试创建 tag 消息"
     let tag_name = "test_tag_1";
@@ -481,6 +64,10 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         println!("✓ tag 创建成功: {}\n", tag_ref.name().unwrap_or("unknown"));
     }
 
+    // git cat-file -p test_tag_1
+    let tag_info = read_tag(&repo, tag_name)?;
+    println!("✓ 读取 tag 信息成功: {:?}\n", tag_info);
+
     // 创建分支 （不 checkout）
     // git branch test_branch_1
     let branch_name = "test_branch_1";
@@ -515,34 +102,87 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         std::fs::write(&file_path3, file_content3)?;
     }
     println!("创建了测试文件3: {:?}", file_path3);
-    // rm -rf test.txt
-    // git add test2.txt subdir/test3.txt test.txt
-    fs::remove_file(file_path.clone())?;
+    // git rm test.txt
+    let (_, rm_report) = remove_files_from_git_repo(&mut repo, [file_relative_path], false)?;
+    print!("{}", rm_report);
     println!("删除测试文件1: {:?}", file_path);
-    let index2 = add_files_to_git_repo_index(
+    // git add test2.txt subdir/test3.txt
+    let (index2, add_report2) = add_files_to_git_repo_index_with_retry(
         &mut repo,
-        vec![file_relative_path, file_relative_path2, file_relative_path3],
+        vec![file_relative_path2, file_relative_path3],
+        &IndexWriteRetry::default(),
     )?;
+    print!("{}", add_report2);
     println!("✓ 添加文件 2 文件 3 到 index, 文件 1 从 index 中移除\n");
 
+    // git ls-files --stage
+    for entry in list_git_repo_index_entries(&repo)? {
+        println!(
+            "{:o} {} {}\t{}",
+            entry.mode, entry.oid, entry.stage, entry.path
+        );
+    }
+    println!("✓ 查看 index 条目成功\n");
+
+    // git diff --cached --stat
+    for file_diff in diff_git_repo_index_to_head(&repo)? {
+        let (additions, deletions) = file_diff.line_stats();
+        println!("{} | +{} -{}", file_diff.path(), additions, deletions);
+    }
+    println!("✓ 预览将要提交的变更成功\n");
+
     // git commit -m "测试提交的消息2"
-    let commit_id2 = commit_index_to_git_repo(&mut repo, index2, "测试提交的消息2")?;
-    println!("✓ 创建了 commit2: {}\n", commit_id2);
+    let commit_id2 = commit_index_to_git_repo(
+        &mut repo,
+        index2,
+        "测试提交的消息2",
+        &CommitOptions::default(),
+    )?;
+    println!(
+        "✓ 创建了 commit2: {} {}\n",
+        commit_id2,
+        safe_display_message(&repo.find_commit(commit_id2)?)
+    );
     let commit2_recorder = traverse_git_repo_commit_tree_recorder(&repo, Some(commit_id2))?;
     println!("✓ 遍历 commit2 树成功: {:?}\n", commit2_recorder);
 
+    // git diff --name-status commit1 commit2
+    for change in name_status(&repo, commit_id1, commit_id2, false)? {
+        let paths = match (change.old_path, change.new_path) {
+            (Some(old_path), Some(new_path)) if old_path != new_path => {
+                format!("{}\t{}", old_path, new_path)
+            }
+            (old_path, new_path) => new_path.or(old_path).unwrap_or_default(),
+        };
+        println!("{}\t{}", change.kind.status_char(), paths);
+    }
+    println!("✓ 比较 commit1 与 commit2 的文件变更成功\n");
+
+    // git diff --stat commit1 commit2
+    print!(
+        "{}",
+        diff_stats(&repo, commit_id1, commit_id2)?.to_stat_string()
+    );
+    println!("✓ 统计 commit1 与 commit2 的行数变更成功\n");
+
     let entry = lookup_entry_from_git_repo_commit_tree_by_path(
         &repo,
         Some(commit_id2),
         file_relative_path3,
     )?;
     println!("✓ 从 commit2 树中查找文件 3 成功: {:?}\n", entry);
+
+    // git cat-file -e commit2:test.txt
+    let file1_exists = path_exists_in_commit(&repo, Some(commit_id2), file_relative_path)?;
+    println!("✓ commit2 树中文件 1 是否存在: {}\n", file1_exists);
     let entry = entry.unwrap();
     let blob_content = read_git_repo_blob_content(&repo, entry.oid)?;
     println!(
         "✓ 从 blob 中读取文件 3 内容成功: {:?}\n",
         String::from_utf8_lossy(&blob_content)
     );
+    let blob_lines = blob_lines(&repo, entry.oid)?;
+    println!("✓ 按行读取文件 3 内容成功: {:?}\n", blob_lines);
 
     // git branch test_branch_2
     let branch_name2 = "test_branch_2";
@@ -552,6 +192,33 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         println!("✓ branch 创建成功: {}\n", branch_ref_name2);
     }
 
+    // git for-each-ref --format='%(refname)'
+    let ref_names = list_refs(&repo, None)?;
+    println!("✓ 当前仓库的引用: {:?}\n", ref_names);
+
+    // git branch -vv
+    for branch in list_git_repo_branches(&repo, None)? {
+        let marker = if branch.is_head { '*' } else { ' ' };
+        println!(
+            "{} {} {} {}",
+            marker, branch.name, branch.tip, branch.summary
+        );
+    }
+    println!("✓ 列出分支成功\n");
+
+    // git remote add origin https://example.com/test_repo.git
+    // git remote -v
+    remote::add_git_repo_remote(
+        &mut repo,
+        "origin",
+        "https://example.com/test_repo.git",
+        &[],
+    )?;
+    for remote in remote::list_git_repo_remotes(&repo)? {
+        println!("{}\t{}", remote.name, remote.url.unwrap_or_default());
+    }
+    println!("✓ 配置远程仓库成功\n");
+
     // 切换到 test_branch_1 分支，并切换 workdir。
     // git checkout test_branch_1
     {
@@ -571,19 +238,49 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // 测试 reset hard
     // git reset --hard HEAD^1
-    reset_git_repo_head(&mut repo, commit_id1)?;
-    println!("✓ 已 reset hard 到 commit1: {:?}\n", commit_id1);
+    let (reset_target, _) = resolve_git_revspec(&repo, "HEAD^1")?;
+    reset_git_repo_head(&mut repo, reset_target)?;
+    println!("✓ 已 reset hard 到 commit1: {:?}\n", reset_target);
+
+    // git reflog -n 3
+    for entry in read_git_repo_reflog(&repo, "HEAD")?.iter().take(3) {
+        println!(
+            "{} -> {} {}",
+            entry.old_oid,
+            entry.new_oid,
+            entry.message.as_deref().unwrap_or_default()
+        );
+    }
+    println!("✓ 读取 HEAD 的 reflog 成功\n");
 
     // git rm --cached -r .
     let index3 = clean_git_repo_index(&mut repo)?;
     println!("✓ 已从 index 中移除所有文件\n");
 
+    // git status --short
+    for entry in get_git_repo_status(&repo, &GitStatusOptions::default())? {
+        println!("{} {}", entry.short_code(), entry.path);
+    }
+    println!("✓ 查看仓库状态成功\n");
+
     // git commit -m "清空所有文件"
-    let commit_id3 = commit_index_to_git_repo(&mut repo, index3, "清空所有文件")?;
-    println!("✓ 已创建 commit3: {}\n", commit_id3);
+    let commit_id3 =
+        commit_index_to_git_repo(&mut repo, index3, "清空所有文件", &CommitOptions::default())?;
+    println!(
+        "✓ 已创建 commit3: {} {}\n",
+        commit_id3,
+        safe_display_message(&repo.find_commit(commit_id3)?)
+    );
+
+    // git log --oneline
+    for entry in log_git_repo_commits(&repo, None, None, LogSort::TopologicalTime)? {
+        println!("{}", entry.oneline());
+    }
+    println!("✓ 查看提交历史成功\n");
 
     // git restore .
     restore_git_repo_head_to_workdir(&repo)?;
+    println!("✓ 已将工作目录恢复到 HEAD 状态\n");
 
     Ok(())
 }
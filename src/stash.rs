@@ -0,0 +1,222 @@
+// git stash：在 switch_git_repo_branch / reset_git_repo_head 等强制 checkout 之前暂存工作目录的修改
+use crate::{SignatureRole, index_conflict_paths, require_workdir, resolve_signature};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StashEntry {
+    // stash@{index}，0 为最近一次保存的 stash
+    pub index: usize,
+    pub message: String,
+    pub oid: git2::Oid,
+}
+
+// 保存 stash 时的选项
+#[derive(Debug, Clone, Default)]
+pub struct StashSaveOptions {
+    // 同时暂存未跟踪的文件（git stash -u）
+    pub include_untracked: bool,
+    // 保留已暂存到 index 的修改（git stash --keep-index）
+    pub keep_index: bool,
+}
+
+// git stash push -m <message>，工作目录和 index 没有修改时返回 None
+pub fn save_stash(
+    repo: &mut git2::Repository,
+    message: &str,
+    options: &StashSaveOptions,
+) -> Result<Option<git2::Oid>, Box<dyn std::error::Error>> {
+    require_workdir(repo, "git stash")?;
+    let stasher = resolve_signature(repo, None, SignatureRole::Committer)?;
+    let mut flags = git2::StashFlags::DEFAULT;
+    if options.include_untracked {
+        flags |= git2::StashFlags::INCLUDE_UNTRACKED;
+    }
+    if options.keep_index {
+        flags |= git2::StashFlags::KEEP_INDEX;
+    }
+
+    match repo.stash_save2(&stasher, Some(message), Some(flags)) {
+        Ok(oid) => Ok(Some(oid)),
+        Err(e) if e.code() == git2::ErrorCode::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+// git stash list，按从新到旧的顺序返回
+pub fn list_stashes(
+    repo: &mut git2::Repository,
+) -> Result<Vec<StashEntry>, Box<dyn std::error::Error>> {
+    let mut entries = Vec::new();
+    repo.stash_foreach(|index, message, oid| {
+        entries.push(StashEntry {
+            index,
+            message: message.to_string(),
+            oid: *oid,
+        });
+        true
+    })?;
+    Ok(entries)
+}
+
+// 应用 stash 时的选项
+#[derive(Debug, Clone, Default)]
+pub struct StashApplyOptions {
+    // 保存时已暂存的修改也恢复到 index 中（git stash apply --index），默认只恢复到工作目录
+    pub restore_index: bool,
+}
+
+// git stash apply [--index] stash@{index}，返回冲突的路径，没有冲突时为空。
+// 与工作目录已有的修改冲突、或恢复 index 时冲突会返回错误，已跟踪的文件保持不变（包含未跟踪文件的
+// stash 可能已经恢复了部分未跟踪文件）；与 HEAD 的变更冲突时会在 index 中留下冲突条目
+pub fn apply_stash(
+    repo: &mut git2::Repository,
+    index: usize,
+    options: &StashApplyOptions,
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let mut apply_options = git2::StashApplyOptions::new();
+    if options.restore_index {
+        apply_options.reinstantiate_index();
+    }
+    match repo.stash_apply(index, Some(&mut apply_options)) {
+        Ok(()) => {}
+        Err(e) if e.code() == git2::ErrorCode::Conflict => {
+            return Err(format!("无法应用 stash@{{{}}}: {}", index, e.message()).into());
+        }
+        Err(e) => return Err(e.into()),
+    }
+
+    index_conflict_paths(&repo.index()?)
+}
+
+// git stash pop [--index] stash@{index}，与 git 一样只在没有冲突时删除该 stash
+pub fn pop_stash(
+    repo: &mut git2::Repository,
+    index: usize,
+    options: &StashApplyOptions,
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let conflicts = apply_stash(repo, index, options)?;
+    if conflicts.is_empty() {
+        drop_stash(repo, index)?;
+    }
+    Ok(conflicts)
+}
+
+// git stash drop stash@{index}
+pub fn drop_stash(
+    repo: &mut git2::Repository,
+    index: usize,
+) -> Result<(), Box<dyn std::error::Error>> {
+    repo.stash_drop(index)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{init_test_repo, write_and_commit};
+    use crate::{
+        add_files_to_git_repo_index, reset_git_repo_head, switch_git_repo_branch,
+        upsert_branch_to_git_repo,
+    };
+    use std::fs;
+    use std::path::Path;
+
+    #[test]
+    fn test_stash_save_list_apply_pop_drop() {
+        let (mut repo, dir) = init_test_repo("stash");
+        write_and_commit(&mut repo, "a.txt", "committed", "init");
+        upsert_branch_to_git_repo(&mut repo, "feature", None).unwrap();
+
+        assert_eq!(
+            save_stash(&mut repo, "nothing", &StashSaveOptions::default()).unwrap(),
+            None
+        );
+
+        fs::write(dir.join("a.txt"), "first change").unwrap();
+        save_stash(&mut repo, "first", &StashSaveOptions::default()).unwrap();
+        assert_eq!(fs::read_to_string(dir.join("a.txt")).unwrap(), "committed");
+
+        fs::write(dir.join("a.txt"), "second change").unwrap();
+        fs::write(dir.join("new.txt"), "untracked").unwrap();
+        let options = StashSaveOptions {
+            include_untracked: true,
+            ..Default::default()
+        };
+        let second_oid = save_stash(&mut repo, "second", &options).unwrap().unwrap();
+        assert!(!dir.join("new.txt").exists());
+
+        let entries = list_stashes(&mut repo).unwrap();
+        let messages: Vec<_> = entries.iter().map(|entry| entry.message.as_str()).collect();
+        assert_eq!(messages, vec!["On main: second", "On main: first"]);
+        assert_eq!(entries[0].oid, second_oid);
+
+        // 暂存后可以安全地切换分支，再把修改恢复到新分支上
+        switch_git_repo_branch(&mut repo, "feature", true).unwrap();
+        // 工作目录中已有冲突的修改时应用失败，stash 保留
+        fs::write(dir.join("a.txt"), "dirty").unwrap();
+        assert!(apply_stash(&mut repo, 1, &StashApplyOptions::default()).is_err());
+        assert_eq!(fs::read_to_string(dir.join("a.txt")).unwrap(), "dirty");
+
+        fs::write(dir.join("a.txt"), "committed").unwrap();
+        assert!(
+            apply_stash(&mut repo, 1, &StashApplyOptions::default())
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(
+            fs::read_to_string(dir.join("a.txt")).unwrap(),
+            "first change"
+        );
+        assert_eq!(list_stashes(&mut repo).unwrap().len(), 2);
+
+        fs::write(dir.join("a.txt"), "committed").unwrap();
+        assert!(
+            pop_stash(&mut repo, 0, &StashApplyOptions::default())
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(
+            fs::read_to_string(dir.join("a.txt")).unwrap(),
+            "second change"
+        );
+        assert_eq!(
+            fs::read_to_string(dir.join("new.txt")).unwrap(),
+            "untracked"
+        );
+        assert_eq!(list_stashes(&mut repo).unwrap().len(), 1);
+
+        drop_stash(&mut repo, 0).unwrap();
+        assert!(list_stashes(&mut repo).unwrap().is_empty());
+        assert!(drop_stash(&mut repo, 0).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_apply_stash_restore_index() {
+        let (mut repo, dir) = init_test_repo("stash_restore_index");
+        let init = write_and_commit(&mut repo, "a.txt", "committed", "init");
+        let staged_entry = |repo: &git2::Repository| {
+            let index = repo.index().unwrap();
+            let entry = index.get_path(Path::new("a.txt"), 0).unwrap();
+            repo.find_blob(entry.id).unwrap().content().to_vec()
+        };
+
+        fs::write(dir.join("a.txt"), "staged").unwrap();
+        add_files_to_git_repo_index(&mut repo, ["a.txt"]).unwrap();
+        save_stash(&mut repo, "staged", &StashSaveOptions::default()).unwrap();
+
+        // 默认与 git stash apply 一致，修改只恢复到工作目录
+        apply_stash(&mut repo, 0, &StashApplyOptions::default()).unwrap();
+        assert_eq!(fs::read_to_string(dir.join("a.txt")).unwrap(), "staged");
+        assert_eq!(staged_entry(&repo), b"committed");
+
+        // --index 时已暂存的修改也回到 index
+        reset_git_repo_head(&mut repo, init).unwrap();
+        let options = StashApplyOptions {
+            restore_index: true,
+        };
+        apply_stash(&mut repo, 0, &options).unwrap();
+        assert_eq!(fs::read_to_string(dir.join("a.txt")).unwrap(), "staged");
+        assert_eq!(staged_entry(&repo), b"staged");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
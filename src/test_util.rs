@@ -0,0 +1,51 @@
+// 单测共用的仓库夹具，lib.rs、remote.rs、stash.rs 的单测都从这里取用
+use crate::{
+    CommitOptions, add_files_to_git_repo_index, commit_index_to_git_repo, config_git_repo_user,
+    open_or_init_git_repo,
+};
+use std::{fs, path::PathBuf};
+
+// 测试目录的路径，带上进程 id 避免并行运行的测试进程互相干扰
+pub(crate) fn test_dir(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("git2_rs_demo_{}_{}", name, std::process::id()))
+}
+
+// 测试目录的路径，并清掉上次运行残留的内容
+pub(crate) fn clean_test_dir(name: &str) -> PathBuf {
+    let dir = test_dir(name);
+    if dir.exists() {
+        fs::remove_dir_all(&dir).unwrap();
+    }
+    dir
+}
+
+// 在系统临时目录下创建一个全新的测试仓库，并配置好用户信息
+pub(crate) fn init_test_repo(name: &str) -> (git2::Repository, PathBuf) {
+    let dir = clean_test_dir(name);
+    let mut repo = open_or_init_git_repo(dir.to_str().unwrap()).unwrap();
+    config_git_repo_user(&mut repo, "TestUser", "test@example.com").unwrap();
+    (repo, dir)
+}
+
+// 在系统临时目录下创建一个全新的裸仓库，常用作 push 的目标
+pub(crate) fn init_bare_test_repo(name: &str) -> (git2::Repository, PathBuf) {
+    let dir = clean_test_dir(name);
+    let repo = git2::Repository::init_bare(&dir).unwrap();
+    (repo, dir)
+}
+
+// 写入文件并提交，返回 commit id
+pub(crate) fn write_and_commit(
+    repo: &mut git2::Repository,
+    file_relative_path: &str,
+    content: &str,
+    message: &str,
+) -> git2::Oid {
+    let file_path = repo.workdir().unwrap().join(file_relative_path);
+    if let Some(parent) = file_path.parent() {
+        fs::create_dir_all(parent).unwrap();
+    }
+    fs::write(&file_path, content).unwrap();
+    let (index, _) = add_files_to_git_repo_index(repo, vec![file_relative_path]).unwrap();
+    commit_index_to_git_repo(repo, index, message, &CommitOptions::default()).unwrap()
+}